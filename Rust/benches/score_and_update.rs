@@ -0,0 +1,70 @@
+//! Compares `score_and_update` against `anomaly_score` followed by `update`
+//! on a stream of normal points, and reports the cost of each half.
+//!
+//! Run with `cargo bench --bench score_and_update`.
+extern crate rand;
+extern crate rand_chacha;
+extern crate random_cut_forest;
+
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use rand_distr::{Distribution, StandardNormal};
+use random_cut_forest::{RandomCutForest, RandomCutForestBuilder};
+
+use std::time::{Duration, Instant};
+
+const DIMENSION: usize = 8;
+const NUM_POINTS: usize = 20000;
+
+fn stream() -> Vec<Vec<f32>> {
+    let mut rng = ChaCha8Rng::seed_from_u64(0);
+    (0..NUM_POINTS)
+        .map(|_| (0..DIMENSION).map(|_| StandardNormal.sample(&mut rng)).collect())
+        .collect()
+}
+
+fn forest() -> RandomCutForest<f32> {
+    let mut forest: RandomCutForest<f32> = RandomCutForestBuilder::new(DIMENSION)
+        .num_trees(50)
+        .sample_size(256)
+        .seed(42)
+        .build();
+    for point in stream() {
+        forest.update(point);
+    }
+    forest
+}
+
+fn time<F: FnMut(&mut RandomCutForest<f32>, Vec<f32>) -> f32>(mut step: F) -> Duration {
+    let mut forest = forest();
+    let points = stream();
+    let mut total = 0.0;
+    let start = Instant::now();
+    for point in points {
+        total += step(&mut forest, point);
+    }
+    let elapsed = start.elapsed();
+    assert!(total > 0.0);
+    elapsed
+}
+
+fn main() {
+    let score = time(|forest, point| forest.anomaly_score(&point));
+    let update = time(|forest, point| {
+        forest.update(point);
+        1.0
+    });
+    let separate = time(|forest, point| {
+        let score = forest.anomaly_score(&point);
+        forest.update(point);
+        score
+    });
+    let fused = time(|forest, point| forest.score_and_update(point).unwrap());
+
+    let per_point = |elapsed: Duration| elapsed.as_nanos() as f64 / NUM_POINTS as f64 / 1000.0;
+    println!("anomaly_score:          {:8.2} us/point", per_point(score));
+    println!("update:                 {:8.2} us/point", per_point(update));
+    println!("anomaly_score + update: {:8.2} us/point", per_point(separate));
+    println!("score_and_update:       {:8.2} us/point", per_point(fused));
+    println!("speedup:                {:8.2}x", separate.as_secs_f64() / fused.as_secs_f64());
+}
@@ -0,0 +1,97 @@
+//! End-to-end anomaly detection on a synthetic stream.
+//!
+//! This example walks through the main scoring APIs of the crate on a seeded,
+//! reproducible stream: a noisy two-dimensional periodic signal with a few
+//! injected spikes and a level shift. For every point it
+//!
+//! * scores and updates the forest in one call with `score_and_update`,
+//! * flags scores above a threshold, with a bootstrap confidence interval
+//!   from `score_with_confidence` to ignore uncertain flags,
+//! * explains each flag with the nearest retained points and the isolation
+//!   forest depth from the same trees,
+//! * feeds the scores to a `ChangePointDetector` to tell the level shift
+//!   apart from the spikes.
+//!
+//! The example checks its own results and runs as a test under `cargo test`,
+//! so a change of behavior of these APIs makes it fail. Run with
+//! `cargo run --example anomaly_detection`.
+//!
+use random_cut_forest::{RandomCutForest, RandomCutForestBuilder};
+use random_cut_forest::common::ChangePointDetector;
+
+const NUM_POINTS: usize = 4000;
+const SPIKES: [usize; 3] = [1200, 2100, 2900];
+const SHIFT_START: usize = 3200;
+const SCORE_THRESHOLD: f32 = 2.0;
+
+/// The value of the stream at time `t`.
+fn signal(t: usize) -> Vec<f32> {
+    let phase = t as f32 * 2.0 * std::f32::consts::PI / 50.0;
+    let noise = ((t * 7919) % 101) as f32 / 500.0 - 0.1;
+    let mut point = vec![phase.sin() + noise, phase.cos() - noise];
+    if SPIKES.contains(&t) {
+        point[0] += 4.0;
+    }
+    if t >= SHIFT_START {
+        point[1] += 2.0;
+    }
+    point
+}
+
+fn main() {
+    let mut forest: RandomCutForest<f32> = RandomCutForestBuilder::new(2)
+        .num_trees(50)
+        .sample_size(256)
+        .time_decay(1.0 / 2560.0)
+        .output_after(256)
+        .seed(7)
+        .build();
+    let mut detector: ChangePointDetector<f32> = ChangePointDetector::new(0.01, 0.5, 20.0, 3.0);
+
+    let mut flagged = Vec::new();
+    let mut changes = Vec::new();
+    for t in 0..NUM_POINTS {
+        let point = signal(t);
+
+        // queries see the forest before it learns the point
+        let (_, lower, _) = forest.score_with_confidence(&point, 0.9);
+        let explanation = match lower > SCORE_THRESHOLD / 2.0 {
+            true => Some((forest.nearest_neighbors(&point, 1), forest.expected_depth(&point))),
+            false => None,
+        };
+        let score = forest.score_and_update(point).expect("valid point");
+
+        if let (true, Some((neighbors, depth))) = (score > SCORE_THRESHOLD, explanation) {
+            println!(
+                "t={:<5} score={:.2} lower bound={:.2} depth={:.2} nearest={:?} at {:.2}",
+                t, score, lower, depth, neighbors[0].point, neighbors[0].distance);
+            flagged.push(t);
+        }
+        if t > forest.output_after() {
+            if let Some(change) = detector.update(score) {
+                // the detector counts its own updates
+                let start = change.index + forest.output_after() + 1;
+                println!("t={:<5} change of score level starting at t={}", t, start);
+                changes.push(start);
+            }
+        }
+    }
+
+    for spike in SPIKES.iter() {
+        assert!(flagged.contains(spike), "spike at t={} was not flagged", spike);
+    }
+    assert!(flagged.contains(&SHIFT_START), "the level shift was not flagged");
+    assert!(changes.iter().all(|&start| !SPIKES.contains(&start)), "a spike was taken for a change");
+    assert!(changes.iter().any(|&start| (SHIFT_START..SHIFT_START + 50).contains(&start)),
+        "the level shift was not detected as a change");
+    println!("flagged {} of {} points", flagged.len(), NUM_POINTS);
+}
+
+#[cfg(test)]
+mod tests {
+    /// Runs the example, and its checks, under `cargo test`.
+    #[test]
+    fn example() {
+        super::main();
+    }
+}
@@ -0,0 +1,77 @@
+//! Channel-based anomaly scoring pipeline.
+//!
+//! This example runs a random cut forest as a streaming operator between two
+//! threads. A producer thread sends a noisy sine wave with a few injected
+//! spikes through a bounded channel, the forest scores and learns each point
+//! on the main thread, and a consumer thread prints the points whose score
+//! exceeds a threshold. Because both channels are bounded, a slow consumer
+//! slows down the whole pipeline instead of letting results pile up.
+//!
+//! The example checks that every spike is flagged and runs as a test under
+//! `cargo test`. Run with `cargo run --example channel_pipeline`.
+//!
+use random_cut_forest::RandomCutForestBuilder;
+use random_cut_forest::operator::{ScoredPoint, ScoringOperator};
+
+use std::sync::mpsc::sync_channel;
+use std::thread;
+
+const NUM_POINTS: usize = 5000;
+const CHANNEL_CAPACITY: usize = 64;
+const SCORE_THRESHOLD: f32 = 2.0;
+const SPIKES: [usize; 3] = [1500, 3000, 4500];
+
+fn main() {
+    let (point_sender, point_receiver) = sync_channel::<Vec<f32>>(CHANNEL_CAPACITY);
+    let (mut result_sender, result_receiver) = sync_channel::<ScoredPoint<f32>>(CHANNEL_CAPACITY);
+
+    let producer = thread::spawn(move || {
+        for i in 0..NUM_POINTS {
+            let phase = i as f32 * 2.0 * std::f32::consts::PI / 100.0;
+            let noise = ((i * 7919) % 101) as f32 / 500.0;
+            let spike = if SPIKES.contains(&i) { 5.0 } else { 0.0 };
+            if point_sender.send(vec![phase.sin() + noise + spike, phase.cos()]).is_err() {
+                break;
+            }
+        }
+    });
+
+    let consumer = thread::spawn(move || {
+        let mut flagged = Vec::new();
+        for scored in result_receiver.iter() {
+            if scored.score > SCORE_THRESHOLD {
+                println!("{}\t{:.3}\t{:?}", scored.index, scored.score, scored.point);
+                flagged.push(scored.index);
+            }
+        }
+        flagged
+    });
+
+    let forest = RandomCutForestBuilder::new(2)
+        .num_trees(50)
+        .sample_size(256)
+        .seed(42)
+        .build();
+    let mut operator = ScoringOperator::new(forest);
+    let result = operator.run(&mut point_receiver.into_iter(), &mut result_sender);
+    drop(result_sender);
+
+    producer.join().expect("producer thread panicked");
+    let flagged = consumer.join().expect("consumer thread panicked");
+    let num_points = result.expect("pipeline stopped");
+    eprintln!("scored {} points, {} above {}", num_points, flagged.len(), SCORE_THRESHOLD);
+
+    assert_eq!(num_points, NUM_POINTS);
+    for spike in SPIKES.iter() {
+        assert!(flagged.contains(spike), "spike at {} was not flagged", spike);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    /// Runs the example, and its checks, under `cargo test`.
+    #[test]
+    fn example() {
+        super::main();
+    }
+}
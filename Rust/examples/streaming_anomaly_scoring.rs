@@ -8,15 +8,17 @@
 //! package to parse the input CSV data to be fed into an RCF model.
 //!
 extern crate clap;
-use clap::{AppSettings, Clap};
+use clap::{Arg, ArgMatches, Command};
 
 extern crate csv;
 
 use random_cut_forest::{RandomCutForest, RandomCutForestBuilder};
 
 use std::error::Error;
+use std::fmt;
 use std::io;
 use std::process;
+use std::str::FromStr;
 
 /// Streaming random cut forest anomaly scoring.
 ///
@@ -25,28 +27,46 @@ use std::process;
 /// automatically ignored. Many data contains a timestamp column in the first
 /// column. The --ignore-first-column flag is useful in this situation.
 ///
-#[derive(Clap)]
-#[clap(setting=AppSettings::ColoredHelp)]
-struct Opts {
-    /// Dimensionality of the input
-    #[clap(short, long)]
-    dimension: usize,
-
-    /// Number of trees used in the model
-    #[clap(short, long, default_value="50")]
-    num_trees: usize,
-
-    /// Number of samples per tree
-    #[clap(short, long, default_value="256")]
-    sample_size: usize,
-
-    /// Parameter for time-decay reservoir sampling
-    #[clap(short, long, default_value="0.000390625")]
-    time_decay: f32,
+fn command() -> Command<'static> {
+    Command::new("streaming_anomaly_scoring")
+        .about("Streaming random cut forest anomaly scoring.")
+        .arg(Arg::new("dimension")
+            .short('d')
+            .long("dimension")
+            .takes_value(true)
+            .required(true)
+            .help("Dimensionality of the input"))
+        .arg(Arg::new("num-trees")
+            .short('n')
+            .long("num-trees")
+            .takes_value(true)
+            .default_value("50")
+            .help("Number of trees used in the model"))
+        .arg(Arg::new("sample-size")
+            .short('s')
+            .long("sample-size")
+            .takes_value(true)
+            .default_value("256")
+            .help("Number of samples per tree"))
+        .arg(Arg::new("time-decay")
+            .short('t')
+            .long("time-decay")
+            .takes_value(true)
+            .default_value("0.000390625")
+            .help("Parameter for time-decay reservoir sampling"))
+        .arg(Arg::new("ignore-first-column")
+            .long("ignore-first-column")
+            .help("Ignore the first column of input. (e.g. timestamps)"))
+}
 
-    /// Ignore the first column of input. (e.g. timestamps)
-    #[clap(long)]
-    ignore_first_column: bool,
+/// Parse the value of an argument, exiting with a usage error if invalid.
+fn parse<V>(matches: &ArgMatches, name: &str) -> V
+    where V: FromStr, V::Err: fmt::Display
+{
+    match matches.value_of_t(name) {
+        Ok(value) => value,
+        Err(err) => err.exit(),
+    }
 }
 
 fn run(rcf: &mut RandomCutForest<f32>, ignore_first_column: bool) -> Result<(), Box<dyn Error>> {
@@ -74,14 +94,14 @@ fn run(rcf: &mut RandomCutForest<f32>, ignore_first_column: bool) -> Result<(),
 }
 
 fn main() {
-    let opts = Opts::parse();
-    let mut rcf: RandomCutForest<f32> = RandomCutForestBuilder::new(opts.dimension)
-        .num_trees(opts.num_trees)
-        .sample_size(opts.sample_size)
-        .time_decay(opts.time_decay)
+    let matches = command().get_matches();
+    let mut rcf: RandomCutForest<f32> = RandomCutForestBuilder::new(parse(&matches, "dimension"))
+        .num_trees(parse(&matches, "num-trees"))
+        .sample_size(parse(&matches, "sample-size"))
+        .time_decay(parse(&matches, "time-decay"))
         .build();
 
-    if let Err(err) = run(&mut rcf, opts.ignore_first_column) {
+    if let Err(err) = run(&mut rcf, matches.is_present("ignore-first-column")) {
         println!("error running example: {}", err);
         process::exit(1);
     }
@@ -0,0 +1,11 @@
+//! Structural invariants of a random cut tree checked by the fuzz targets.
+
+use random_cut_forest::Tree;
+
+/// Checks the structure of the tree with [`Tree::validate`] and returns the
+/// number of leaves.
+pub fn check_tree(tree: &Tree<f32>) -> usize {
+    let violations = tree.validate();
+    assert!(violations.is_empty(), "broken invariants: {:?}", violations);
+    tree.num_points()
+}
@@ -0,0 +1,34 @@
+//! Arbitrary update streams on a sampled tree, where the sampler decides which
+//! points are added to and evicted from the tree.
+#![no_main]
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+
+use random_cut_forest::SampledTree;
+
+#[derive(Arbitrary, Debug)]
+struct Input {
+    seed: u64,
+    sample_size: u8,
+    time_decay: u8,
+    points: Vec<([i8; 2], u8)>,
+}
+
+fuzz_target!(|input: Input| {
+    let sample_size = 1 + (input.sample_size % 32) as usize;
+    let time_decay = input.time_decay as f32 / 64.0;
+    let mut tree: SampledTree<f32> = SampledTree::new(sample_size, time_decay);
+    tree.seed(input.seed);
+
+    let mut sequence_index = 0;
+    for (values, gap) in input.points.iter() {
+        let point: Vec<f32> = values.iter().map(|&v| (v % 4) as f32).collect();
+        sequence_index += *gap as usize;
+        tree.update(point, sequence_index);
+
+        let violations = tree.validate();
+        assert!(violations.is_empty(), "broken invariants: {:?}", violations);
+        let expected_mass = usize::min(tree.num_observations(), sample_size);
+        assert_eq!(tree.tree().mass() as usize, expected_mass);
+    }
+});
@@ -0,0 +1,92 @@
+//! Arbitrary interleavings of point additions, deletions, and scoring on a
+//! single tree, checked against a simple model of the expected point masses.
+#![no_main]
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+
+use random_cut_forest::Tree;
+use random_cut_forest::tree::{CutStrategy, TieBreaking};
+use random_cut_forest::visitor::AnomalyScoreVisitor;
+
+mod invariants;
+
+#[derive(Arbitrary, Debug)]
+enum Operation {
+    Add([i8; 3]),
+    Delete([i8; 3]),
+    DeleteExisting(u8),
+    Score([i8; 3]),
+}
+
+#[derive(Arbitrary, Debug)]
+struct Input {
+    seed: u64,
+    tie_breaking: u8,
+    subsample: u8,
+    operations: Vec<Operation>,
+}
+
+/// Small integer coordinates make duplicates and points on cuts common.
+fn to_point(values: &[i8; 3]) -> Vec<f32> {
+    values.iter().map(|&v| (v % 8) as f32 * 0.5).collect()
+}
+
+fuzz_target!(|input: Input| {
+    let mut tree: Tree<f32> = Tree::new();
+    tree.seed(input.seed);
+    tree.set_tie_breaking(match input.tie_breaking % 3 {
+        0 => TieBreaking::Left,
+        1 => TieBreaking::Right,
+        _ => TieBreaking::Random,
+    });
+    if input.subsample % 4 != 0 {
+        tree.set_cut_strategy(CutStrategy::DimensionSubsample((input.subsample % 4) as usize));
+    }
+
+    // model of the tree contents: distinct points and their masses
+    let mut model: Vec<(Vec<f32>, u32)> = Vec::new();
+
+    for operation in input.operations.iter() {
+        match operation {
+            Operation::Add(values) => {
+                let point = to_point(values);
+                tree.add_point(point.clone());
+                match model.iter_mut().find(|(p, _)| *p == point) {
+                    Some((_, mass)) => *mass += 1,
+                    None => model.push((point, 1)),
+                }
+            },
+            Operation::Delete(values) => {
+                let point = to_point(values);
+                tree.delete_point(&point);
+                if let Some(i) = model.iter().position(|(p, _)| *p == point) {
+                    model[i].1 -= 1;
+                    if model[i].1 == 0 { model.remove(i); }
+                }
+            },
+            Operation::DeleteExisting(index) => {
+                if !model.is_empty() {
+                    let i = *index as usize % model.len();
+                    tree.delete_point(&model[i].0);
+                    model[i].1 -= 1;
+                    if model[i].1 == 0 { model.remove(i); }
+                }
+            },
+            Operation::Score(values) => {
+                if tree.root_node().is_some() {
+                    let point = to_point(values);
+                    let mut visitor = AnomalyScoreVisitor::new(&tree, &point);
+                    let score = tree.traverse(&point, &mut visitor);
+                    assert!(score.is_finite() && score >= 0.0, "invalid score {}", score);
+                }
+            },
+        }
+
+        let num_leaves = invariants::check_tree(&tree);
+        assert_eq!(num_leaves, model.len());
+        assert_eq!(tree.mass(), model.iter().map(|(_, m)| m).sum::<u32>());
+        for (point, mass) in model.iter() {
+            assert_eq!(tree.point_mass(point), *mass);
+        }
+    }
+});
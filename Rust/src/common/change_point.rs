@@ -0,0 +1,263 @@
+extern crate num_traits;
+use num_traits::Float;
+
+use crate::common::Covariance;
+
+/// Direction of a change detected by a [`ChangePointDetector`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ChangeDirection {
+    Increase,
+    Decrease,
+}
+
+/// A change of level detected by a [`ChangePointDetector`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChangePoint {
+    /// Estimated index of the first update after the change.
+    pub index: usize,
+    /// Index of the update at which the change was detected.
+    pub detected_at: usize,
+    pub direction: ChangeDirection,
+}
+
+/// Sequential change-point detector for a stream of values, such as the
+/// anomaly scores of a forest or one of its input dimensions.
+///
+/// A spike and a regime change can have the same anomaly score, but only the
+/// latter persists. This detector runs two one-sided CUSUM statistics, one
+/// per direction, on the deviations of the values from a decayed baseline
+/// mean, measured in baseline standard deviations. Every deviation is
+/// reduced by the allowed `drift` and clipped to `max_step`, so an isolated
+/// spike adds at most `max_step` to a statistic while a level shift, or a
+/// change of trend, accumulates until the statistic exceeds `threshold`.
+/// With `max_step < threshold` no single value can trigger a change.
+///
+/// The baseline follows the values winsorized at `max_step` deviations. A
+/// detected change reports the estimated change index, the first update
+/// since the statistic last left zero, and restarts the baseline. No change
+/// is reported while the baseline spans less than its effective window of
+/// `1 / decay` updates, at the start of the stream and after every change.
+///
+/// # Examples
+///
+/// ```
+/// use random_cut_forest::common::{ChangeDirection, ChangePointDetector};
+///
+/// let mut detector: ChangePointDetector<f64> = ChangePointDetector::new(0.01, 0.5, 8.0, 3.0);
+/// let mut changes = Vec::new();
+/// for i in 0..1000 {
+///     let noise = ((i * 7919) % 13) as f64 / 13.0 - 0.5;
+///     let mut value = 10.0 + noise;
+///     if i == 400 {
+///         // a single spike is not a change
+///         value += 100.0;
+///     }
+///     if i >= 700 {
+///         // a level shift is
+///         value += 3.0;
+///     }
+///     changes.extend(detector.update(value));
+/// }
+///
+/// assert_eq!(changes.len(), 1);
+/// assert_eq!(changes[0].direction, ChangeDirection::Increase);
+/// assert!(changes[0].index >= 700 && changes[0].index <= changes[0].detected_at);
+/// ```
+#[derive(Clone, Debug)]
+pub struct ChangePointDetector<T> {
+    baseline: Covariance<T>,
+    drift: T,
+    threshold: T,
+    max_step: T,
+    increase: T,
+    decrease: T,
+    increase_start: usize,
+    decrease_start: usize,
+    num_updates: usize,
+    baseline_updates: usize,
+}
+
+impl<T> ChangePointDetector<T>
+    where T: Float
+{
+
+    /// Create a change-point detector.
+    ///
+    /// The baseline mean and deviation decay at rate `decay`. The `drift`,
+    /// `threshold` and `max_step` are in units of the baseline standard
+    /// deviation.
+    ///
+    /// # Panics
+    ///
+    /// If `decay` is not in `(0, 1)`, if `drift` is negative, or if
+    /// `threshold` or `max_step` is not positive.
+    pub fn new(decay: T, drift: T, threshold: T, max_step: T) -> Self {
+        assert!(decay > T::zero() && decay < T::one(), "Decay must be in (0, 1)");
+        assert!(drift >= T::zero(), "Drift must be non-negative");
+        assert!(threshold > T::zero(), "Threshold must be positive");
+        assert!(max_step > T::zero(), "Maximum step must be positive");
+        ChangePointDetector {
+            baseline: Covariance::new(1, decay),
+            drift,
+            threshold,
+            max_step,
+            increase: T::zero(),
+            decrease: T::zero(),
+            increase_start: 0,
+            decrease_start: 0,
+            num_updates: 0,
+            baseline_updates: 0,
+        }
+    }
+
+    /// Update the detector with the next value of the stream and return the
+    /// change detected at this value, if any.
+    pub fn update(&mut self, value: T) -> Option<ChangePoint> {
+        let index = self.num_updates;
+        self.num_updates += 1;
+
+        let window = T::one() / self.baseline.decay();
+        if T::from(self.baseline_updates).unwrap() < window {
+            self.baseline.update(&[value]);
+            self.baseline_updates += 1;
+            return None;
+        }
+
+        let step = self.standardized_step(value);
+        // the baseline follows the values winsorized at the maximum step, so
+        // spikes do not inflate it
+        let winsorized = self.mean() + step * self.deviation();
+        self.baseline.update(&[winsorized]);
+
+        if self.increase == T::zero() {
+            self.increase_start = index;
+        }
+        if self.decrease == T::zero() {
+            self.decrease_start = index;
+        }
+        self.increase = Float::max(self.increase + step - self.drift, T::zero());
+        self.decrease = Float::max(self.decrease - step - self.drift, T::zero());
+
+        let direction = match (self.increase > self.threshold, self.decrease > self.threshold) {
+            (true, _) => ChangeDirection::Increase,
+            (false, true) => ChangeDirection::Decrease,
+            (false, false) => return None,
+        };
+
+        let start = match direction {
+            ChangeDirection::Increase => self.increase_start,
+            ChangeDirection::Decrease => self.decrease_start,
+        };
+        self.restart();
+        Some(ChangePoint { index: start, detected_at: index, direction })
+    }
+
+    /// Deviation of a value from the baseline mean in baseline standard
+    /// deviations, clipped to the maximum step.
+    fn standardized_step(&self, value: T) -> T {
+        let deviation = value - self.mean();
+        let scale = self.deviation();
+        let step = match scale > T::zero() {
+            true => deviation / scale,
+            false => deviation.signum() * self.max_step,
+        };
+        match deviation == T::zero() {
+            true => T::zero(),
+            false => Float::max(Float::min(step, self.max_step), -self.max_step),
+        }
+    }
+
+    /// Restart the baseline after a change, so that it learns the new level.
+    fn restart(&mut self) {
+        self.baseline = Covariance::new(1, self.baseline.decay());
+        self.baseline_updates = 0;
+        self.increase = T::zero();
+        self.decrease = T::zero();
+    }
+
+    /// Returns the baseline mean of the stream.
+    pub fn mean(&self) -> T { self.baseline.mean()[0] }
+
+    /// Returns the baseline standard deviation of the stream.
+    pub fn deviation(&self) -> T { self.baseline.covariance(0, 0).sqrt() }
+
+    /// Returns the current values of the CUSUM statistics for an increase
+    /// and a decrease of the level.
+    pub fn statistics(&self) -> (T, T) { (self.increase, self.decrease) }
+
+    /// Returns the number of updates so far.
+    pub fn num_updates(&self) -> usize { self.num_updates }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn noise(i: usize) -> f64 { ((i * 7919) % 13) as f64 / 13.0 - 0.5 }
+
+    #[test]
+    fn level_decrease() {
+        let mut detector: ChangePointDetector<f64> = ChangePointDetector::new(0.01, 0.5, 8.0, 3.0);
+        let changes: Vec<ChangePoint> = (0..1000)
+            .filter_map(|i| detector.update(if i < 500 { 10.0 } else { 7.0 } + noise(i)))
+            .collect();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].direction, ChangeDirection::Decrease);
+        assert!(changes[0].index >= 500 && changes[0].index <= changes[0].detected_at);
+        assert!(changes[0].detected_at < 520);
+
+        // the baseline restarts and learns the new level
+        assert!((detector.mean() - 7.0).abs() < 0.1);
+        assert_eq!(detector.num_updates(), 1000);
+    }
+
+    #[test]
+    fn warm_up() {
+        // a shift within the first window of 100 updates becomes the baseline
+        let mut detector: ChangePointDetector<f64> = ChangePointDetector::new(0.01, 0.5, 8.0, 3.0);
+        for i in 0..1000 {
+            let value = if i < 50 { 10.0 } else { 20.0 } + noise(i);
+            assert_eq!(detector.update(value), None, "update {}", i);
+        }
+        assert!((detector.mean() - 20.0).abs() < 0.1);
+    }
+
+    #[test]
+    fn spikes_are_bounded() {
+        let mut detector: ChangePointDetector<f64> = ChangePointDetector::new(0.01, 0.5, 8.0, 3.0);
+        for i in 0..200 {
+            assert_eq!(detector.update(10.0 + noise(i)), None);
+        }
+
+        // a spike adds the maximum step less the drift
+        let (before, _) = detector.statistics();
+        assert_eq!(detector.update(1e6), None);
+        let (increase, decrease) = detector.statistics();
+        assert!((increase - before - 2.5).abs() < 1e-9, "{} after {}", increase, before);
+        assert_eq!(decrease, 0.0);
+
+        // and isolated spikes are not a change
+        for i in 201..2000 {
+            let value = if i % 50 == 0 { 1e6 } else { 10.0 + noise(i) };
+            assert_eq!(detector.update(value), None, "update {}", i);
+        }
+    }
+
+    #[test]
+    fn constant_stream() {
+        // without deviation, any other value is a full step
+        let mut detector: ChangePointDetector<f64> = ChangePointDetector::new(0.1, 0.0, 2.5, 1.0);
+        for _ in 0..100 {
+            assert_eq!(detector.update(1.0), None);
+        }
+        assert_eq!(detector.statistics(), (0.0, 0.0));
+        assert_eq!(detector.update(1.5), None);
+        assert_eq!(detector.statistics(), (1.0, 0.0));
+    }
+
+    #[test]
+    #[should_panic(expected = "Decay must be in (0, 1)")]
+    fn zero_decay() {
+        ChangePointDetector::<f64>::new(0.0, 0.5, 8.0, 3.0);
+    }
+}
@@ -0,0 +1,279 @@
+extern crate num_traits;
+use num_traits::{Float, One, Zero};
+
+/// Streaming estimator of the mean and covariance of multivariate data.
+///
+/// The estimator keeps an exponentially decayed weighted mean and covariance
+/// of the observed points. Before each update the weight of the previous
+/// observations is multiplied by `1 - decay`, so a `decay` of zero weighs all
+/// observations equally while larger values track recent data. The update
+/// takes `O(d^2)` time and memory for `d`-dimensional points.
+///
+/// Besides diagnostics, such as spotting correlated input dimensions, the
+/// covariance supports Mahalanobis-style normalization of inputs with
+/// [`Covariance::whiten`].
+///
+/// # Examples
+///
+/// ```
+/// use random_cut_forest::common::Covariance;
+///
+/// let mut covariance: Covariance<f64> = Covariance::new(2, 0.0);
+/// covariance.update(&[1.0, 2.0]);
+/// covariance.update(&[2.0, 4.0]);
+/// covariance.update(&[3.0, 6.0]);
+///
+/// assert_eq!(covariance.mean(), &vec![2.0, 4.0]);
+/// assert!((covariance.covariance(0, 1) - 4.0/3.0).abs() < 1e-12);
+/// assert!((covariance.correlation(0, 1) - 1.0).abs() < 1e-12);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Covariance<T> {
+    dimension: usize,
+    decay: T,
+    weight: T,
+    mean: Vec<T>,
+    comoments: Vec<T>,
+}
+
+impl<T> Covariance<T>
+    where T: Float
+{
+
+    /// Create an estimator for `dimension`-dimensional points with the given
+    /// decay.
+    ///
+    /// # Panics
+    ///
+    /// If `decay` is not in `[0, 1)`.
+    pub fn new(dimension: usize, decay: T) -> Self {
+        assert!(decay >= Zero::zero() && decay < One::one(), "Decay must be in [0, 1)");
+        Covariance {
+            dimension,
+            decay,
+            weight: Zero::zero(),
+            mean: vec![Zero::zero(); dimension],
+            comoments: vec![Zero::zero(); dimension * dimension],
+        }
+    }
+
+    /// Update the estimator with a new point.
+    ///
+    /// # Panics
+    ///
+    /// If the dimensionality of the point does not match the dimensionality
+    /// of the estimator.
+    pub fn update(&mut self, point: &[T]) {
+        assert_eq!(point.len(), self.dimension,
+            "Dimension mismatch. Expected {}-dimensional input.",
+            self.dimension);
+
+        let retain = T::one() - self.decay;
+        self.weight = self.weight * retain + One::one();
+        for comoment in self.comoments.iter_mut() {
+            *comoment = *comoment * retain;
+        }
+
+        // weighted version of Welford's algorithm: the co-moments are updated
+        // with the deviations from the means before and after the update
+        let delta: Vec<T> = point.iter().zip(self.mean.iter()).map(|(x, m)| *x - *m).collect();
+        for (mean, d) in self.mean.iter_mut().zip(delta.iter()) {
+            *mean = *mean + *d / self.weight;
+        }
+        let rows = self.comoments.chunks_mut(self.dimension);
+        for (row, d) in rows.zip(delta.iter()) {
+            for ((comoment, x), mean) in row.iter_mut().zip(point.iter()).zip(self.mean.iter()) {
+                *comoment = *comoment + *d * (*x - *mean);
+            }
+        }
+    }
+
+    /// Returns the covariance between dimensions `i` and `j`.
+    ///
+    /// This is the weighted population covariance of the observed points. It
+    /// is zero before any observation.
+    pub fn covariance(&self, i: usize, j: usize) -> T {
+        if self.weight == Zero::zero() {
+            return Zero::zero();
+        }
+        self.comoments[i * self.dimension + j] / self.weight
+    }
+
+    /// Returns the Pearson correlation between dimensions `i` and `j`, or zero
+    /// if either dimension has zero variance.
+    pub fn correlation(&self, i: usize, j: usize) -> T {
+        let scale = (self.covariance(i, i) * self.covariance(j, j)).sqrt();
+        if scale > Zero::zero() {
+            self.covariance(i, j) / scale
+        } else {
+            Zero::zero()
+        }
+    }
+
+    /// Returns the covariance matrix as a vector of rows.
+    pub fn covariance_matrix(&self) -> Vec<Vec<T>> {
+        (0..self.dimension)
+            .map(|i| (0..self.dimension).map(|j| self.covariance(i, j)).collect())
+            .collect()
+    }
+
+    /// Returns the point transformed to have zero mean and identity
+    /// covariance under the current estimate.
+    ///
+    /// If `L` is the Cholesky factor of the covariance matrix, the result is
+    /// `L^{-1} (point - mean)`. Its Euclidean norm is the Mahalanobis distance
+    /// of the point from the mean. Returns `None` if the covariance matrix is
+    /// not positive definite, for example before `dimension + 1` distinct
+    /// observations or when a dimension is constant.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use random_cut_forest::common::Covariance;
+    ///
+    /// let mut covariance: Covariance<f64> = Covariance::new(2, 0.0);
+    /// for point in [[0.0, 0.0], [2.0, 0.0], [0.0, 4.0], [2.0, 4.0]].iter() {
+    ///     covariance.update(point);
+    /// }
+    ///
+    /// // the dimensions are uncorrelated with standard deviations 1 and 2
+    /// let whitened = covariance.whiten(&[3.0, 6.0]).unwrap();
+    /// assert!((whitened[0] - 2.0).abs() < 1e-12);
+    /// assert!((whitened[1] - 2.0).abs() < 1e-12);
+    /// ```
+    pub fn whiten(&self, point: &[T]) -> Option<Vec<T>> {
+        let lower = self.cholesky()?;
+        let n = self.dimension;
+
+        // forward substitution solving L y = point - mean
+        let mut whitened: Vec<T> = Vec::with_capacity(n);
+        for i in 0..n {
+            let mut value = point[i] - self.mean[i];
+            for (j, y) in whitened.iter().enumerate() {
+                value = value - lower[i * n + j] * *y;
+            }
+            whitened.push(value / lower[i * n + i]);
+        }
+        Some(whitened)
+    }
+
+    /// Returns the Mahalanobis distance of the point from the mean, or `None`
+    /// if the covariance matrix is not positive definite.
+    pub fn mahalanobis_distance(&self, point: &[T]) -> Option<T> {
+        self.whiten(point)
+            .map(|whitened| whitened.iter().fold(T::zero(), |sum, y| sum + *y * *y).sqrt())
+    }
+
+    /// Returns the lower triangular Cholesky factor of the covariance matrix
+    /// in row-major order, or `None` if the matrix is not positive definite.
+    fn cholesky(&self) -> Option<Vec<T>> {
+        let n = self.dimension;
+        let mut lower = vec![T::zero(); n * n];
+        for i in 0..n {
+            for j in 0..=i {
+                let mut sum = self.covariance(i, j);
+                for k in 0..j {
+                    sum = sum - lower[i * n + k] * lower[j * n + k];
+                }
+                if i == j {
+                    if sum.is_nan() || sum <= T::zero() {
+                        return None;
+                    }
+                    lower[i * n + i] = sum.sqrt();
+                } else {
+                    lower[i * n + j] = sum / lower[j * n + j];
+                }
+            }
+        }
+        Some(lower)
+    }
+
+    /// Returns the number of bytes allocated on the heap by the estimator.
+    pub fn heap_size(&self) -> usize {
+        (self.mean.capacity() + self.comoments.capacity()) * std::mem::size_of::<T>()
+    }
+
+    /// Return the dimension of the points accepted by the estimator.
+    pub fn dimension(&self) -> usize { self.dimension }
+
+    /// Return the decay of the weight of past observations.
+    pub fn decay(&self) -> T { self.decay }
+
+    /// Return the total weight of the observations.
+    pub fn weight(&self) -> T { self.weight }
+
+    /// Return the weighted mean of the observations.
+    pub fn mean(&self) -> &Vec<T> { &self.mean }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    extern crate rand;
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha8Rng;
+    use rand_distr::StandardNormal;
+
+    #[test]
+    fn batch_covariance() {
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let points: Vec<Vec<f64>> = (0..500)
+            .map(|_| {
+                let z: f64 = rng.sample(StandardNormal);
+                let w: f64 = rng.sample(StandardNormal);
+                let u: f64 = rng.sample(StandardNormal);
+                vec![z, 2.0 * z + w, 3.0 - w + u]
+            })
+            .collect();
+
+        let mut covariance = Covariance::new(3, 0.0);
+        for point in points.iter() {
+            covariance.update(point);
+        }
+
+        let n = points.len() as f64;
+        let mean: Vec<f64> = (0..3).map(|i| points.iter().map(|p| p[i]).sum::<f64>() / n).collect();
+        for i in 0..3 {
+            assert!((covariance.mean()[i] - mean[i]).abs() < 1e-9);
+            for j in 0..3 {
+                let expected = points.iter()
+                    .map(|p| (p[i] - mean[i]) * (p[j] - mean[j]))
+                    .sum::<f64>() / n;
+                assert!((covariance.covariance(i, j) - expected).abs() < 1e-9);
+            }
+        }
+
+        // whitened points have identity sample covariance
+        let mut whitened = Covariance::new(3, 0.0);
+        for point in points.iter() {
+            whitened.update(&covariance.whiten(point).unwrap());
+        }
+        for i in 0..3 {
+            for j in 0..3 {
+                let expected = if i == j { 1.0 } else { 0.0 };
+                assert!((whitened.covariance(i, j) - expected).abs() < 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn decay() {
+        // the correlation changes sign halfway through the stream. a decayed
+        // estimator follows the change while an undecayed one averages it out
+        let mut rng = ChaCha8Rng::seed_from_u64(1);
+        let mut decayed = Covariance::new(2, 0.01);
+        let mut undecayed = Covariance::new(2, 0.0);
+        for t in 0..2000 {
+            let z: f64 = rng.sample(StandardNormal);
+            let sign = if t < 1000 { 1.0 } else { -1.0 };
+            decayed.update(&[z, sign * z]);
+            undecayed.update(&[z, sign * z]);
+        }
+
+        assert!(decayed.correlation(0, 1) < -0.99);
+        assert!(undecayed.correlation(0, 1).abs() < 0.2);
+        assert!((decayed.weight() - 100.0).abs() < 1.0);
+    }
+}
@@ -0,0 +1,142 @@
+use std::convert::TryFrom;
+use std::time::Duration;
+
+/// Number of bits of the sub-buckets of each power of two. Values are
+/// recorded with a relative error of at most `2^(1 - SUB_BUCKET_BITS)`.
+const SUB_BUCKET_BITS: u32 = 7;
+const SUB_BUCKET_COUNT: u64 = 1 << SUB_BUCKET_BITS;
+const SUB_BUCKET_HALF: u64 = SUB_BUCKET_COUNT / 2;
+
+/// Histogram of durations with logarithmic buckets, in the style of an HDR
+/// histogram.
+///
+/// Durations are recorded in nanoseconds. Values below 128 nanoseconds are
+/// exact, and every larger power of two is split into 64 linear buckets, so
+/// a recorded value is known within 1.6% at any scale. Buckets are
+/// allocated up to the largest value recorded, which takes a few kilobytes
+/// for durations up to seconds. Percentiles report the upper end of their
+/// bucket, capped at the largest value recorded.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use random_cut_forest::common::LatencyHistogram;
+///
+/// let mut histogram = LatencyHistogram::new();
+/// for micros in 1..=1000 {
+///     histogram.record(Duration::from_micros(micros));
+/// }
+///
+/// assert_eq!(histogram.count(), 1000);
+/// assert_eq!(histogram.max(), Some(Duration::from_micros(1000)));
+/// let median = histogram.value_at_percentile(50.0).unwrap();
+/// assert!(median >= Duration::from_micros(500) && median <= Duration::from_micros(508));
+/// ```
+#[derive(Clone, Debug)]
+pub struct LatencyHistogram {
+    counts: Vec<u64>,
+    count: u64,
+    min: u64,
+    max: u64,
+}
+
+impl LatencyHistogram {
+
+    /// Create an empty histogram.
+    pub fn new() -> Self {
+        LatencyHistogram { counts: Vec::new(), count: 0, min: u64::MAX, max: 0 }
+    }
+
+    /// Record a duration, saturating at `u64::MAX` nanoseconds.
+    pub fn record(&mut self, duration: Duration) {
+        let value = u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX);
+        let index = bucket_index(value);
+        if index >= self.counts.len() {
+            self.counts.resize(index + 1, 0);
+        }
+        self.counts[index] += 1;
+        self.count += 1;
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+    }
+
+    /// Returns the duration below or at which the given percentage of the
+    /// recorded durations lie, or `None` if the histogram is empty.
+    ///
+    /// # Panics
+    ///
+    /// If `percentile` is not in `[0, 100]`.
+    pub fn value_at_percentile(&self, percentile: f64) -> Option<Duration> {
+        assert!((0.0..=100.0).contains(&percentile), "Percentile must be in [0, 100]");
+        if self.count == 0 {
+            return None;
+        }
+
+        let rank = ((percentile / 100.0 * self.count as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (index, count) in self.counts.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                let value = bucket_upper_bound(index).min(self.max).max(self.min);
+                return Some(Duration::from_nanos(value));
+            }
+        }
+        self.max()
+    }
+
+    /// Returns the number of recorded durations.
+    pub fn count(&self) -> u64 { self.count }
+
+    /// Returns the smallest recorded duration, or `None` if the histogram is
+    /// empty.
+    pub fn min(&self) -> Option<Duration> {
+        match self.count {
+            0 => None,
+            _ => Some(Duration::from_nanos(self.min)),
+        }
+    }
+
+    /// Returns the largest recorded duration, or `None` if the histogram is
+    /// empty.
+    pub fn max(&self) -> Option<Duration> {
+        match self.count {
+            0 => None,
+            _ => Some(Duration::from_nanos(self.max)),
+        }
+    }
+
+    /// Returns the number of bytes allocated on the heap by the histogram.
+    pub fn heap_size(&self) -> usize { self.counts.capacity() * std::mem::size_of::<u64>() }
+
+    /// Remove every recorded duration.
+    pub fn reset(&mut self) {
+        *self = LatencyHistogram::new();
+    }
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self { LatencyHistogram::new() }
+}
+
+/// Index of the bucket of a value in nanoseconds.
+fn bucket_index(value: u64) -> usize {
+    if value < SUB_BUCKET_COUNT {
+        return value as usize;
+    }
+    let shift = (63 - value.leading_zeros()) + 1 - SUB_BUCKET_BITS;
+    let sub_bucket = (value >> shift) - SUB_BUCKET_HALF;
+    (SUB_BUCKET_COUNT + (shift as u64 - 1) * SUB_BUCKET_HALF + sub_bucket) as usize
+}
+
+/// Largest value in nanoseconds of the bucket with the given index.
+fn bucket_upper_bound(index: usize) -> u64 {
+    let index = index as u64;
+    if index < SUB_BUCKET_COUNT {
+        return index;
+    }
+    let offset = index - SUB_BUCKET_COUNT;
+    let shift = offset / SUB_BUCKET_HALF + 1;
+    let lower = (offset % SUB_BUCKET_HALF + SUB_BUCKET_HALF) << shift;
+    lower + ((1 << shift) - 1)
+}
@@ -0,0 +1,23 @@
+//! Submodule containing streaming statistics shared by the forest and its
+//! users.
+//!
+mod covariance;
+pub use covariance::Covariance;
+
+mod sliding_window;
+pub use sliding_window::SlidingWindowScore;
+
+mod score_correlation;
+pub use score_correlation::ScoreCorrelation;
+
+mod change_point;
+pub use change_point::{ChangeDirection, ChangePoint, ChangePointDetector};
+
+mod latency;
+pub use latency::LatencyHistogram;
+
+mod z_score_filter;
+pub use z_score_filter::{FilterDecision, ZScoreFilter};
+
+mod update_governor;
+pub use update_governor::UpdateGovernor;
@@ -0,0 +1,199 @@
+extern crate num_traits;
+use num_traits::Float;
+
+use crate::common::Covariance;
+
+/// Divergence detector for the anomaly scores of related streams.
+///
+/// Streams that observe the same system, such as the hosts of a fleet, often
+/// have correlated anomaly scores. A stream that stops following its group
+/// can be anomalous even when none of its own scores is. This type keeps two
+/// decayed estimates of the pairwise correlations of the scores of a fixed
+/// set of streams, see [`Covariance`]: a slow baseline and a fast recent
+/// estimate. A pair of streams has diverged when its baseline correlation is
+/// at least `min_correlation` and its recent correlation has dropped by more
+/// than `max_drop` below it. A lasting divergence is gradually absorbed by
+/// the baseline, at a rate set by `baseline_decay`, after which the pair is
+/// no longer flagged.
+///
+/// Every update takes one score per stream, for example the scores of one
+/// forest per stream at the same time step. No pair is flagged before the
+/// recent estimate spans its effective window of `1 / recent_decay` updates.
+///
+/// # Examples
+///
+/// ```
+/// use random_cut_forest::common::ScoreCorrelation;
+///
+/// let mut correlation: ScoreCorrelation<f64> = ScoreCorrelation::new(3, 0.001, 0.05, 0.8, 0.5);
+///
+/// // three streams following a common signal
+/// for i in 0..1000 {
+///     let signal = (i as f64 * 0.3).sin();
+///     let diverged = correlation.update(&[signal, 2.0 * signal, signal + 1.0]);
+///     assert!(diverged.is_empty());
+/// }
+///
+/// // the last stream stops following the others
+/// let mut diverged = Vec::new();
+/// for i in 1000..1050 {
+///     let signal = (i as f64 * 0.3).sin();
+///     diverged = correlation.update(&[signal, 2.0 * signal, (i as f64 * 1.7).cos()]);
+/// }
+/// assert_eq!(diverged, vec![(0, 2), (1, 2)]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct ScoreCorrelation<T> {
+    baseline: Covariance<T>,
+    recent: Covariance<T>,
+    min_correlation: T,
+    max_drop: T,
+    num_updates: usize,
+}
+
+impl<T> ScoreCorrelation<T>
+    where T: Float
+{
+
+    /// Create a divergence detector for the given number of streams.
+    ///
+    /// # Panics
+    ///
+    /// If `baseline_decay` is not in `[0, 1)`, or if `recent_decay` is not
+    /// larger than `baseline_decay` and smaller than one.
+    pub fn new(num_streams: usize, baseline_decay: T, recent_decay: T, min_correlation: T, max_drop: T) -> Self {
+        assert!(baseline_decay >= T::zero() && baseline_decay < T::one(),
+            "Baseline decay must be in [0, 1)");
+        assert!(recent_decay > baseline_decay && recent_decay < T::one(),
+            "Recent decay must be larger than the baseline decay and smaller than one");
+        ScoreCorrelation {
+            baseline: Covariance::new(num_streams, baseline_decay),
+            recent: Covariance::new(num_streams, recent_decay),
+            min_correlation,
+            max_drop,
+            num_updates: 0,
+        }
+    }
+
+    /// Update the correlations with one score per stream and return the
+    /// pairs of streams that have diverged, see
+    /// [`diverged_pairs`](Self::diverged_pairs).
+    ///
+    /// # Panics
+    ///
+    /// If the number of scores does not match the number of streams.
+    pub fn update(&mut self, scores: &[T]) -> Vec<(usize, usize)> {
+        self.baseline.update(scores);
+        self.recent.update(scores);
+        self.num_updates += 1;
+        self.diverged_pairs()
+    }
+
+    /// Returns the pairs `(i, j)`, with `i < j`, of streams whose baseline
+    /// correlation is at least the minimum correlation and whose recent
+    /// correlation is more than the maximum drop below it.
+    pub fn diverged_pairs(&self) -> Vec<(usize, usize)> {
+        let window = T::one() / self.recent.decay();
+        if T::from(self.num_updates).unwrap() < window {
+            return Vec::new();
+        }
+
+        let num_streams = self.num_streams();
+        let mut pairs = Vec::new();
+        for i in 0..num_streams {
+            for j in (i + 1)..num_streams {
+                let baseline = self.baseline_correlation(i, j);
+                if baseline >= self.min_correlation &&
+                    self.recent_correlation(i, j) < baseline - self.max_drop
+                {
+                    pairs.push((i, j));
+                }
+            }
+        }
+        pairs
+    }
+
+    /// Returns the baseline correlation of the scores of streams `i` and `j`.
+    pub fn baseline_correlation(&self, i: usize, j: usize) -> T { self.baseline.correlation(i, j) }
+
+    /// Returns the recent correlation of the scores of streams `i` and `j`.
+    pub fn recent_correlation(&self, i: usize, j: usize) -> T { self.recent.correlation(i, j) }
+
+    /// Returns the number of streams.
+    pub fn num_streams(&self) -> usize { self.baseline.dimension() }
+
+    /// Returns the number of updates so far.
+    pub fn num_updates(&self) -> usize { self.num_updates }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha8Rng;
+    use rand_distr::StandardNormal;
+
+    #[test]
+    fn linear_streams() {
+        let mut correlation: ScoreCorrelation<f64> = ScoreCorrelation::new(3, 0.0, 0.1, 0.8, 0.5);
+        for i in 0..100 {
+            let signal = (i as f64 * 0.3).sin();
+            assert!(correlation.update(&[signal, 3.0 * signal + 1.0, -signal]).is_empty());
+        }
+        assert_eq!(correlation.num_streams(), 3);
+        assert_eq!(correlation.num_updates(), 100);
+        assert!((correlation.baseline_correlation(0, 1) - 1.0).abs() < 1e-9);
+        assert!((correlation.recent_correlation(0, 1) - 1.0).abs() < 1e-9);
+        assert!((correlation.baseline_correlation(0, 2) + 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn uncorrelated_streams() {
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let mut correlation: ScoreCorrelation<f64> = ScoreCorrelation::new(2, 0.001, 0.05, 0.8, 0.5);
+        for _ in 0..1000 {
+            let scores = [rng.sample(StandardNormal), rng.sample(StandardNormal)];
+            assert!(correlation.update(&scores).is_empty());
+        }
+        assert!(correlation.baseline_correlation(0, 1).abs() < 0.2);
+    }
+
+    #[test]
+    fn warm_up() {
+        // thresholds that flag every pair, once the recent window of twenty
+        // updates is spanned
+        let mut rng = ChaCha8Rng::seed_from_u64(1);
+        let mut correlation: ScoreCorrelation<f64> = ScoreCorrelation::new(2, 0.01, 0.05, -1.0, -3.0);
+        for i in 0..40 {
+            let scores = [rng.sample(StandardNormal), rng.sample(StandardNormal)];
+            assert_eq!(correlation.update(&scores).is_empty(), i < 19, "update {}", i);
+        }
+    }
+
+    #[test]
+    fn divergence_is_absorbed() {
+        let mut correlation: ScoreCorrelation<f64> = ScoreCorrelation::new(2, 0.01, 0.1, 0.8, 0.5);
+        for i in 0..500 {
+            let signal = (i as f64 * 0.3).sin();
+            assert!(correlation.update(&[signal, signal]).is_empty());
+        }
+
+        // a lasting divergence is flagged, then becomes the baseline
+        let flagged: Vec<usize> = (500..2000)
+            .filter(|&i| {
+                let signal = (i as f64 * 0.3).sin();
+                !correlation.update(&[signal, (i as f64 * 1.7).cos()]).is_empty()
+            })
+            .collect();
+        assert!(flagged.first().is_some_and(|&i| i < 530), "{:?}", flagged.first());
+        assert!(flagged.last().is_some_and(|&i| i < 1500), "{:?}", flagged.last());
+        assert!(correlation.diverged_pairs().is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "Recent decay must be larger than the baseline decay")]
+    fn recent_decay_below_baseline() {
+        ScoreCorrelation::<f64>::new(2, 0.1, 0.01, 0.8, 0.5);
+    }
+}
@@ -0,0 +1,197 @@
+extern crate num_traits;
+use num_traits::{Float, Zero};
+
+use std::collections::VecDeque;
+
+use crate::common::Covariance;
+
+/// Collective anomaly score over a sliding window of scores.
+///
+/// Single-point anomaly scores miss streams that are slightly off for a long
+/// time: each score is unremarkable while their average is not. This type
+/// keeps the mean of the last `window_size` scores, updated in constant time,
+/// and compares it with a baseline of the scores that have left the window.
+/// The baseline is a decayed mean and variance of these older scores, so it
+/// follows slow drifts without absorbing the window being tested.
+///
+/// A window is flagged when its mean exceeds the baseline mean by more than
+/// `threshold` standard errors, that is, `threshold` baseline standard
+/// deviations divided by the square root of the window size. No window is
+/// flagged until at least `window_size` scores have left the window.
+///
+/// # Examples
+///
+/// ```
+/// use random_cut_forest::common::SlidingWindowScore;
+///
+/// let mut window: SlidingWindowScore<f32> = SlidingWindowScore::new(50, 3.0, 0.001);
+///
+/// // alternating scores with mean 1.0 and standard deviation 0.1
+/// for i in 0..1000 {
+///     let score = if i % 2 == 0 { 0.9 } else { 1.1 };
+///     assert!(!window.update(score));
+/// }
+///
+/// // a persistent shift of 0.1 is never flagged by single scores, since
+/// // each score is within one standard deviation of the mean
+/// let mut flagged = false;
+/// for i in 0..50 {
+///     let score = if i % 2 == 0 { 1.0 } else { 1.2 };
+///     flagged |= window.update(score);
+/// }
+/// assert!(flagged);
+/// ```
+#[derive(Clone, Debug)]
+pub struct SlidingWindowScore<T> {
+    window_size: usize,
+    threshold: T,
+    window: VecDeque<T>,
+    window_sum: T,
+    baseline: Covariance<T>,
+}
+
+impl<T> SlidingWindowScore<T>
+    where T: Float
+{
+
+    /// Create a sliding window of the given size and flagging threshold. The
+    /// baseline decays the weight of past scores by `baseline_decay` per
+    /// score, see [`Covariance`].
+    ///
+    /// # Panics
+    ///
+    /// If `window_size` is zero, `threshold` is negative, or `baseline_decay`
+    /// is not in `[0, 1)`.
+    pub fn new(window_size: usize, threshold: T, baseline_decay: T) -> Self {
+        assert!(window_size > 0, "Window size must be positive");
+        assert!(threshold >= Zero::zero(), "Threshold must be non-negative");
+        SlidingWindowScore {
+            window_size,
+            threshold,
+            window: VecDeque::with_capacity(window_size + 1),
+            window_sum: Zero::zero(),
+            baseline: Covariance::new(1, baseline_decay),
+        }
+    }
+
+    /// Add a score to the window and return true if the window is flagged.
+    ///
+    /// The oldest score is moved from the window to the baseline once the
+    /// window is full.
+    pub fn update(&mut self, score: T) -> bool {
+        self.window.push_back(score);
+        self.window_sum = self.window_sum + score;
+        if self.window.len() > self.window_size {
+            let oldest = self.window.pop_front().unwrap();
+            self.window_sum = self.window_sum - oldest;
+            self.baseline.update(&[oldest]);
+        }
+        self.is_flagged()
+    }
+
+    /// Returns true if the mean of the current window deviates from the
+    /// baseline by more than the threshold.
+    pub fn is_flagged(&self) -> bool {
+        match self.deviation() {
+            Some(deviation) => deviation > self.threshold,
+            None => false,
+        }
+    }
+
+    /// Returns the difference between the window mean and the baseline mean
+    /// in standard errors of the window mean, or `None` until the baseline has
+    /// seen `window_size` scores or if its variance is zero.
+    pub fn deviation(&self) -> Option<T> {
+        if self.baseline.weight() < T::from(self.window_size).unwrap() {
+            return None;
+        }
+
+        let variance = self.baseline.covariance(0, 0);
+        if variance <= Zero::zero() {
+            return None;
+        }
+        let standard_error = (variance / T::from(self.window_size).unwrap()).sqrt();
+        Some((self.window_mean() - self.baseline_mean()) / standard_error)
+    }
+
+    /// Return the mean of the scores in the window.
+    pub fn window_mean(&self) -> T {
+        if self.window.is_empty() {
+            return Zero::zero();
+        }
+        self.window_sum / T::from(self.window.len()).unwrap()
+    }
+
+    /// Return the mean of the scores that have left the window.
+    pub fn baseline_mean(&self) -> T { self.baseline.mean()[0] }
+
+    /// Return the number of scores in a full window.
+    pub fn window_size(&self) -> usize { self.window_size }
+
+    /// Return the number of standard errors above which a window is flagged.
+    pub fn threshold(&self) -> T { self.threshold }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha8Rng;
+
+    #[test]
+    fn window_and_baseline_means() {
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let scores: Vec<f64> = (0..300).map(|_| rng.gen::<f64>()).collect();
+        let mut window: SlidingWindowScore<f64> = SlidingWindowScore::new(20, 3.0, 0.0);
+        for (i, score) in scores.iter().enumerate() {
+            window.update(*score);
+            let start = (i + 1).saturating_sub(20);
+            let window_mean = scores[start..=i].iter().sum::<f64>() / (i + 1 - start) as f64;
+            assert!((window.window_mean() - window_mean).abs() < 1e-9);
+            if start > 0 {
+                let baseline_mean = scores[..start].iter().sum::<f64>() / start as f64;
+                assert!((window.baseline_mean() - baseline_mean).abs() < 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn warm_up() {
+        // scores that would be flagged as soon as the baseline is ready
+        let mut window: SlidingWindowScore<f64> = SlidingWindowScore::new(10, 0.0, 0.0);
+        for i in 0..30 {
+            let score = if i % 2 == 0 { 0.0 } else { 1.0 } + i as f64;
+            assert_eq!(window.update(score), i >= 19, "update {}", i);
+            assert_eq!(window.deviation().is_some(), i >= 19);
+        }
+    }
+
+    #[test]
+    fn constant_scores() {
+        let mut window: SlidingWindowScore<f64> = SlidingWindowScore::new(10, 3.0, 0.01);
+        for _ in 0..100 {
+            assert!(!window.update(1.0));
+        }
+        assert_eq!(window.deviation(), None);
+        assert!(!window.update(100.0));
+    }
+
+    #[test]
+    fn decrease_is_not_flagged() {
+        let mut window: SlidingWindowScore<f64> = SlidingWindowScore::new(50, 3.0, 0.001);
+        for i in 0..1000 {
+            assert!(!window.update(if i % 2 == 0 { 0.9 } else { 1.1 }));
+        }
+        for i in 0..50 {
+            assert!(!window.update(if i % 2 == 0 { 0.6 } else { 0.8 }));
+        }
+        assert!(window.deviation().unwrap() < -3.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Window size must be positive")]
+    fn empty_window() {
+        SlidingWindowScore::<f64>::new(0, 3.0, 0.0);
+    }
+}
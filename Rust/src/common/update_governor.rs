@@ -0,0 +1,130 @@
+extern crate rand;
+use rand::Rng;
+
+use std::time::{Duration, Instant};
+
+/// Probabilistic limit on the rate of updates, shedding updates under
+/// bursts of traffic instead of blocking.
+///
+/// The governor counts arrivals in consecutive windows of a fixed duration
+/// and estimates the arrival rate as the larger of the counts of the
+/// current and previous windows. While the estimate is within
+/// `max_updates` per window, every update is admitted. Above it, an update
+/// is admitted with probability `p = max_updates / estimate` and stands for
+/// `1 / p` arrivals: its multiplicity, which a weighted sampler uses to keep
+/// every arrival equally likely to be sampled in expectation. Once a burst
+/// has lasted a full window, about `max_updates` updates are admitted per
+/// window; during its first window, the count of the current window lets
+/// the governor react within a logarithmic factor of the limit.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::{Duration, Instant};
+/// use rand::SeedableRng;
+/// use rand_chacha::ChaCha8Rng;
+/// use random_cut_forest::common::UpdateGovernor;
+///
+/// let mut governor = UpdateGovernor::new(100, Duration::from_secs(1));
+/// let mut rng = ChaCha8Rng::seed_from_u64(0);
+/// let start = Instant::now();
+///
+/// // a quiet second admits everything
+/// for i in 0..50 {
+///     let now = start + Duration::from_millis(20 * i);
+///     assert_eq!(governor.admit(now, &mut rng), Some(1.0));
+/// }
+///
+/// // a burst of 20000 updates per second: once it has lasted a full second,
+/// // one update in 200 is admitted and stands for 200 arrivals
+/// for i in 0..20000 {
+///     governor.admit(start + Duration::from_secs(1) + Duration::from_micros(50 * i), &mut rng);
+/// }
+/// let admitted = (0..20000)
+///     .filter_map(|i| governor.admit(start + Duration::from_secs(2) + Duration::from_micros(50 * i), &mut rng))
+///     .collect::<Vec<f64>>();
+/// assert!(admitted.len() > 70 && admitted.len() < 130);
+/// assert!(admitted.iter().all(|&multiplicity| (multiplicity - 200.0).abs() < 1e-9));
+/// ```
+#[derive(Clone, Debug)]
+pub struct UpdateGovernor {
+    max_updates: usize,
+    window: Duration,
+    window_start: Option<Instant>,
+    num_arrivals: usize,
+    num_previous_arrivals: usize,
+    num_admitted: usize,
+    num_shed: usize,
+}
+
+impl UpdateGovernor {
+
+    /// Create a governor admitting about `max_updates` updates per window
+    /// of the given duration.
+    ///
+    /// # Panics
+    ///
+    /// If `max_updates` is zero or `window` is empty.
+    pub fn new(max_updates: usize, window: Duration) -> Self {
+        assert!(max_updates > 0, "Maximum number of updates must be positive");
+        assert!(window > Duration::from_secs(0), "Window must not be empty");
+        UpdateGovernor {
+            max_updates,
+            window,
+            window_start: None,
+            num_arrivals: 0,
+            num_previous_arrivals: 0,
+            num_admitted: 0,
+            num_shed: 0,
+        }
+    }
+
+    /// Record an arrival at time `now` and decide whether to admit the
+    /// update. Returns the multiplicity of an admitted update, one unless
+    /// updates are shed, or `None` if the update is shed.
+    ///
+    /// Random numbers are only drawn while updates are shed.
+    pub fn admit<R: Rng + ?Sized>(&mut self, now: Instant, rng: &mut R) -> Option<f64> {
+        let window_start = *self.window_start.get_or_insert(now);
+        let elapsed = now.saturating_duration_since(window_start);
+        if elapsed >= self.window {
+            // the previous window only counts if it directly precedes this one
+            self.num_previous_arrivals = match elapsed < 2 * self.window {
+                true => self.num_arrivals,
+                false => 0,
+            };
+            self.num_arrivals = 0;
+            self.window_start = Some(now);
+        }
+        self.num_arrivals += 1;
+
+        let estimate = self.num_arrivals.max(self.num_previous_arrivals);
+        if estimate <= self.max_updates {
+            self.num_admitted += 1;
+            return Some(1.0);
+        }
+        let probability = self.max_updates as f64 / estimate as f64;
+        match rng.gen::<f64>() < probability {
+            true => {
+                self.num_admitted += 1;
+                Some(1.0 / probability)
+            },
+            false => {
+                self.num_shed += 1;
+                None
+            },
+        }
+    }
+
+    /// Returns the largest number of updates admitted per window.
+    pub fn max_updates(&self) -> usize { self.max_updates }
+
+    /// Returns the duration of a window.
+    pub fn window(&self) -> Duration { self.window }
+
+    /// Returns the number of updates admitted.
+    pub fn num_admitted(&self) -> usize { self.num_admitted }
+
+    /// Returns the number of updates shed.
+    pub fn num_shed(&self) -> usize { self.num_shed }
+}
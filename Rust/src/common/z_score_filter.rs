@@ -0,0 +1,188 @@
+extern crate num_traits;
+use num_traits::{Float, One, Zero};
+
+/// Total weight of observations below which the filter scores every point.
+const MIN_WEIGHT: f64 = 32.0;
+
+/// One in this many points that would be skipped is audited instead.
+const AUDIT_INTERVAL: usize = 16;
+
+/// Relative step of the adjustments of the z-score bound after an audit.
+const STEP: f64 = 0.05;
+
+/// Decision of a [`ZScoreFilter`] on a point.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FilterDecision {
+    /// The point is outside the bound and must be scored.
+    Score,
+    /// The point is inside the bound and can be skipped.
+    Skip,
+    /// The point is inside the bound but should be scored anyway, and the
+    /// outcome reported with [`ZScoreFilter::record_audit`].
+    Audit,
+}
+
+/// Cheap pre-filter of clearly normal points, based on per-dimension
+/// z-scores.
+///
+/// The filter keeps an exponentially decayed mean and variance of each
+/// dimension of the observed points, as [`Covariance`](super::Covariance)
+/// does without the cross terms. A point whose coordinates all lie within
+/// `z_bound` standard deviations of the mean is considered normal and can
+/// skip full scoring. Until the observations have a total weight of 32,
+/// every point is scored.
+///
+/// Skipping points risks false negatives: anomalous points that are close
+/// to the mean in every dimension. To keep them within a budget, one in 16
+/// skipped points is audited instead, that is scored in full, and the
+/// outcome reported to the filter. Every false negative shrinks the bound
+/// by 5% and every audited normal point widens it by a fraction of that,
+/// up to the initial bound, so that the rate of false negatives among
+/// audited points settles at the budget.
+///
+/// # Examples
+///
+/// ```
+/// use random_cut_forest::common::{FilterDecision, ZScoreFilter};
+///
+/// let mut filter: ZScoreFilter<f64> = ZScoreFilter::new(1, 3.0, 0.01, 0.0);
+/// for i in 0..100 {
+///     filter.update(&[(i % 10) as f64]);
+/// }
+///
+/// assert_eq!(filter.decide(&[100.0], None), FilterDecision::Score);
+/// let decisions: Vec<FilterDecision> = (0..16).map(|_| filter.decide(&[5.0], None)).collect();
+/// assert_eq!(decisions.iter().filter(|&&d| d == FilterDecision::Skip).count(), 15);
+/// assert_eq!(decisions[15], FilterDecision::Audit);
+///
+/// // the audited point turned out to be anomalous
+/// filter.record_audit(true);
+/// assert!(filter.z_bound() < 3.0);
+/// ```
+#[derive(Clone, Debug)]
+pub struct ZScoreFilter<T> {
+    decay: T,
+    weight: T,
+    mean: Vec<T>,
+    moments: Vec<T>,
+    z_bound: T,
+    max_z_bound: T,
+    false_negative_budget: f64,
+    num_skipped: usize,
+    num_audited: usize,
+    num_false_negatives: usize,
+}
+
+impl<T> ZScoreFilter<T>
+    where T: Float
+{
+
+    /// Create a filter for `dimension`-dimensional points with the given
+    /// initial z-score bound, budget of false negatives and decay.
+    ///
+    /// # Panics
+    ///
+    /// If `z_bound` is not positive and finite, `false_negative_budget` is
+    /// not in `(0, 1)` or `decay` is not in `[0, 1)`.
+    pub fn new(dimension: usize, z_bound: T, false_negative_budget: f64, decay: T) -> Self {
+        assert!(z_bound > Zero::zero() && z_bound.is_finite(), "Z-score bound must be positive and finite");
+        assert!(false_negative_budget > 0.0 && false_negative_budget < 1.0,
+            "False negative budget must be in (0, 1)");
+        assert!(decay >= Zero::zero() && decay < One::one(), "Decay must be in [0, 1)");
+        ZScoreFilter {
+            decay,
+            weight: Zero::zero(),
+            mean: vec![Zero::zero(); dimension],
+            moments: vec![Zero::zero(); dimension],
+            z_bound,
+            max_z_bound: z_bound,
+            false_negative_budget,
+            num_skipped: 0,
+            num_audited: 0,
+            num_false_negatives: 0,
+        }
+    }
+
+    /// Update the mean and variance of each dimension with a new point.
+    ///
+    /// # Panics
+    ///
+    /// If the dimensionality of the point does not match the dimensionality
+    /// of the filter.
+    pub fn update(&mut self, point: &[T]) {
+        assert_eq!(point.len(), self.mean.len(),
+            "Dimension mismatch. Expected {}-dimensional input.",
+            self.mean.len());
+
+        let retain = T::one() - self.decay;
+        self.weight = self.weight * retain + One::one();
+        for ((mean, moment), &x) in self.mean.iter_mut().zip(self.moments.iter_mut()).zip(point.iter()) {
+            let delta = x - *mean;
+            *mean = *mean + delta / self.weight;
+            *moment = *moment * retain + delta * (x - *mean);
+        }
+    }
+
+    /// Decide whether a point must be scored.
+    ///
+    /// Dimension `i` is ignored if `masked_dimensions[i]` is true. A point
+    /// is inside the bound if every other coordinate is within `z_bound`
+    /// standard deviations of its mean; a coordinate of a constant dimension
+    /// must equal the mean.
+    pub fn decide(&mut self, point: &[T], masked_dimensions: Option<&[bool]>) -> FilterDecision {
+        if self.weight < T::from(MIN_WEIGHT).unwrap() {
+            return FilterDecision::Score;
+        }
+
+        let is_masked = |i: usize| masked_dimensions.is_some_and(|masked| masked[i]);
+        let is_inside = (0..self.mean.len()).all(|i| {
+            let deviation = (point[i] - self.mean[i]).abs();
+            let std_dev = (self.moments[i] / self.weight).sqrt();
+            is_masked(i) || deviation <= self.z_bound * std_dev
+        });
+        if !is_inside {
+            return FilterDecision::Score;
+        }
+
+        if (self.num_skipped + self.num_audited + 1).is_multiple_of(AUDIT_INTERVAL) {
+            self.num_audited += 1;
+            FilterDecision::Audit
+        } else {
+            self.num_skipped += 1;
+            FilterDecision::Skip
+        }
+    }
+
+    /// Report whether an audited point turned out to be anomalous, and
+    /// adjust the z-score bound accordingly.
+    pub fn record_audit(&mut self, is_false_negative: bool) {
+        let factor = match is_false_negative {
+            true => {
+                self.num_false_negatives += 1;
+                1.0 - STEP
+            },
+            false => 1.0 + STEP * self.false_negative_budget / (1.0 - self.false_negative_budget),
+        };
+        self.z_bound = Float::min(self.z_bound * T::from(factor).unwrap(), self.max_z_bound);
+    }
+
+    /// Returns the current z-score bound.
+    pub fn z_bound(&self) -> T { self.z_bound }
+
+    /// Returns the budget of false negatives among audited points.
+    pub fn false_negative_budget(&self) -> f64 { self.false_negative_budget }
+
+    /// Returns the number of points skipped.
+    pub fn num_skipped(&self) -> usize { self.num_skipped }
+
+    /// Returns the number of points audited.
+    pub fn num_audited(&self) -> usize { self.num_audited }
+
+    /// Returns the number of audited points reported as anomalous.
+    pub fn num_false_negatives(&self) -> usize { self.num_false_negatives }
+
+    /// Returns the number of bytes allocated on the heap by the filter.
+    pub fn heap_size(&self) -> usize {
+        (self.mean.capacity() + self.moments.capacity()) * std::mem::size_of::<T>()
+    }
+}
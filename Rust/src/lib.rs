@@ -41,10 +41,18 @@
 //! on Machine Learning, pp. 2712-2721. PMLR, 2016. ()
 //!
 
+pub mod common;
+
+pub mod operator;
+
+pub mod tuning;
+
 pub mod visitor;
 
 mod random_cut_forest;
-pub use crate::random_cut_forest::{RandomCutForest, RandomCutForestBuilder};
+pub use crate::random_cut_forest::{
+    Clock, ConfigWarning, CutSummary, ForestParameters, FusedResult, InputTransform, Neighbor, NonFinitePolicy, PreFilter, RandomCutForest,
+    RandomCutForestBuilder, ScoreContext, ScoreDistribution, ScoreTransform, TimedOperation, TreeDivergence, TreeStatistics, UpdateRateLimit, ValidationReport, VisitorKind};
 
 mod sampler;
 pub use sampler::{SamplerResult, StreamSampler, WeightedSample};
@@ -0,0 +1,197 @@
+//! Submodule for running a forest as a stateful streaming operator.
+//!
+//! A [`ScoringOperator`] owns a forest and turns a stream of points into a
+//! stream of [`ScoredPoint`]s: every point is first scored and then used to
+//! update the forest. Points are pulled from a [`Source`] and results pushed
+//! to a [`Sink`], so the operator can be placed between the stages of an
+//! existing pipeline. Any iterator of points is a source, including the
+//! receiving end of a channel, and the sending end of a channel is a sink.
+//! With a bounded channel, [`std::sync::mpsc::sync_channel`], a slow
+//! consumer blocks the operator, which in turn stops pulling points:
+//! backpressure needs no extra glue.
+//!
+//! A forest is not `Send`, so the operator runs on the thread that built it
+//! while the producers and consumers of the channels run elsewhere.
+//!
+//! # Examples
+//!
+//! ```
+//! use std::sync::mpsc::sync_channel;
+//! use std::thread;
+//!
+//! use random_cut_forest::RandomCutForestBuilder;
+//! use random_cut_forest::operator::{ScoredPoint, ScoringOperator};
+//!
+//! let (point_sender, points) = sync_channel::<Vec<f32>>(16);
+//! let (mut results, result_receiver) = sync_channel::<ScoredPoint<f32>>(16);
+//!
+//! let producer = thread::spawn(move || {
+//!     for i in 0..1000 {
+//!         let value = if i == 900 { 100.0 } else { (i % 10) as f32 };
+//!         point_sender.send(vec![value]).unwrap();
+//!     }
+//! });
+//! let consumer = thread::spawn(move || {
+//!     result_receiver.iter()
+//!         .max_by(|a, b| a.score.partial_cmp(&b.score).unwrap())
+//!         .map(|scored| scored.index)
+//! });
+//!
+//! let forest = RandomCutForestBuilder::new(1).num_trees(20).sample_size(64).build();
+//! let mut operator = ScoringOperator::new(forest);
+//! let num_points = operator.run(&mut points.into_iter(), &mut results).unwrap();
+//! drop(results);
+//!
+//! producer.join().unwrap();
+//! assert_eq!(num_points, 1000);
+//! assert_eq!(consumer.join().unwrap(), Some(900));
+//! ```
+extern crate num_traits;
+use num_traits::{Float, Zero};
+
+use std::iter::Sum;
+use std::sync::mpsc::{Sender, SyncSender};
+
+use crate::RandomCutForest;
+
+/// A point with its anomaly score, emitted by a [`ScoringOperator`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScoredPoint<T> {
+    /// Position of the point in the stream processed by the operator,
+    /// starting at zero.
+    pub index: usize,
+    pub point: Vec<T>,
+    /// Anomaly score of the point before the forest was updated with it.
+    pub score: T,
+}
+
+/// A source of points for a [`ScoringOperator`].
+///
+/// Every iterator of points is a source. The stream ends when `next_point`
+/// returns `None`.
+pub trait Source<T> {
+    fn next_point(&mut self) -> Option<Vec<T>>;
+}
+
+impl<T, I> Source<T> for I
+    where I: Iterator<Item=Vec<T>>
+{
+    fn next_point(&mut self) -> Option<Vec<T>> { self.next() }
+}
+
+/// A destination for the results of a [`ScoringOperator`].
+///
+/// An error stops the operator, for example when the receiver of a channel
+/// has been dropped.
+pub trait Sink<T> {
+    fn emit(&mut self, scored_point: ScoredPoint<T>) -> Result<(), &'static str>;
+}
+
+impl<T> Sink<T> for Vec<ScoredPoint<T>> {
+    fn emit(&mut self, scored_point: ScoredPoint<T>) -> Result<(), &'static str> {
+        self.push(scored_point);
+        Ok(())
+    }
+}
+
+impl<T> Sink<T> for Sender<ScoredPoint<T>> {
+    fn emit(&mut self, scored_point: ScoredPoint<T>) -> Result<(), &'static str> {
+        self.send(scored_point).map_err(|_| "Sink disconnected")
+    }
+}
+
+impl<T> Sink<T> for SyncSender<ScoredPoint<T>> {
+    fn emit(&mut self, scored_point: ScoredPoint<T>) -> Result<(), &'static str> {
+        self.send(scored_point).map_err(|_| "Sink disconnected")
+    }
+}
+
+/// A forest run as a streaming operator that scores each point and then
+/// updates the forest with it.
+///
+/// See the [module documentation](self) for an example pipeline.
+pub struct ScoringOperator<T> {
+    forest: RandomCutForest<T>,
+    num_processed: usize,
+}
+
+impl<T> ScoringOperator<T>
+    where T: Float + Sum + Zero
+{
+
+    /// Create an operator around a forest, which may already be trained.
+    pub fn new(forest: RandomCutForest<T>) -> Self {
+        ScoringOperator { forest, num_processed: 0 }
+    }
+
+    /// Score a point, update the forest with it and return the result.
+    ///
+    /// See [`RandomCutForest::score_and_update`] for the errors. A point
+    /// that fails is not counted.
+    pub fn process(&mut self, point: Vec<T>) -> Result<ScoredPoint<T>, &'static str> {
+        let score = self.forest.score_and_update(point.clone())?;
+        let index = self.num_processed;
+        self.num_processed += 1;
+        Ok(ScoredPoint { index, point, score })
+    }
+
+    /// Process the points of a source until it ends, emitting the results
+    /// to a sink, and return the number of points processed.
+    ///
+    /// The operator stops at the first error of [`process`](Self::process)
+    /// or of the sink. Points already processed stay in the forest, so the
+    /// operator can be run again on the rest of the stream.
+    pub fn run<I, O>(&mut self, source: &mut I, sink: &mut O) -> Result<usize, &'static str>
+        where I: Source<T>, O: Sink<T>
+    {
+        let mut num_points = 0;
+        while let Some(point) = source.next_point() {
+            sink.emit(self.process(point)?)?;
+            num_points += 1;
+        }
+        Ok(num_points)
+    }
+
+    /// Returns the number of points processed so far.
+    pub fn num_processed(&self) -> usize { self.num_processed }
+
+    /// Returns a reference to the forest of the operator.
+    pub fn forest(&self) -> &RandomCutForest<T> { &self.forest }
+
+    /// Returns a mutable reference to the forest of the operator, for
+    /// example to reconfigure it between runs.
+    pub fn forest_mut(&mut self) -> &mut RandomCutForest<T> { &mut self.forest }
+
+    /// Consume the operator and return its forest.
+    pub fn into_forest(self) -> RandomCutForest<T> { self.forest }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RandomCutForestBuilder;
+    use std::sync::mpsc::channel;
+
+    #[test]
+    fn stops_on_errors() {
+        let forest = RandomCutForestBuilder::<f32>::new(1).num_trees(5).seed(0).build();
+        let mut operator = ScoringOperator::new(forest);
+
+        let mut points = vec![vec![0.0], vec![1.0, 2.0], vec![2.0]].into_iter();
+        let mut results: Vec<ScoredPoint<f32>> = Vec::new();
+        assert!(operator.run(&mut points, &mut results).is_err());
+        assert_eq!(results.len(), 1);
+
+        // the operator resumes with the rest of the stream
+        assert_eq!(operator.run(&mut points, &mut results), Ok(1));
+        assert_eq!(results[1].index, 1);
+        assert_eq!(results[1].point, vec![2.0]);
+        assert_eq!(operator.num_processed(), 2);
+
+        let (mut sender, receiver) = channel();
+        drop(receiver);
+        let mut points = vec![vec![3.0]].into_iter();
+        assert_eq!(operator.run(&mut points, &mut sender), Err("Sink disconnected"));
+        assert_eq!(operator.forest().num_observations(), 3);
+    }
+}
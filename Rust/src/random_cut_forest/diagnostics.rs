@@ -0,0 +1,379 @@
+extern crate num_traits;
+use num_traits::{Float, Zero};
+
+use std::iter::Sum;
+use std::time::Duration;
+
+use crate::{Node, SampledTree};
+use crate::common::LatencyHistogram;
+use crate::random_cut_forest::RandomCutForest;
+use crate::tree::InvariantViolation;
+
+/// Sampling counts of one tree of a forest and their divergence from the
+/// forest, see [`RandomCutForest::tree_divergence`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct TreeDivergence {
+    /// Identifier of the tree, see [`SampledTree::id`].
+    pub id: usize,
+    /// Number of points offered to the tree's sampler.
+    pub entries_seen: usize,
+    /// Number of points accepted into the tree's sample, including those
+    /// evicted since.
+    pub entries_accepted: usize,
+    /// Number of updates of the forest that the tree has not seen, not
+    /// counting the updates shed under a rate limit.
+    pub missed_updates: usize,
+    /// Difference between the number of points held by the sampler and the
+    /// mass of the tree, zero for a consistent tree.
+    pub sample_mismatch: usize,
+}
+
+impl TreeDivergence {
+
+    /// Returns true if the tree has diverged from the forest.
+    pub fn is_diverged(&self) -> bool {
+        self.missed_updates > 0 || self.sample_mismatch > 0
+    }
+}
+
+/// Shape and storage statistics of one tree of a forest, see
+/// [`RandomCutForest::tree_statistics`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct TreeStatistics {
+    /// Identifier of the tree, see [`SampledTree::id`].
+    pub id: usize,
+    /// Number of leaves at each depth, the root having depth zero.
+    pub leaf_depths: Vec<usize>,
+    /// Total mass of the leaves at each depth.
+    pub mass_profile: Vec<u32>,
+    /// Mean depth of the points of the sample, that is the average path
+    /// length from the root to a sampled point.
+    pub mean_depth: f64,
+    /// Number of nodes in the node store and number of nodes it can hold
+    /// without reallocating.
+    pub num_nodes: usize,
+    pub node_capacity: usize,
+    /// Number of distinct points in the point store and number of points it
+    /// can hold without reallocating.
+    pub num_points: usize,
+    pub point_capacity: usize,
+}
+
+impl TreeStatistics {
+
+    /// Returns the fraction of the allocated node slots in use, one for an
+    /// empty store.
+    pub fn node_utilization(&self) -> f64 {
+        match self.node_capacity {
+            0 => 1.0,
+            capacity => self.num_nodes as f64 / capacity as f64,
+        }
+    }
+}
+
+/// Result of [`RandomCutForest::validate`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct ValidationReport {
+    /// Number of trees, nodes and distinct points checked.
+    pub num_trees: usize,
+    pub num_nodes: usize,
+    pub num_points: usize,
+    /// Broken invariants, with the identifier of their tree.
+    pub violations: Vec<(usize, InvariantViolation)>,
+}
+
+impl ValidationReport {
+
+    /// Returns true if no invariant is broken.
+    pub fn is_valid(&self) -> bool { self.violations.is_empty() }
+}
+
+/// Forest operation timed by latency tracking, see
+/// [`RandomCutForestBuilder::track_latency`].
+///
+/// [`RandomCutForestBuilder::track_latency`]: crate::RandomCutForestBuilder::track_latency
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TimedOperation {
+    /// [`RandomCutForest::update`] and [`RandomCutForest::try_update`].
+    Update,
+    /// [`RandomCutForest::anomaly_score`] and
+    /// [`RandomCutForest::try_anomaly_score`].
+    Score,
+    /// [`RandomCutForest::score_and_update`].
+    ScoreAndUpdate,
+}
+
+impl<T> RandomCutForest<T>
+    where T: Float + Sum + Zero
+{
+
+    /// Return the mean age of the points retained by each tree, tagged with
+    /// the tree's identifier.
+    ///
+    /// Ages are counted in updates: the most recent point has age zero. The
+    /// mean age shows the memory horizon of each tree, to check it against
+    /// the expectation for the chosen time decay. Without time decay it is
+    /// about half the number of observations. Trees with an empty sample
+    /// are omitted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use random_cut_forest::{RandomCutForest, RandomCutForestBuilder};
+    ///
+    /// let mut time_decays = vec![0.0; 5];
+    /// time_decays.extend(vec![0.01; 5]);
+    /// let mut forest: RandomCutForest<f32> = RandomCutForestBuilder::new(1)
+    ///     .tree_time_decays(time_decays)
+    ///     .sample_size(64)
+    ///     .seed(2)
+    ///     .build();
+    /// for i in 0..5000 {
+    ///     forest.update(vec![(i % 10) as f32]);
+    /// }
+    ///
+    /// let ages = forest.sample_ages();
+    /// assert!(ages[..5].iter().all(|(_, age)| *age > 1000.0));
+    /// assert!(ages[5..].iter().all(|(_, age)| *age < 1000.0));
+    /// ```
+    pub fn sample_ages(&self) -> Vec<(usize, f64)> {
+        self.trees.iter()
+            .filter_map(|tree| {
+                tree.mean_sample_age(self.num_observations).map(|age| (tree.id(), age))
+            })
+            .collect()
+    }
+
+    /// Return the sampling counts of each tree and their divergence from the
+    /// forest.
+    ///
+    /// Every tree of a healthy forest sees every update that is not shed
+    /// under a rate limit, and its tree holds exactly the points of its
+    /// sample. A tree that has missed updates or whose structure no longer
+    /// matches its sample, for example after a failure during an update, is
+    /// diverged and can be repaired with [`resync_tree`](Self::resync_tree).
+    pub fn tree_divergence(&self) -> Vec<TreeDivergence> {
+        self.trees.iter()
+            .map(|tree| {
+                let sample_size = tree.num_samples();
+                let mass = tree.tree().mass() as usize;
+                TreeDivergence {
+                    id: tree.id(),
+                    entries_seen: tree.num_observations(),
+                    entries_accepted: tree.num_accepted(),
+                    missed_updates: self.num_delivered_updates.saturating_sub(tree.num_observations()),
+                    sample_mismatch: sample_size.max(mass) - sample_size.min(mass),
+                }
+            })
+            .collect()
+    }
+
+    /// Returns the shape and storage statistics of each tree.
+    ///
+    /// The depth histogram and mass profile of the leaves show how balanced
+    /// the trees are; the mean depth of the sample is the expected path
+    /// length that anomaly scores are measured against. The node and point
+    /// counts against the capacity of the stores show how much of the
+    /// memory reported by [`size_in_bytes`](Self::size_in_bytes) is in use,
+    /// to size the forest empirically.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use random_cut_forest::{RandomCutForest, RandomCutForestBuilder};
+    ///
+    /// let mut forest: RandomCutForest<f32> = RandomCutForestBuilder::new(2)
+    ///     .num_trees(10)
+    ///     .sample_size(64)
+    ///     .seed(6)
+    ///     .build();
+    /// for i in 0..1000 {
+    ///     forest.update(vec![(i % 100) as f32, (i % 37) as f32]);
+    /// }
+    ///
+    /// for statistics in forest.tree_statistics() {
+    ///     assert_eq!(statistics.mass_profile.iter().sum::<u32>(), 64);
+    ///     assert_eq!(statistics.num_nodes, 2 * statistics.num_points - 1);
+    ///     assert!(statistics.mean_depth > 4.0 && statistics.mean_depth < 20.0);
+    ///     assert!(statistics.node_utilization() <= 1.0);
+    /// }
+    /// ```
+    pub fn tree_statistics(&self) -> Vec<TreeStatistics> {
+        self.trees.iter().map(tree_statistics).collect()
+    }
+
+    /// Check the structural invariants of every tree and return a report,
+    /// as an error if any invariant is broken.
+    ///
+    /// See [`SampledTree::validate`] for the checks. Corruption otherwise
+    /// only shows as a panic deep inside a later update or score; a broken
+    /// tree can be repaired with [`rebuild_tree`](Self::rebuild_tree) or
+    /// [`resync_tree`](Self::resync_tree).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use random_cut_forest::{RandomCutForest, RandomCutForestBuilder};
+    ///
+    /// let mut forest: RandomCutForest<f32> = RandomCutForestBuilder::new(2)
+    ///     .num_trees(10)
+    ///     .sample_size(32)
+    ///     .build();
+    /// for i in 0..500 {
+    ///     forest.update(vec![(i % 10) as f32, (i % 7) as f32]);
+    /// }
+    ///
+    /// let report = forest.validate().unwrap();
+    /// assert_eq!(report.num_trees, 10);
+    /// assert!(report.is_valid());
+    /// ```
+    pub fn validate(&self) -> Result<ValidationReport, ValidationReport> {
+        let report = ValidationReport {
+            num_trees: self.trees.len(),
+            num_nodes: self.trees.iter().map(|tree| tree.tree().node_store().len()).sum(),
+            num_points: self.trees.iter().map(|tree| tree.borrow_point_store().len()).sum(),
+            violations: self.trees.iter()
+                .flat_map(|tree| tree.validate().into_iter().map(move |violation| (tree.id(), violation)))
+                .collect(),
+        };
+        match report.is_valid() {
+            true => Ok(report),
+            false => Err(report),
+        }
+    }
+
+    /// Returns a Graphviz DOT description of the tree at position
+    /// `tree_index`, or an error if there is no such tree.
+    ///
+    /// Internal nodes show their cut, as `x<dimension> <= <value>` or with
+    /// `<` when ties go right, and their mass. Leaves show the key of their
+    /// point in the tree's point store, the point and its mass. Cut values
+    /// and points are in input units. Nodes are named after their keys in
+    /// the node store and listed depth first from the root, so the output of
+    /// an unchanged tree is stable. Render it with `dot -Tsvg`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use random_cut_forest::{RandomCutForest, RandomCutForestBuilder};
+    ///
+    /// let mut forest: RandomCutForest<f32> = RandomCutForestBuilder::new(2)
+    ///     .num_trees(3)
+    ///     .sample_size(4)
+    ///     .seed(2)
+    ///     .build();
+    /// forest.update(vec![0.0, 0.0]);
+    /// forest.update(vec![1.0, 0.0]);
+    /// forest.update(vec![1.0, 0.0]);
+    ///
+    /// let dot = forest.export_tree_dot(1).unwrap();
+    /// assert!(dot.starts_with("digraph tree_1 {"));
+    /// assert!(dot.contains("x0 <= "));
+    /// assert!(dot.contains("[1, 0]\\nmass 2"));
+    /// assert!(forest.export_tree_dot(3).is_err());
+    /// ```
+    pub fn export_tree_dot(&self, tree_index: usize) -> Result<String, &'static str> {
+        let sampled_tree = self.trees.get(tree_index).ok_or("Tree index out of range")?;
+        let tree = sampled_tree.tree();
+        let point_store = sampled_tree.borrow_point_store();
+        let format_value = |value: T| value.to_f64().unwrap().to_string();
+
+        let mut dot = format!("digraph tree_{} {{\n    node [shape=box];\n", sampled_tree.id());
+        let mut stack: Vec<usize> = tree.root_node().into_iter().collect();
+        while let Some(node_key) = stack.pop() {
+            match tree.get_node(node_key) {
+                Node::Leaf(leaf) => {
+                    let point = self.to_input_units(point_store[leaf.point()].clone());
+                    let coordinates: Vec<String> = point.into_iter().map(format_value).collect();
+                    dot += &format!("    n{} [shape=ellipse, label=\"point {}\\n[{}]\\nmass {}\"];\n",
+                        node_key, leaf.point(), coordinates.join(", "), leaf.mass());
+                },
+                Node::Internal(internal) => {
+                    let cut = internal.cut();
+                    let value = self.coordinate_to_input_units(cut.dimension(), cut.value());
+                    let comparison = if cut.ties_left() { "<=" } else { "<" };
+                    dot += &format!("    n{} [label=\"x{} {} {}\\nmass {}\"];\n",
+                        node_key, cut.dimension(), comparison, format_value(value), internal.mass());
+                    dot += &format!("    n{} -> n{} [label=\"yes\"];\n", node_key, internal.left());
+                    dot += &format!("    n{} -> n{} [label=\"no\"];\n", node_key, internal.right());
+                    stack.push(internal.right());
+                    stack.push(internal.left());
+                },
+            }
+        }
+        dot += "}\n";
+        Ok(dot)
+    }
+
+    /// Returns the latencies of an operation at the given percentiles, in
+    /// `[0, 100]`, or `None` if latency tracking is disabled or the
+    /// operation has not completed yet.
+    ///
+    /// See [`RandomCutForestBuilder::track_latency`].
+    ///
+    /// # Panics
+    ///
+    /// If a percentile is not in `[0, 100]`.
+    ///
+    /// [`RandomCutForestBuilder::track_latency`]: crate::RandomCutForestBuilder::track_latency
+    pub fn latency_percentiles(&self, operation: TimedOperation, percentiles: &[f64]) -> Option<Vec<Duration>> {
+        let histogram = self.latency.as_ref()?[operation as usize].borrow();
+        percentiles.iter()
+            .map(|percentile| histogram.value_at_percentile(*percentile))
+            .collect()
+    }
+
+    /// Returns a copy of the latency histogram of an operation, or `None` if
+    /// latency tracking is disabled.
+    pub fn latency_histogram(&self, operation: TimedOperation) -> Option<LatencyHistogram> {
+        self.latency.as_ref().map(|latency| latency[operation as usize].borrow().clone())
+    }
+
+    /// Remove the latencies recorded so far, for example at the start of a
+    /// reporting period.
+    pub fn reset_latency(&mut self) {
+        for histogram in self.latency.iter().flatten() {
+            histogram.borrow_mut().reset();
+        }
+    }
+}
+
+/// Shape and storage statistics of a single tree.
+fn tree_statistics<T: Float + Sum>(sampled_tree: &SampledTree<T>) -> TreeStatistics {
+    let tree = sampled_tree.tree();
+    let mut leaf_depths: Vec<usize> = Vec::new();
+    let mut mass_profile: Vec<u32> = Vec::new();
+    let mut stack: Vec<(usize, usize)> = tree.root_node().map(|root| (root, 0)).into_iter().collect();
+    while let Some((node_key, depth)) = stack.pop() {
+        match tree.get_node(node_key) {
+            Node::Leaf(leaf) => {
+                if leaf_depths.len() <= depth {
+                    leaf_depths.resize(depth + 1, 0);
+                    mass_profile.resize(depth + 1, 0);
+                }
+                leaf_depths[depth] += 1;
+                mass_profile[depth] += leaf.mass();
+            },
+            Node::Internal(internal) => {
+                stack.push((internal.left(), depth + 1));
+                stack.push((internal.right(), depth + 1));
+            },
+        }
+    }
+
+    let total_mass: u32 = mass_profile.iter().sum();
+    let total_depth: f64 = mass_profile.iter().enumerate()
+        .map(|(depth, &mass)| depth as f64 * mass as f64)
+        .sum();
+    let point_store = sampled_tree.borrow_point_store();
+    TreeStatistics {
+        id: sampled_tree.id(),
+        leaf_depths,
+        mass_profile,
+        mean_depth: if total_mass > 0 { total_depth / total_mass as f64 } else { 0.0 },
+        num_nodes: tree.node_store().len(),
+        node_capacity: tree.node_store().capacity(),
+        num_points: point_store.len(),
+        point_capacity: point_store.capacity(),
+    }
+}
@@ -0,0 +1,613 @@
+extern crate num_traits;
+use num_traits::{Float, Zero};
+
+use std::fmt;
+use std::iter::Sum;
+use std::time::Duration;
+
+use crate::random_cut_forest::{
+    InputTransform, NonFinitePolicy, PreFilter, RandomCutForest, RandomCutForestBuilder, ScoreTransform,
+    UpdateRateLimit};
+use crate::tree::{CutStrategy, TieBreaking};
+use crate::visitor::ScoreFunction;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// The effective configuration of a [`RandomCutForest`].
+///
+/// Returned by [`RandomCutForest::parameters`], it collects every parameter
+/// set through [`RandomCutForestBuilder`], with defaults filled in, so that
+/// the configuration of a running forest can be logged or stored alongside
+/// its results.
+///
+/// With the `serde` feature, the parameters implement `Serialize` and
+/// `Deserialize`, so a configuration can be read from a file and passed to
+/// [`RandomCutForestBuilder::from_parameters`]. Functions are not
+/// serialized: a score transform is skipped, the clock of a rate limit is
+/// reset to the system clock, and a custom score function fails to
+/// serialize.
+///
+/// # Examples
+///
+/// ```
+/// use random_cut_forest::{RandomCutForest, RandomCutForestBuilder};
+///
+/// let forest: RandomCutForest<f32> = RandomCutForestBuilder::new(3)
+///     .num_trees(20)
+///     .seed(42)
+///     .build();
+///
+/// let parameters = forest.parameters();
+/// assert_eq!(parameters.dimension, 3);
+/// assert_eq!(parameters.num_trees, 20);
+/// assert_eq!(parameters.sample_size, 256);
+/// assert_eq!(parameters.seed, Some(42));
+/// ```
+// Score transforms compare by address, which is enough to detect a change
+// of transform between two sets of parameters.
+#[allow(unpredictable_function_pointer_comparisons)]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ForestParameters<T> {
+    pub dimension: usize,
+    pub num_trees: usize,
+    pub sample_size: usize,
+    /// Sample size of each tree, if not all trees use `sample_size`.
+    pub tree_sample_sizes: Option<Vec<usize>>,
+    pub time_decay: f32,
+    /// Time decay of each tree, if not all trees use `time_decay`.
+    pub tree_time_decays: Option<Vec<f32>>,
+    pub output_after: usize,
+    pub noise_scale: f32,
+    pub max_score: Option<T>,
+    /// Transformation applied to anomaly scores, if any. Functions cannot
+    /// be serialized: the transform is skipped and deserialized as `None`.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub score_transform: Option<ScoreTransform<T>>,
+    pub score_function: ScoreFunction,
+    pub cut_strategy: CutStrategy,
+    pub tie_breaking: TieBreaking,
+    pub non_finite_policy: NonFinitePolicy,
+    /// Lower and upper bounds of the input dimensions.
+    pub bounds: Option<(Vec<T>, Vec<T>)>,
+    pub clamp_to_bounds: bool,
+    /// Decay of the tracked covariance, if any.
+    pub covariance_decay: Option<T>,
+    /// Scale and offset applied to input points.
+    pub input_scaling: Option<(Vec<T>, Vec<T>)>,
+    /// Transformation of each input dimension, applied before the scaling.
+    pub input_transforms: Option<Vec<InputTransform>>,
+    /// Number of recent raw inputs kept for debugging.
+    pub raw_input_history: usize,
+    /// Decay of the tracked tree reliabilities, if any.
+    pub tree_reliability_decay: Option<T>,
+    /// Whether tree scores are weighted by tree reliability.
+    pub weighted_voting: bool,
+    /// Whether the latency of forest operations is recorded.
+    pub track_latency: bool,
+    /// Pre-filter of clearly normal points, if any.
+    pub pre_filter: Option<PreFilter<T>>,
+    /// Limit on the rate of updates, if any.
+    pub update_rate_limit: Option<UpdateRateLimit>,
+    /// The random seed. `None` if the forest was seeded from system entropy
+    /// or built with an [`rng_source`](RandomCutForestBuilder::rng_source).
+    pub seed: Option<u64>,
+}
+
+impl<T> ForestParameters<T>
+    where T: Float
+{
+
+    /// Returns a stable 64-bit hash of the parameters that determine the
+    /// model.
+    ///
+    /// The fingerprint is the FNV-1a hash of a fixed encoding of the
+    /// parameters, so it is the same across runs, platforms and builds and
+    /// can be attached to alerts to detect a change of model. Floating point
+    /// parameters are hashed by value, and enumerations by an explicit tag
+    /// for each variant followed by its payload. Parameters that do not
+    /// change the model are left out: `raw_input_history`, `track_latency`
+    /// and `update_rate_limit`, which preserves the model in expectation,
+    /// although a seeded forest is not reproducible under a rate limit timed
+    /// by the system clock. A score transform or custom score function only
+    /// contributes its presence, since function addresses are not stable
+    /// across builds.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use random_cut_forest::{RandomCutForest, RandomCutForestBuilder};
+    ///
+    /// let builder = || RandomCutForestBuilder::new(3).num_trees(20).seed(42);
+    /// let forest: RandomCutForest<f32> = builder().build();
+    /// let same: RandomCutForest<f32> = builder().raw_input_history(10).build();
+    /// let other: RandomCutForest<f32> = builder().time_decay(0.001).build();
+    ///
+    /// let fingerprint = forest.parameters().fingerprint();
+    /// assert_eq!(same.parameters().fingerprint(), fingerprint);
+    /// assert_ne!(other.parameters().fingerprint(), fingerprint);
+    /// ```
+    pub fn fingerprint(&self) -> u64 {
+        let mut hasher = Fingerprint::new();
+        hasher.write_usize(self.dimension);
+        hasher.write_usize(self.num_trees);
+        hasher.write_usize(self.sample_size);
+        hasher.write_option(self.tree_sample_sizes.as_ref(), |hasher, sizes| {
+            sizes.iter().for_each(|&size| hasher.write_usize(size))
+        });
+        hasher.write_f64(self.time_decay as f64);
+        hasher.write_option(self.tree_time_decays.as_ref(), |hasher, decays| {
+            decays.iter().for_each(|&decay| hasher.write_f64(decay as f64))
+        });
+        hasher.write_usize(self.output_after);
+        hasher.write_f64(self.noise_scale as f64);
+        hasher.write_option(self.max_score.as_ref(), |hasher, &max_score| hasher.write_float(max_score));
+        hasher.write_option(self.score_transform.as_ref(), |_, _| ());
+        // enums are hashed as explicit variant tags followed by their payload
+        hasher.write_u64(match self.score_function {
+            ScoreFunction::Standard => 0,
+            ScoreFunction::Displacement => 1,
+            ScoreFunction::ExpectedInverseDepth => 2,
+            ScoreFunction::Custom(_) => 3,
+        });
+        match self.cut_strategy {
+            CutStrategy::RangeProportional => hasher.write_u64(0),
+            CutStrategy::DimensionSubsample(size) => {
+                hasher.write_u64(1);
+                hasher.write_usize(size);
+            },
+        }
+        hasher.write_u64(match self.tie_breaking {
+            TieBreaking::Left => 0,
+            TieBreaking::Right => 1,
+            TieBreaking::Random => 2,
+        });
+        match self.non_finite_policy {
+            NonFinitePolicy::Error => hasher.write_u64(0),
+            NonFinitePolicy::Clamp(bound) => {
+                hasher.write_u64(1);
+                hasher.write_f64(bound);
+            },
+            NonFinitePolicy::Skip => hasher.write_u64(2),
+        }
+        hasher.write_option(self.bounds.as_ref(), |hasher, (lower, upper)| {
+            lower.iter().chain(upper.iter()).for_each(|&value| hasher.write_float(value))
+        });
+        hasher.write_usize(self.clamp_to_bounds as usize);
+        hasher.write_option(self.covariance_decay.as_ref(), |hasher, &decay| hasher.write_float(decay));
+        hasher.write_option(self.input_scaling.as_ref(), |hasher, (scale, offset)| {
+            scale.iter().chain(offset.iter()).for_each(|&value| hasher.write_float(value))
+        });
+        hasher.write_option(self.input_transforms.as_ref(), |hasher, transforms| {
+            transforms.iter().for_each(|transform| hasher.write_u64(match transform {
+                InputTransform::Identity => 0,
+                InputTransform::Sqrt => 1,
+                InputTransform::Log1p => 2,
+            }))
+        });
+        hasher.write_option(self.tree_reliability_decay.as_ref(), |hasher, &decay| hasher.write_float(decay));
+        hasher.write_usize(self.weighted_voting as usize);
+        hasher.write_option(self.seed.as_ref(), |hasher, &seed| hasher.write_u64(seed));
+        hasher.write_option(self.pre_filter.as_ref(), |hasher, pre_filter| {
+            hasher.write_float(pre_filter.z_bound);
+            hasher.write_float(pre_filter.score_threshold);
+            hasher.write_f64(pre_filter.false_negative_budget);
+        });
+        hasher.finish()
+    }
+}
+
+/// FNV-1a hash of a sequence of values, see [`ForestParameters::fingerprint`].
+struct Fingerprint {
+    state: u64,
+}
+
+impl Fingerprint {
+    fn new() -> Self { Fingerprint { state: 0xcbf29ce484222325 } }
+
+    fn write_bytes(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.state = (self.state ^ byte as u64).wrapping_mul(0x100000001b3);
+        }
+    }
+
+    fn write_u64(&mut self, value: u64) { self.write_bytes(&value.to_le_bytes()) }
+
+    fn write_usize(&mut self, value: usize) { self.write_u64(value as u64) }
+
+    fn write_f64(&mut self, value: f64) { self.write_u64(value.to_bits()) }
+
+    fn write_float<T: Float>(&mut self, value: T) { self.write_f64(value.to_f64().unwrap()) }
+
+    /// Hash whether a value is present and, if so, the value.
+    fn write_option<V, F>(&mut self, value: Option<&V>, write: F) where F: FnOnce(&mut Self, &V) {
+        match value {
+            Some(value) => {
+                self.write_u64(1);
+                write(self, value);
+            },
+            None => self.write_u64(0),
+        }
+    }
+
+    fn finish(&self) -> u64 { self.state }
+}
+
+/// Non-fatal configuration issue reported by
+/// [`RandomCutForestBuilder::warnings`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum ConfigWarning {
+    /// The effective memory of a tree's time decay, `1 / time_decay`
+    /// updates, is shorter than its sample size, so most of the sample is
+    /// replaced before it could be used and the sample is effectively
+    /// smaller.
+    ShortMemoryHorizon { horizon: f64, sample_size: usize },
+    /// The dimension is at least the sample size of a tree. The trees then
+    /// cannot separate points along most dimensions, which dilutes
+    /// anomalies in a few of them, as with long shingles.
+    DimensionExceedsSampleSize { dimension: usize, sample_size: usize },
+    /// The forest has so few trees that its scores have a high variance.
+    FewTrees { num_trees: usize },
+}
+
+impl fmt::Display for ConfigWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigWarning::ShortMemoryHorizon { horizon, sample_size } => write!(f,
+                "time_decay implies a memory horizon of {:.0} updates, shorter than the sample size {}",
+                horizon, sample_size),
+            ConfigWarning::DimensionExceedsSampleSize { dimension, sample_size } => write!(f,
+                "dimension {} with sample size {} will dilute anomalies", dimension, sample_size),
+            ConfigWarning::FewTrees { num_trees } => write!(f,
+                "{} trees give high variance scores", num_trees),
+        }
+    }
+}
+
+/// Smallest number of trees not reported by [`ConfigWarning::FewTrees`].
+const MIN_RECOMMENDED_TREES: usize = 10;
+
+impl<T> RandomCutForest<T>
+    where T: Float + Sum + Zero
+{
+
+    /// Return the effective configuration of the forest.
+    pub fn parameters(&self) -> ForestParameters<T> {
+        ForestParameters {
+            dimension: self.dimension,
+            num_trees: self.trees.len(),
+            sample_size: self.sample_size,
+            tree_sample_sizes: match self.trees.iter().all(|tree| tree.sample_size() == self.sample_size) {
+                true => None,
+                false => Some(self.trees.iter().map(|tree| tree.sample_size()).collect()),
+            },
+            time_decay: self.time_decay,
+            tree_time_decays: match self.trees.iter().all(|tree| tree.time_decay() == self.time_decay) {
+                true => None,
+                false => Some(self.trees.iter().map(|tree| tree.time_decay()).collect()),
+            },
+            output_after: self.output_after,
+            noise_scale: self.noise_scale,
+            max_score: self.max_score,
+            score_transform: self.score_transform,
+            score_function: self.score_function.clone(),
+            cut_strategy: self.cut_strategy,
+            tie_breaking: self.tie_breaking,
+            non_finite_policy: self.non_finite_policy,
+            bounds: self.bounds.as_ref()
+                .map(|bounds| (bounds.min_values().clone(), bounds.max_values().clone())),
+            clamp_to_bounds: self.clamp_to_bounds,
+            covariance_decay: self.covariance.as_ref().map(|covariance| covariance.decay()),
+            input_scaling: self.input_scaling.clone(),
+            input_transforms: self.input_transforms.clone(),
+            raw_input_history: self.raw_input_history,
+            tree_reliability_decay: self.tree_reliability.as_ref()
+                .and_then(|reliability| reliability.first())
+                .map(|covariance| covariance.decay()),
+            weighted_voting: self.weighted_voting,
+            track_latency: self.latency.is_some(),
+            pre_filter: self.pre_filter.as_ref().map(|(pre_filter, _)| *pre_filter),
+            update_rate_limit: self.governor.as_ref().map(|(limit, _)| *limit),
+            seed: self.seed,
+        }
+    }
+}
+
+impl<T> RandomCutForestBuilder<T>
+    where T: Float + Sum
+{
+
+    /// Initialize a random cut forest builder from a complete configuration,
+    /// such as one returned by [`RandomCutForest::parameters`].
+    ///
+    /// Unlike the individual setters, which panic on invalid values, the
+    /// configuration is validated as a whole and the first invalid field is
+    /// reported in the error, prefixed with its name.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use random_cut_forest::{RandomCutForest, RandomCutForestBuilder};
+    ///
+    /// let forest: RandomCutForest<f32> = RandomCutForestBuilder::new(3)
+    ///     .num_trees(20)
+    ///     .seed(42)
+    ///     .build();
+    ///
+    /// // build a second forest with the same configuration
+    /// let parameters = forest.parameters();
+    /// let copy: RandomCutForest<f32> = RandomCutForestBuilder::from_parameters(&parameters)
+    ///     .unwrap()
+    ///     .build();
+    /// assert_eq!(copy.parameters(), parameters);
+    ///
+    /// let mut invalid = parameters.clone();
+    /// invalid.sample_size = 0;
+    /// assert!(RandomCutForestBuilder::from_parameters(&invalid).is_err());
+    /// ```
+    pub fn from_parameters(parameters: &ForestParameters<T>) -> Result<RandomCutForestBuilder<T>, &'static str> {
+        let dimension = parameters.dimension;
+        if dimension == 0 {
+            return Err("dimension: must be positive");
+        }
+        if parameters.num_trees == 0 {
+            return Err("num_trees: must be positive");
+        }
+        if parameters.sample_size == 0 {
+            return Err("sample_size: must be positive");
+        }
+        if let Some(sizes) = &parameters.tree_sample_sizes {
+            if sizes.len() != parameters.num_trees {
+                return Err("tree_sample_sizes: length must match num_trees");
+            }
+            if sizes.contains(&0) {
+                return Err("tree_sample_sizes: must be positive");
+            }
+        }
+        if !(parameters.time_decay >= 0.0 && parameters.time_decay.is_finite()) {
+            return Err("time_decay: must be non-negative and finite");
+        }
+        if let Some(decays) = &parameters.tree_time_decays {
+            if decays.len() != parameters.num_trees {
+                return Err("tree_time_decays: length must match num_trees");
+            }
+            if !decays.iter().all(|decay| *decay >= 0.0 && decay.is_finite()) {
+                return Err("tree_time_decays: must be non-negative and finite");
+            }
+        }
+        if !(parameters.noise_scale >= 0.0 && parameters.noise_scale.is_finite()) {
+            return Err("noise_scale: must be non-negative and finite");
+        }
+        if let Some(max_score) = parameters.max_score {
+            if max_score.is_nan() || max_score <= T::zero() {
+                return Err("max_score: must be positive");
+            }
+        }
+        if parameters.cut_strategy == CutStrategy::DimensionSubsample(0) {
+            return Err("cut_strategy: dimension subsample size must be positive");
+        }
+        if let NonFinitePolicy::Clamp(bound) = parameters.non_finite_policy {
+            if !(bound > 0.0 && bound.is_finite()) {
+                return Err("non_finite_policy: clamp bound must be positive and finite");
+            }
+        }
+        if let Some((lower, upper)) = &parameters.bounds {
+            if lower.len() != dimension || upper.len() != dimension {
+                return Err("bounds: length must match the dimension");
+            }
+            if lower.iter().zip(upper.iter()).any(|(l, u)| l > u) {
+                return Err("bounds: lower bounds must not exceed upper bounds");
+            }
+        }
+        if let Some(decay) = parameters.covariance_decay {
+            if !(decay >= T::zero() && decay < T::one()) {
+                return Err("covariance_decay: must be in [0, 1)");
+            }
+        }
+        if let Some((scale, offset)) = &parameters.input_scaling {
+            if scale.len() != dimension || offset.len() != dimension {
+                return Err("input_scaling: length must match the dimension");
+            }
+            if !scale.iter().all(|s| *s > T::zero() && s.is_finite()) {
+                return Err("input_scaling: scales must be positive and finite");
+            }
+        }
+        if let Some(transforms) = &parameters.input_transforms {
+            if transforms.len() != dimension {
+                return Err("input_transforms: length must match the dimension");
+            }
+        }
+        if let Some(decay) = parameters.tree_reliability_decay {
+            if !(decay > T::zero() && decay < T::one()) {
+                return Err("tree_reliability_decay: must be in (0, 1)");
+            }
+        }
+        if parameters.weighted_voting && parameters.tree_reliability_decay.is_none() {
+            return Err("weighted_voting: requires tree_reliability_decay");
+        }
+        if let Some(pre_filter) = &parameters.pre_filter {
+            if !(pre_filter.z_bound > T::zero() && pre_filter.z_bound.is_finite()) {
+                return Err("pre_filter: z_bound must be positive and finite");
+            }
+            if !(pre_filter.false_negative_budget > 0.0 && pre_filter.false_negative_budget < 1.0) {
+                return Err("pre_filter: false_negative_budget must be in (0, 1)");
+            }
+        }
+        if let Some(limit) = &parameters.update_rate_limit {
+            if limit.max_updates == 0 {
+                return Err("update_rate_limit: max_updates must be positive");
+            }
+            if limit.window == Duration::from_secs(0) {
+                return Err("update_rate_limit: window must not be empty");
+            }
+        }
+
+        let mut builder = RandomCutForestBuilder::new(dimension)
+            .num_trees(parameters.num_trees)
+            .sample_size(parameters.sample_size)
+            .time_decay(parameters.time_decay)
+            .output_after(parameters.output_after)
+            .score_function(parameters.score_function.clone())
+            .cut_strategy(parameters.cut_strategy)
+            .tie_breaking(parameters.tie_breaking)
+            .non_finite_policy(parameters.non_finite_policy)
+            .clamp_to_bounds(parameters.clamp_to_bounds)
+            .raw_input_history(parameters.raw_input_history)
+            .track_latency(parameters.track_latency);
+        builder.tree_sample_sizes = parameters.tree_sample_sizes.clone();
+        builder.tree_time_decays = parameters.tree_time_decays.clone();
+        builder.noise_scale = parameters.noise_scale;
+        builder.max_score = parameters.max_score;
+        builder.score_transform = parameters.score_transform;
+        builder.bounds = parameters.bounds.clone();
+        builder.covariance_decay = parameters.covariance_decay;
+        builder.input_scaling = parameters.input_scaling.clone();
+        builder.input_transforms = parameters.input_transforms.clone();
+        builder.tree_reliability_decay = parameters.tree_reliability_decay;
+        builder.weighted_voting = parameters.weighted_voting;
+        builder.pre_filter = parameters.pre_filter;
+        builder.update_rate_limit = parameters.update_rate_limit;
+        builder.seed = parameters.seed;
+        Ok(builder)
+    }
+
+    /// Returns the non-fatal issues of the configuration.
+    ///
+    /// Invalid parameters are rejected by the setters and by
+    /// [`build`](Self::build). Valid ones can still make a poor model, such
+    /// as a sample that is too small for the dimension. Each tree's own
+    /// sample size and time decay are checked, and each issue is reported
+    /// once, for the worst tree. The warnings implement `Display` for
+    /// logging.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use random_cut_forest::{ConfigWarning, RandomCutForestBuilder};
+    ///
+    /// let builder = RandomCutForestBuilder::<f32>::new(288)
+    ///     .sample_size(256)
+    ///     .time_decay(0.01);
+    /// let warnings = builder.warnings();
+    /// assert_eq!(warnings, vec![
+    ///     ConfigWarning::ShortMemoryHorizon { horizon: 100.0, sample_size: 256 },
+    ///     ConfigWarning::DimensionExceedsSampleSize { dimension: 288, sample_size: 256 },
+    /// ]);
+    /// assert_eq!(warnings[0].to_string(),
+    ///     "time_decay implies a memory horizon of 100 updates, shorter than the sample size 256");
+    ///
+    /// assert!(RandomCutForestBuilder::<f32>::new(4).warnings().is_empty());
+    /// ```
+    pub fn warnings(&self) -> Vec<ConfigWarning> {
+        let sample_sizes: Vec<usize> = (0..self.num_trees)
+            .map(|i| self.tree_sample_sizes.as_ref().and_then(|sizes| sizes.get(i).copied()).unwrap_or(self.sample_size))
+            .collect();
+        let time_decays: Vec<f32> = (0..self.num_trees)
+            .map(|i| self.tree_time_decays.as_ref().and_then(|decays| decays.get(i).copied()).unwrap_or(self.time_decay))
+            .collect();
+
+        let mut warnings = Vec::new();
+        let shortest_horizon = sample_sizes.iter()
+            .zip(time_decays.iter())
+            .filter(|(_, &decay)| decay > 0.0)
+            .map(|(&sample_size, &decay)| ((1.0 / decay) as f64, sample_size))
+            .filter(|&(horizon, sample_size)| horizon < sample_size as f64)
+            .min_by(|a, b| (a.0 / a.1 as f64).partial_cmp(&(b.0 / b.1 as f64)).unwrap());
+        if let Some((horizon, sample_size)) = shortest_horizon {
+            warnings.push(ConfigWarning::ShortMemoryHorizon { horizon, sample_size });
+        }
+        let smallest_sample = sample_sizes.iter().copied().min().unwrap_or(self.sample_size);
+        if self.dimension >= smallest_sample {
+            warnings.push(ConfigWarning::DimensionExceedsSampleSize {
+                dimension: self.dimension,
+                sample_size: smallest_sample,
+            });
+        }
+        if self.num_trees < MIN_RECOMMENDED_TREES {
+            warnings.push(ConfigWarning::FewTrees { num_trees: self.num_trees });
+        }
+        warnings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_parameters() {
+        let forest: RandomCutForest<f64> = RandomCutForestBuilder::new(2)
+            .num_trees(10)
+            .sample_size(64)
+            .tree_sample_sizes(vec![64, 64, 64, 64, 64, 64, 64, 64, 32, 128])
+            .tree_time_decays(vec![0.001, 0.001, 0.001, 0.001, 0.001, 0.01, 0.01, 0.01, 0.01, 0.01])
+            .time_decay(0.001)
+            .output_after(32)
+            .privacy_noise(2.0, 1.0)
+            .max_score(4.0)
+            .score_transform(|score, _| score.ln_1p())
+            .score_function(ScoreFunction::Displacement)
+            .cut_strategy(CutStrategy::DimensionSubsample(1))
+            .tie_breaking(TieBreaking::Random)
+            .non_finite_policy(NonFinitePolicy::Clamp(1e6))
+            .dimension_bounds(vec![-1.0, 0.0], vec![1.0, f64::INFINITY])
+            .clamp_to_bounds(true)
+            .track_covariance(0.01)
+            .input_scaling(vec![2.0, 3.0], vec![0.5, 0.0])
+            .input_transforms(vec![InputTransform::Log1p, InputTransform::Identity])
+            .raw_input_history(8)
+            .tree_reliability(0.05)
+            .weighted_voting(true)
+            .seed(7)
+            .build();
+
+        let parameters = forest.parameters();
+        let copy: RandomCutForest<f64> = RandomCutForestBuilder::from_parameters(&parameters)
+            .unwrap()
+            .build();
+        assert_eq!(copy.parameters(), parameters);
+
+        let mut invalid = parameters.clone();
+        invalid.bounds = Some((vec![0.0], vec![1.0]));
+        let error = RandomCutForestBuilder::from_parameters(&invalid).err().unwrap();
+        assert!(error.starts_with("bounds:"));
+
+        let mut invalid = parameters.clone();
+        invalid.covariance_decay = Some(1.0);
+        let error = RandomCutForestBuilder::from_parameters(&invalid).err().unwrap();
+        assert!(error.starts_with("covariance_decay:"));
+
+        let mut invalid = parameters;
+        invalid.max_score = Some(f64::NAN);
+        let error = RandomCutForestBuilder::from_parameters(&invalid).err().unwrap();
+        assert!(error.starts_with("max_score:"));
+    }
+
+    #[test]
+    fn fingerprint_is_stable() {
+        // the fingerprint must not change between releases or point types
+        let forest: RandomCutForest<f32> = RandomCutForestBuilder::new(3)
+            .seed(42)
+            .build();
+        assert_eq!(forest.parameters().fingerprint(), 0x50acdf3d809a7804);
+        let forest: RandomCutForest<f64> = RandomCutForestBuilder::new(3)
+            .seed(42)
+            .build();
+        assert_eq!(forest.parameters().fingerprint(), 0x50acdf3d809a7804);
+
+        // enum payloads are part of the fingerprint
+        let parameters = forest.parameters();
+        let fingerprints = [
+            ForestParameters { cut_strategy: CutStrategy::DimensionSubsample(1), ..parameters.clone() },
+            ForestParameters { cut_strategy: CutStrategy::DimensionSubsample(2), ..parameters.clone() },
+            ForestParameters { non_finite_policy: NonFinitePolicy::Clamp(1.0), ..parameters.clone() },
+            ForestParameters { non_finite_policy: NonFinitePolicy::Clamp(2.0), ..parameters.clone() },
+            ForestParameters { input_transforms: Some(vec![InputTransform::Sqrt; 3]), ..parameters.clone() },
+            ForestParameters { input_transforms: Some(vec![InputTransform::Log1p; 3]), ..parameters },
+        ].iter().map(|parameters| parameters.fingerprint()).collect::<Vec<u64>>();
+        for (i, fingerprint) in fingerprints.iter().enumerate() {
+            assert!(!fingerprints[..i].contains(fingerprint));
+        }
+    }
+}
@@ -0,0 +1,207 @@
+extern crate num_traits;
+use num_traits::{Float, Zero};
+
+use std::iter::Sum;
+
+use crate::random_cut_forest::{active_mask, Neighbor, RandomCutForest};
+use crate::visitor::{AnomalyScoreVisitor, ExpectedDepthVisitor, NearNeighborVisitor};
+
+/// A visitor run by [`RandomCutForest::fused_traversal`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum VisitorKind {
+    /// The anomaly score of the point.
+    Score,
+    /// The expected depth of the point.
+    ExpectedDepth,
+    /// The given number of nearest neighbors of the point.
+    NearestNeighbors(usize),
+}
+
+/// Results of [`RandomCutForest::fused_traversal`], `None` for the visitors
+/// that were not requested.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FusedResult<T> {
+    pub score: Option<T>,
+    pub expected_depth: Option<T>,
+    pub nearest_neighbors: Option<Vec<Neighbor<T>>>,
+}
+
+impl<T> RandomCutForest<T>
+    where T: Float + Sum + Zero
+{
+
+    /// Runs several visitors on the input point in a single traversal of
+    /// each tree and returns their results.
+    ///
+    /// Each requested [`VisitorKind`] gives the same result as the method of
+    /// the same name: [`anomaly_score`](Self::anomaly_score),
+    /// [`expected_depth`](Self::expected_depth) and
+    /// [`nearest_neighbors`](Self::nearest_neighbors), except that the
+    /// pre-filter and latency tracking do not apply. Fields of the result
+    /// that were not requested are `None`, and neighbors requested more than
+    /// once are computed for the largest `k`. As in those methods, empty trees
+    /// are skipped for the expected depth and the neighbors, and the depth is
+    /// the mean over the trees visited. The path of the point in a tree
+    /// is found and its nodes fetched once for all visitors, which saves
+    /// most of the cost of the repeated traversals when several results are
+    /// needed for the same point.
+    ///
+    /// # Panics
+    ///
+    /// If the dimensionality of the point does not match the dimensionality
+    /// of the forest, or under the forest's [`NonFinitePolicy`] as for
+    /// `anomaly_score`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use random_cut_forest::{RandomCutForest, RandomCutForestBuilder, VisitorKind};
+    ///
+    /// let mut forest: RandomCutForest<f32> = RandomCutForestBuilder::new(2)
+    ///     .seed(0)
+    ///     .build();
+    /// for i in 0..200 {
+    ///     forest.update(vec![(i % 10) as f32, (i % 7) as f32]);
+    /// }
+    ///
+    /// let point = [20.0, 3.0];
+    /// let result = forest.fused_traversal(&point, &[VisitorKind::Score, VisitorKind::NearestNeighbors(2)]);
+    /// assert_eq!(result.score, Some(forest.anomaly_score(&point.to_vec())));
+    /// assert_eq!(result.nearest_neighbors, Some(forest.nearest_neighbors(&point, 2)));
+    /// assert_eq!(result.expected_depth, None);
+    /// ```
+    ///
+    /// [`NonFinitePolicy`]: crate::NonFinitePolicy
+    pub fn fused_traversal(&self, point: &[T], kinds: &[VisitorKind]) -> FusedResult<T> {
+        assert_eq!(point.len(), self.dimension,
+            "Dimension mismatch. Expected {}-dimensional input.",
+            self.dimension);
+        let has_score = kinds.contains(&VisitorKind::Score);
+        let has_depth = kinds.contains(&VisitorKind::ExpectedDepth);
+        let num_neighbors = kinds.iter()
+            .filter_map(|kind| match kind {
+                VisitorKind::NearestNeighbors(k) => Some(*k),
+                _ => None,
+            })
+            .max();
+        let mut result = FusedResult {
+            score: if has_score { Some(Zero::zero()) } else { None },
+            expected_depth: if has_depth { Some(Zero::zero()) } else { None },
+            nearest_neighbors: num_neighbors.map(|_| Vec::new()),
+        };
+        if self.num_observations <= self.output_after {
+            return result;
+        }
+        let query = match self.sanitize_query(point) {
+            Ok(Some(query)) => query,
+            Ok(None) => return result,
+            Err(message) => panic!("{}", message),
+        };
+
+        let masked_dimensions = active_mask(&self.masked_dimensions);
+        let mut scores: Vec<T> = Vec::with_capacity(self.trees.len());
+        let mut depths: Vec<T> = Vec::with_capacity(self.trees.len());
+        let mut candidates: Vec<Vec<Vec<T>>> = Vec::with_capacity(self.trees.len());
+        for sampled_tree in self.trees.iter() {
+            let tree = sampled_tree.tree();
+            let is_empty = tree.mass() == 0;
+            if is_empty && !has_score {
+                continue;
+            }
+            let score_visitor = match has_score {
+                true => {
+                    let visitor = AnomalyScoreVisitor::new_with_score_function(tree, &query, &self.score_function);
+                    Some(match masked_dimensions {
+                        Some(masked_dimensions) => visitor.with_masked_dimensions(masked_dimensions),
+                        None => visitor,
+                    })
+                },
+                false => None,
+            };
+            let depth_visitor = match has_depth && !is_empty {
+                true => Some(ExpectedDepthVisitor::new(tree, &query)),
+                false => None,
+            };
+            let neighbor_visitor = num_neighbors
+                .filter(|_| !is_empty)
+                .map(|k| NearNeighborVisitor::new(tree, k));
+            let mut visitors = ((score_visitor, depth_visitor), neighbor_visitor);
+
+            let ((score, depth), tree_candidates) = sampled_tree.traverse(&query, &mut visitors);
+            scores.extend(score);
+            depths.extend(depth);
+            candidates.extend(tree_candidates);
+        }
+
+        if has_score {
+            result.score = Some(self.combine_scores(scores.into_iter()));
+        }
+        if has_depth && !depths.is_empty() {
+            let num_trees = T::from(depths.len()).unwrap();
+            result.expected_depth = Some(depths.into_iter().sum::<T>() / num_trees);
+        }
+        if let Some(k) = num_neighbors {
+            result.nearest_neighbors = Some(self.merge_neighbors(point, candidates.into_iter(), k));
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::random_cut_forest::RandomCutForestBuilder;
+    use crate::random_cut_forest::tests::seeded_randn;
+
+    #[test]
+    fn fused_traversal() {
+        let points = seeded_randn(500, 3, 5);
+        let mut forest: RandomCutForest<f32> = RandomCutForestBuilder::new(3)
+            .num_trees(20)
+            .tree_reliability(0.01)
+            .weighted_voting(true)
+            .seed(5)
+            .build();
+        for point in points.iter() {
+            forest.score_and_update(point.clone()).unwrap();
+        }
+        forest.set_masked_dimensions(vec![false, true, false]);
+
+        let kinds = [VisitorKind::NearestNeighbors(2), VisitorKind::ExpectedDepth, VisitorKind::Score,
+                     VisitorKind::NearestNeighbors(3)];
+        for query in [vec![0.0, 0.0, 0.0], vec![4.0, -1.0, 2.0]].iter() {
+            let result = forest.fused_traversal(query, &kinds);
+            assert_eq!(result.score, Some(forest.anomaly_score(query)));
+            assert_eq!(result.expected_depth, Some(forest.expected_depth(query)));
+            assert_eq!(result.nearest_neighbors, Some(forest.nearest_neighbors(query, 3)));
+        }
+        assert_eq!(forest.fused_traversal(&points[0], &[]),
+                   FusedResult { score: None, expected_depth: None, nearest_neighbors: None });
+
+        // trees emptied by forgetting points are skipped, as by expected_depth
+        // and nearest_neighbors
+        let mut forest: RandomCutForest<f32> = RandomCutForestBuilder::new(3)
+            .num_trees(20)
+            .sample_size(4)
+            .seed(6)
+            .build();
+        for point in points.iter().take(100) {
+            forest.update(point.clone());
+        }
+        forest.forget_points(1..71);
+        let masses: Vec<u32> = forest.trees().iter().map(|tree| tree.tree().mass()).collect();
+        assert!(masses.contains(&0) && masses.iter().any(|&mass| mass > 0), "{:?}", masses);
+        let kinds = [VisitorKind::ExpectedDepth, VisitorKind::NearestNeighbors(2)];
+        for query in [vec![0.0, 0.0, 0.0], vec![4.0, -1.0, 2.0]].iter() {
+            let result = forest.fused_traversal(query, &kinds);
+            assert_eq!(result.expected_depth, Some(forest.expected_depth(query)));
+            assert_eq!(result.nearest_neighbors, Some(forest.nearest_neighbors(query, 2)));
+        }
+
+        forest.forget_points(0..1000);
+        let result = forest.fused_traversal(&points[0], &kinds);
+        assert_eq!(result.expected_depth, Some(0.0));
+        assert_eq!(result.nearest_neighbors, Some(Vec::new()));
+    }
+}
@@ -0,0 +1,3110 @@
+extern crate num_traits;
+use num_traits::{Float, Zero};
+
+extern crate rand;
+use rand::{Rng, RngCore, SeedableRng};
+
+extern crate rand_chacha;
+use rand_chacha::ChaCha8Rng;
+
+extern crate rand_distr;
+use rand_distr::Exp1;
+
+use crate::{BoundingBox, SampledTree};
+use crate::common::{Covariance, FilterDecision, LatencyHistogram, UpdateGovernor, ZScoreFilter};
+use crate::tree::{CutStrategy, TieBreaking};
+use crate::visitor::{AnomalyScoreVisitor, ExpectedDepthVisitor, ScoreFunction};
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::marker::PhantomData;
+use std::iter::Sum;
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+mod forest_parameters;
+pub use forest_parameters::{ConfigWarning, ForestParameters};
+
+mod nearest_neighbors;
+pub use nearest_neighbors::Neighbor;
+
+mod fused_traversal;
+pub use fused_traversal::{FusedResult, VisitorKind};
+
+mod score_distribution;
+pub use score_distribution::ScoreDistribution;
+
+mod sample_summaries;
+pub use sample_summaries::CutSummary;
+
+mod diagnostics;
+pub use diagnostics::{TimedOperation, TreeDivergence, TreeStatistics, ValidationReport};
+
+mod tree_maintenance;
+
+/// Policy for input points containing NaN or infinite coordinates.
+///
+/// A single non-finite coordinate in a stored point makes the range sums of
+/// every bounding box above it non-finite, which breaks the choice of random
+/// cuts and every score computed from the tree. The forest therefore checks
+/// points at its boundary and handles invalid ones according to this policy.
+///
+/// * `Error` - [`RandomCutForest::try_update`] and
+///   [`RandomCutForest::try_anomaly_score`] return an error, while
+///   [`RandomCutForest::update`] and [`RandomCutForest::anomaly_score`]
+///   panic with a descriptive message.
+/// * `Clamp(bound)` - NaN coordinates are replaced by zero and all other
+///   coordinates are clamped to `[-bound, bound]`.
+/// * `Skip` - invalid points are ignored by updates and have an anomaly
+///   score of zero.
+///
+/// # Examples
+///
+/// ```
+/// use random_cut_forest::{NonFinitePolicy, RandomCutForest, RandomCutForestBuilder};
+///
+/// let mut forest: RandomCutForest<f32> = RandomCutForestBuilder::new(2)
+///     .non_finite_policy(NonFinitePolicy::Skip)
+///     .build();
+///
+/// forest.update(vec![0.0, 1.0]);
+/// forest.update(vec![f32::NAN, 1.0]);
+/// assert_eq!(forest.num_observations(), 1);
+/// assert_eq!(forest.num_rejected_points(), 1);
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum NonFinitePolicy {
+    #[default]
+    Error,
+    Clamp(f64),
+    Skip,
+}
+
+/// Variance-stabilizing transformation of an input dimension.
+///
+/// Count-like dimensions, such as request or error counters, have a variance
+/// that grows with their rate. Standardizing them is misleading at low rates,
+/// where a jump from zero to two events looks as large as one from a
+/// thousand to a few thousand. These transformations compress large values
+/// so that deviations have comparable size across rates.
+///
+/// * `Identity` - the coordinate is used as is.
+/// * `Sqrt` - the coordinate `x` is replaced by `sign(x) * sqrt(|x|)`, the
+///   standard stabilizer for Poisson counts.
+/// * `Log1p` - the coordinate `x` is replaced by `sign(x) * ln(1 + |x|)`,
+///   for heavy-tailed counts and rates.
+///
+/// Both transformations are odd, so they are defined and invertible for
+/// every finite input. See [`RandomCutForestBuilder::input_transforms`].
+///
+/// # Examples
+///
+/// ```
+/// use random_cut_forest::InputTransform;
+///
+/// assert_eq!(InputTransform::Sqrt.apply(9.0), 3.0);
+/// assert_eq!(InputTransform::Sqrt.apply(-4.0), -2.0);
+/// let x: f64 = 1000.0;
+/// assert!((InputTransform::Log1p.invert(InputTransform::Log1p.apply(x)) - x).abs() < 1e-9);
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum InputTransform {
+    #[default]
+    Identity,
+    Sqrt,
+    Log1p,
+}
+
+impl InputTransform {
+
+    /// Apply the transformation to a coordinate.
+    pub fn apply<T: Float>(&self, value: T) -> T {
+        match self {
+            InputTransform::Identity => value,
+            InputTransform::Sqrt => value.signum() * value.abs().sqrt(),
+            InputTransform::Log1p => value.signum() * value.abs().ln_1p(),
+        }
+    }
+
+    /// Map a transformed coordinate back to input units.
+    pub fn invert<T: Float>(&self, value: T) -> T {
+        match self {
+            InputTransform::Identity => value,
+            InputTransform::Sqrt => value.signum() * value * value,
+            InputTransform::Log1p => value.signum() * value.abs().exp_m1(),
+        }
+    }
+}
+
+/// Summary of a forest passed to a [`ScoreTransform`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ScoreContext {
+    pub dimension: usize,
+    pub num_trees: usize,
+    pub sample_size: usize,
+    /// Number of points the forest has been updated with.
+    pub num_observations: usize,
+}
+
+/// Configuration of the pre-filter of a forest, see
+/// [`RandomCutForestBuilder::pre_filter`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct PreFilter<T> {
+    /// Initial and largest bound on the z-score of every coordinate of a
+    /// point that skips scoring.
+    pub z_bound: T,
+    /// Score above which an audited point counts as a false negative.
+    pub score_threshold: T,
+    /// Target fraction of false negatives among audited points, in `(0, 1)`.
+    pub false_negative_budget: f64,
+}
+
+/// Source of the arrival times of updates under an [`UpdateRateLimit`].
+pub type Clock = fn() -> Instant;
+
+/// Limit on the rate of updates of a forest, see
+/// [`RandomCutForestBuilder::update_rate_limit`].
+// Clocks compare by address, as score transforms do.
+#[allow(unpredictable_function_pointer_comparisons)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct UpdateRateLimit {
+    /// Number of updates per window above which updates are shed.
+    pub max_updates: usize,
+    pub window: Duration,
+    /// Clock timing the windows, [`Instant::now`] unless a deterministic
+    /// clock is needed, for example in tests. The clock is not serialized
+    /// and is deserialized as `Instant::now`.
+    #[cfg_attr(feature = "serde", serde(skip, default = "system_clock"))]
+    pub clock: Clock,
+}
+
+#[cfg(feature = "serde")]
+fn system_clock() -> Clock { Instant::now }
+
+impl UpdateRateLimit {
+    /// Create a limit of `max_updates` updates per window, timed by the
+    /// system clock.
+    pub fn new(max_updates: usize, window: Duration) -> Self {
+        UpdateRateLimit { max_updates, window, clock: Instant::now }
+    }
+}
+
+/// Transformation of anomaly scores, see
+/// [`RandomCutForestBuilder::score_transform`].
+pub type ScoreTransform<T> = fn(T, &ScoreContext) -> T;
+
+/// A random cut forest model.
+///
+/// Random cut forests are model-free data structures for sketching data
+/// streams. This type is the main interface for training a random cut forest
+/// and using the model for various scoring operations such as anomaly detection.
+///
+/// A random cut forest is a collection of random cut trees; specifically a
+/// collection of [`SampledTree`] structs. An update to a random cut forest
+/// model corresponds to independently updating each `SampledTree` with the
+/// input point. When a scoring algorithm is called, such as anomaly score,
+/// each random cut tree reports a score and these scores are aggegated in
+/// some way particular to the scoring algorithm.
+///
+/// It is recommended to use [`RandomCutForestBuilder`] to create a new
+/// [`RandomCutForest`] model.
+///
+/// # Examples
+///
+/// ```
+/// use random_cut_forest::{RandomCutForest, RandomCutForestBuilder};
+///
+/// // create the default random cut forest on three-dimensional data points
+/// let mut forest: RandomCutForest<f32> = RandomCutForestBuilder::new(3).build();
+///
+/// // update the forest with some data points
+/// forest.update(vec![0.0, 0.0, 0.0]);
+/// forest.update(vec![1.0, 1.0, 1.0]);
+/// forest.update(vec![0.0, 0.5, 0.3]);
+/// forest.update(vec![0.6, 0.0, -0.2]);
+///
+/// // compute anomaly scores
+/// // let score = forest.anomaly_score(vec![0.1, 0.2, 0.3]);
+/// ```
+///
+/// # Precision
+///
+/// Points, bounding boxes, range sums and cut values all use the point type
+/// `T`; there is no mixed precision. Use `RandomCutForest<f64>` for
+/// numerically extreme domains, such as small variations on top of a large
+/// offset, where `f32` cannot distinguish nearby values and separation
+/// probabilities degenerate.
+///
+/// ```
+/// use random_cut_forest::{RandomCutForest, RandomCutForestBuilder};
+///
+/// // timestamps in nanoseconds differ by less than the precision of an f32
+/// let mut forest: RandomCutForest<f64> = RandomCutForestBuilder::new(1).build();
+/// for i in 0..256 {
+///     forest.update(vec![1.6e18 + (i % 16) as f64 * 1000.0]);
+/// }
+/// let normal = forest.anomaly_score(&vec![1.6e18 + 8000.0]);
+/// let anomaly = forest.anomaly_score(&vec![1.6e18 + 100000.0]);
+/// assert!(anomaly > normal);
+/// ```
+///
+/// # Thread safety
+///
+/// A forest is neither `Send` nor `Sync`: trees share their point store
+/// through `Rc<RefCell<_>>` and own boxed random number generators. The
+/// compiler therefore rejects any attempt to update a forest from one thread
+/// while another traverses it, and no runtime check is needed. Within a
+/// thread, the borrow checker prevents an update during a traversal. To
+/// score from several threads, use one forest per thread.
+///
+/// ```compile_fail
+/// use random_cut_forest::{RandomCutForest, RandomCutForestBuilder};
+///
+/// let forest: RandomCutForest<f32> = RandomCutForestBuilder::new(3).build();
+/// std::thread::spawn(move || forest.anomaly_score(&vec![0.0, 0.0, 0.0]));
+/// ```
+pub struct RandomCutForest<T> {
+    dimension: usize,
+    num_observations: usize,
+    sample_size: usize,
+    time_decay: f32,
+    trees: Vec<SampledTree<T>>,
+    output_after: usize,
+    noise_scale: f32,
+    max_score: Option<T>,
+    score_transform: Option<ScoreTransform<T>>,
+    score_function: ScoreFunction,
+    cut_strategy: CutStrategy,
+    tie_breaking: TieBreaking,
+    non_finite_policy: NonFinitePolicy,
+    num_rejected_points: usize,
+    bounds: Option<BoundingBox<T>>,
+    clamp_to_bounds: bool,
+    num_out_of_bounds_points: usize,
+    covariance: Option<Covariance<T>>,
+    input_scaling: Option<(Vec<T>, Vec<T>)>,
+    input_transforms: Option<Vec<InputTransform>>,
+    raw_input_history: usize,
+    raw_inputs: VecDeque<(usize, Vec<T>)>,
+    masked_dimensions: Vec<bool>,
+    tree_reliability: Option<Vec<Covariance<T>>>,
+    weighted_voting: bool,
+    latency: Option<Vec<RefCell<LatencyHistogram>>>,
+    pre_filter: Option<(PreFilter<T>, RefCell<ZScoreFilter<T>>)>,
+    governor: Option<(UpdateRateLimit, UpdateGovernor)>,
+    // number of updates that reached the trees, below num_observations
+    // when updates are shed
+    num_delivered_updates: usize,
+    next_tree_id: usize,
+    seed: Option<u64>,
+    rng: Box<dyn RngCore>,
+}
+
+impl<T> RandomCutForest<T>
+    where T: Float + Sum + Zero
+{
+
+    /// Update a random cut forest with a new data point.
+    ///
+    /// A copy of the data point will be sent to each sampled tree in the forest
+    /// for consideration. Each tree independently decides whether to accept the
+    /// point into its sample.
+    ///
+    /// # Panics
+    ///
+    /// If the dimensionality of the input data point does not match the
+    /// dimensionality of the forest.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use random_cut_forest::{RandomCutForest, RandomCutForestBuilder};
+    ///
+    /// // create a default RCF on two-dimensional data
+    /// let mut forest: RandomCutForest<f32> = RandomCutForestBuilder::new(2).build();
+    ///
+    /// // update the forest with some data
+    /// forest.update(vec![0.0, 0.0]);
+    /// forest.update(vec![1.0, 0.0]);
+    /// forest.update(vec![0.0, 1.0]);
+    /// forest.update(vec![1.0, 1.0]);
+    ///
+    /// // update panics if the point has an incorrect dimensionality/length
+    /// //forest.update(vec![2.0, 3.0, 4.0]);
+    /// ```
+    ///
+    /// Points with NaN or infinite coordinates are handled according to the
+    /// forest's [`NonFinitePolicy`]. With the default policy they cause a
+    /// panic; use [`try_update`](Self::try_update) to handle them as errors.
+    pub fn update(&mut self, point: Vec<T>) {
+        assert_eq!(point.len(), self.dimension,
+            "Dimension mismatch. Expected {}-dimensional input.",
+            self.dimension);
+
+        if let Err(message) = self.try_update(point) {
+            panic!("{}", message);
+        }
+    }
+
+    /// Update a random cut forest with a new data point, returning an error
+    /// instead of panicking if the point is invalid.
+    ///
+    /// An error is returned if the dimensionality of the point does not match
+    /// the dimensionality of the forest, or if the point has NaN or infinite
+    /// coordinates and the forest's [`NonFinitePolicy`] is `Error`. The
+    /// forest is left unchanged in both cases.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use random_cut_forest::{RandomCutForest, RandomCutForestBuilder};
+    ///
+    /// let mut forest: RandomCutForest<f32> = RandomCutForestBuilder::new(2).build();
+    ///
+    /// assert!(forest.try_update(vec![0.0, 1.0]).is_ok());
+    /// assert!(forest.try_update(vec![f32::INFINITY, 1.0]).is_err());
+    /// assert!(forest.try_update(vec![0.0, 1.0, 2.0]).is_err());
+    /// assert_eq!(forest.num_observations(), 1);
+    /// assert_eq!(forest.num_rejected_points(), 1);
+    /// ```
+    pub fn try_update(&mut self, point: Vec<T>) -> Result<(), &'static str> {
+        let start = self.latency_start();
+        let result = self.update_point(point);
+        self.record_latency(TimedOperation::Update, start);
+        result
+    }
+
+    fn update_point(&mut self, point: Vec<T>) -> Result<(), &'static str> {
+        if let Some(point) = self.prepare_update(point)? {
+            if let Some(multiplicity) = self.admit_update() {
+                for tree in self.trees.iter_mut() {
+                    tree.update_weighted(point.clone(), self.num_observations, multiplicity)
+                }
+                self.num_delivered_updates += 1;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the anomaly score of a point and then updates the forest with
+    /// it.
+    ///
+    /// This is equivalent to [`try_anomaly_score`](Self::try_anomaly_score)
+    /// followed by [`try_update`](Self::try_update), as used to score a
+    /// stream, but checks and transforms the point once and updates every
+    /// tree right after it has scored the point, while its nodes are still
+    /// cached.
+    ///
+    /// Scoring is not fused into the insertion walk: an accepted point first
+    /// evicts a sampled point, which changes the tree before the insertion,
+    /// and the score must be computed on the tree before the update. Each
+    /// tree is therefore still traversed to score and then to update. The
+    /// benchmark in `benches/score_and_update.rs` measures both halves:
+    /// scoring is about a fifth of the combined cost, the rest being the
+    /// sampler bookkeeping, deletion and insertion of the update, so fusing
+    /// the walks could not halve the latency.
+    ///
+    /// Points that are skipped under the forest's [`NonFinitePolicy`] have a
+    /// score of zero. Errors are returned, and the forest left unchanged, as
+    /// for `try_update`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use random_cut_forest::{RandomCutForest, RandomCutForestBuilder};
+    ///
+    /// let mut forest: RandomCutForest<f32> = RandomCutForestBuilder::new(2).build();
+    /// for i in 0..100 {
+    ///     let point = vec![(i % 10) as f32, 0.0];
+    ///     let expected = forest.anomaly_score(&point);
+    ///     let score = forest.score_and_update(point).unwrap();
+    ///     assert_eq!(score, expected);
+    /// }
+    /// assert_eq!(forest.num_observations(), 100);
+    /// ```
+    pub fn score_and_update(&mut self, point: Vec<T>) -> Result<T, &'static str> {
+        let start = self.latency_start();
+        let result = self.score_and_update_point(point);
+        self.record_latency(TimedOperation::ScoreAndUpdate, start);
+        result
+    }
+
+    fn score_and_update_point(&mut self, point: Vec<T>) -> Result<T, &'static str> {
+        let query = match point.len() == self.dimension {
+            true => self.sanitize_query(&point).ok().flatten(),
+            false => None,
+        };
+        // points are not scored before output_after, nor when the
+        // pre-filter finds them clearly normal
+        let decision = match (&query, self.num_observations > self.output_after) {
+            (Some(query), true) => self.pre_filter_decision(query),
+            _ => FilterDecision::Skip,
+        };
+
+        // the query is only missing when the update fails or skips the point
+        let (point, query) = match (self.prepare_update(point)?, query) {
+            (Some(point), Some(query)) => (point, query),
+            _ => return Ok(Zero::zero()),
+        };
+
+        let multiplicity = self.admit_update();
+        let masked_dimensions = active_mask(&self.masked_dimensions);
+        let mut tree_scores: Vec<T> = Vec::with_capacity(self.trees.len());
+        for tree in self.trees.iter_mut() {
+            if decision != FilterDecision::Skip {
+                tree_scores.push(tree_anomaly_score(tree, &query, &self.score_function, masked_dimensions));
+            }
+            if let Some(multiplicity) = multiplicity {
+                tree.update_weighted(point.clone(), self.num_observations, multiplicity);
+            }
+        }
+        if multiplicity.is_some() {
+            self.num_delivered_updates += 1;
+        }
+        if decision == FilterDecision::Skip {
+            return Ok(Zero::zero());
+        }
+
+        let anomaly_score = self.combine_scores(tree_scores.iter().cloned());
+        if decision == FilterDecision::Audit {
+            self.record_audit(anomaly_score);
+        }
+        if let Some(reliability) = self.tree_reliability.as_mut() {
+            let mean = tree_scores.iter().cloned().sum::<T>() / T::from(tree_scores.len()).unwrap();
+            for (covariance, score) in reliability.iter_mut().zip(tree_scores.iter()) {
+                covariance.update(&[*score, mean]);
+            }
+        }
+        Ok(anomaly_score)
+    }
+
+    /// Check and transform a point for an update, in this order: non-finite
+    /// policy, dimension bounds, privacy noise, covariance tracking, input
+    /// scaling and pre-filter statistics. Returns the point in the units of
+    /// the trees, or `None` if the point is skipped.
+    fn prepare_update(&mut self, point: Vec<T>) -> Result<Option<Vec<T>>, &'static str> {
+        if point.len() != self.dimension {
+            return Err("Dimension mismatch. Input has the wrong number of coordinates.");
+        }
+        let raw_input = if self.raw_input_history > 0 { Some(point.clone()) } else { None };
+
+        let mut point = if point.iter().all(|value| value.is_finite()) {
+            point
+        } else {
+            self.num_rejected_points += 1;
+            match self.sanitize(point)? {
+                Some(point) => point,
+                None => return Ok(None),
+            }
+        };
+
+        if let Some(bounds) = &self.bounds {
+            if !bounds.contains_point(&point) {
+                self.num_out_of_bounds_points += 1;
+                if self.clamp_to_bounds {
+                    point = clamp_to_box(point, bounds);
+                }
+            }
+        }
+
+        let point = self.perturb(point);
+        if let Some(covariance) = self.covariance.as_mut() {
+            covariance.update(&point);
+        }
+        let point = self.to_forest_units(point);
+        if let Some((_, filter)) = self.pre_filter.as_mut() {
+            filter.get_mut().update(&point);
+        }
+        self.num_observations += 1;
+
+        if let Some(raw_input) = raw_input {
+            if self.raw_inputs.len() == self.raw_input_history {
+                self.raw_inputs.pop_front();
+            }
+            self.raw_inputs.push_back((self.num_observations, raw_input));
+        }
+        Ok(Some(point))
+    }
+
+    /// Decide whether the trees are updated with a point that passed
+    /// [`prepare_update`](Self::prepare_update), returning the multiplicity
+    /// of the point in the samplers, or `None` if the update is shed.
+    fn admit_update(&mut self) -> Option<f32> {
+        match self.governor.as_mut() {
+            Some((limit, governor)) => governor.admit((limit.clock)(), &mut *self.rng)
+                .map(|multiplicity| multiplicity as f32),
+            None => Some(1.0),
+        }
+    }
+
+    /// Update a new forest with a slice of points at once, see
+    /// [`RandomCutForestBuilder::build_from_slice`].
+    fn bulk_update(&mut self, data: &[Vec<T>]) {
+        let mut points: Vec<(usize, Vec<T>)> = Vec::with_capacity(data.len());
+        for point in data {
+            assert_eq!(point.len(), self.dimension,
+                "Dimension mismatch. Expected {}-dimensional input.",
+                self.dimension);
+            match self.prepare_update(point.clone()) {
+                Ok(Some(point)) => points.push((self.num_observations, point)),
+                Ok(None) => (),
+                Err(message) => panic!("{}", message),
+            }
+        }
+        for tree in self.trees.iter_mut() {
+            tree.bulk_load(&points);
+        }
+        self.num_delivered_updates += points.len();
+    }
+
+    /// Returns the anomaly score associated with the input point relative to
+    /// the data used to update the random cut forest model.
+    ///
+    /// Each constituent random cut tree reports an anomaly score based on its
+    /// random sample of observed data. The forest anomaly score is the mean
+    /// of these per-tree scores.
+    ///
+    /// If the number of observations is less than or equal to the output after
+    /// threshold, this function will return 0, meaning that there is not yet
+    /// enough data to determine if this point is truly an anomaly.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use random_cut_forest::{RandomCutForest, RandomCutForestBuilder};
+    ///
+    /// // create a default RCF on two-dimensional data
+    /// let mut rcf: RandomCutForest<f32> = RandomCutForestBuilder::new(2).build();
+    ///
+    /// // train the forest on some vector data
+    /// # let data: Vec<Vec<f32>> = vec![vec![0.0, 0.0], vec![1.0, 1.0]];
+    /// for point in data.iter() {
+    ///     rcf.update(point.clone());
+    /// }
+    ///
+    /// // compute an anomaly score on some query point
+    /// let query = vec![0.5, 0.5];
+    /// let score = rcf.anomaly_score(&query);
+    ///
+    /// // compute the anomaly scores of the training points
+    /// let scores: Vec<f32> = data.iter().map(|p| rcf.anomaly_score(p)).collect();
+    /// ```
+    ///
+    /// Query points with NaN or infinite coordinates are handled according
+    /// to the forest's [`NonFinitePolicy`]. With the default policy they
+    /// cause a panic; use [`try_anomaly_score`](Self::try_anomaly_score) to
+    /// handle them as errors.
+    pub fn anomaly_score(&self, point: &Vec<T>) -> T {
+        match self.try_anomaly_score(point) {
+            Ok(anomaly_score) => anomaly_score,
+            Err(message) => panic!("{}", message),
+        }
+    }
+
+    /// Returns the anomaly score of the input point, or an error if the point
+    /// has NaN or infinite coordinates and the forest's [`NonFinitePolicy`]
+    /// is `Error`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use random_cut_forest::{NonFinitePolicy, RandomCutForest, RandomCutForestBuilder};
+    ///
+    /// let mut forest: RandomCutForest<f32> = RandomCutForestBuilder::new(2).build();
+    /// forest.update(vec![0.0, 0.0]);
+    /// assert!(forest.try_anomaly_score(&vec![f32::NAN, 0.0]).is_err());
+    ///
+    /// let mut forest: RandomCutForest<f32> = RandomCutForestBuilder::new(2)
+    ///     .non_finite_policy(NonFinitePolicy::Clamp(10.0))
+    ///     .build();
+    /// forest.update(vec![0.0, 0.0]);
+    /// forest.update(vec![f32::INFINITY, 0.0]);
+    /// let score = forest.try_anomaly_score(&vec![f32::NEG_INFINITY, 0.0]).unwrap();
+    /// assert!(score.is_finite());
+    /// ```
+    pub fn try_anomaly_score(&self, point: &Vec<T>) -> Result<T, &'static str> {
+        let start = self.latency_start();
+        let result = match point.iter().all(|value| value.is_finite()) {
+            true => Ok(self.score_finite_point(point)),
+            false => self.sanitize(point.clone())
+                .map(|point| point.map_or(Zero::zero(), |point| self.score_finite_point(&point))),
+        };
+        self.record_latency(TimedOperation::Score, start);
+        result
+    }
+
+    /// Start time of a timed operation, if latency tracking is enabled.
+    fn latency_start(&self) -> Option<Instant> {
+        self.latency.as_ref().map(|_| Instant::now())
+    }
+
+    /// Record the latency of an operation started at `start`.
+    fn record_latency(&self, operation: TimedOperation, start: Option<Instant>) {
+        if let (Some(latency), Some(start)) = (&self.latency, start) {
+            latency[operation as usize].borrow_mut().record(start.elapsed());
+        }
+    }
+
+    /// Anomaly score of a point with finite coordinates.
+    fn score_finite_point(&self, point: &Vec<T>) -> T {
+        if let (Some(bounds), true) = (&self.bounds, self.clamp_to_bounds) {
+            if !bounds.contains_point(point) {
+                return self.score_finite_point(&clamp_to_box(point.clone(), bounds));
+            }
+        }
+
+        match self.input_scaling.is_some() || self.input_transforms.is_some() {
+            true => self.score_forest_point(&self.to_forest_units(point.clone())),
+            false => self.score_forest_point(point),
+        }
+    }
+
+    /// Anomaly score of a point in the units of the trees.
+    fn score_forest_point(&self, point: &Vec<T>) -> T {
+        if self.num_observations <= self.output_after {
+            return Zero::zero();
+        }
+        let decision = self.pre_filter_decision(point);
+        if decision == FilterDecision::Skip {
+            return Zero::zero();
+        }
+
+        let masked_dimensions = active_mask(&self.masked_dimensions);
+        let anomaly_score = self.combine_scores(self.trees.iter().map(|sampled_tree| {
+            tree_anomaly_score(sampled_tree, point, &self.score_function, masked_dimensions)
+        }));
+        if decision == FilterDecision::Audit {
+            self.record_audit(anomaly_score);
+        }
+        anomaly_score
+    }
+
+    /// Decision of the pre-filter on a point in the units of the trees, or
+    /// `Score` without a pre-filter.
+    fn pre_filter_decision(&self, point: &[T]) -> FilterDecision {
+        match &self.pre_filter {
+            Some((_, filter)) => filter.borrow_mut().decide(point, active_mask(&self.masked_dimensions)),
+            None => FilterDecision::Score,
+        }
+    }
+
+    /// Report the score of a point audited by the pre-filter.
+    fn record_audit(&self, anomaly_score: T) {
+        if let Some((pre_filter, filter)) = &self.pre_filter {
+            filter.borrow_mut().record_audit(anomaly_score > pre_filter.score_threshold);
+        }
+    }
+
+    /// Combine the anomaly scores of the trees, in order, into the finalized
+    /// score of the forest.
+    ///
+    /// The forest score is the mean of the tree scores or, with weighted
+    /// voting, their mean weighted by tree reliability. Uniform weights are
+    /// used while no tree has a positive reliability.
+    fn combine_scores<I: Iterator<Item=T>>(&self, tree_scores: I) -> T {
+        let reliability = match self.weighted_voting {
+            true => self.tree_reliability.as_ref(),
+            false => None,
+        };
+        let mut sum: T = Zero::zero();
+        let mut weighted_sum: T = Zero::zero();
+        let mut weight_sum: T = Zero::zero();
+        for (i, score) in tree_scores.enumerate() {
+            sum = sum + score;
+            if let Some(reliability) = reliability {
+                let weight = reliability_weight(&reliability[i]);
+                weighted_sum = weighted_sum + weight * score;
+                weight_sum = weight_sum + weight;
+            }
+        }
+
+        let anomaly_score = match weight_sum > Zero::zero() {
+            true => weighted_sum / weight_sum,
+            false => sum / T::from(self.num_trees()).unwrap(),
+        };
+        self.finalize_score(anomaly_score)
+    }
+
+    /// Apply the score transformation and then the upper bound on scores,
+    /// if any, to an anomaly score of the forest.
+    fn finalize_score(&self, anomaly_score: T) -> T {
+        let anomaly_score = match self.score_transform {
+            Some(transform) => transform(anomaly_score, &self.score_context()),
+            None => anomaly_score,
+        };
+        match self.max_score {
+            Some(max_score) => Float::min(anomaly_score, max_score),
+            None => anomaly_score,
+        }
+    }
+
+    /// Apply the non-finite policy and the bounds of the forest to a query
+    /// point. Returns `None` if the point should be skipped.
+    fn sanitize_query(&self, point: &[T]) -> Result<Option<Vec<T>>, &'static str> {
+        let mut point = point.to_vec();
+        if !point.iter().all(|value| value.is_finite()) {
+            point = match self.sanitize(point)? {
+                Some(point) => point,
+                None => return Ok(None),
+            };
+        }
+        if let (Some(bounds), true) = (&self.bounds, self.clamp_to_bounds) {
+            point = clamp_to_box(point, bounds);
+        }
+        Ok(Some(self.to_forest_units(point)))
+    }
+
+    /// Convert a point from input units to the units of the trees by
+    /// applying the input transformations and then the input scaling, if
+    /// any.
+    fn to_forest_units(&self, mut point: Vec<T>) -> Vec<T> {
+        if let Some(transforms) = &self.input_transforms {
+            for (value, transform) in point.iter_mut().zip(transforms.iter()) {
+                *value = transform.apply(*value);
+            }
+        }
+        if let Some((scale, offset)) = &self.input_scaling {
+            for (i, value) in point.iter_mut().enumerate() {
+                *value = (*value - offset[i]) / scale[i];
+            }
+        }
+        point
+    }
+
+    /// Convert a point from the units of the trees back to input units.
+    fn to_input_units(&self, mut point: Vec<T>) -> Vec<T> {
+        for (i, value) in point.iter_mut().enumerate() {
+            *value = self.coordinate_to_input_units(i, *value);
+        }
+        point
+    }
+
+    /// Convert coordinate `i` of a point from the units of the trees back to
+    /// input units.
+    fn coordinate_to_input_units(&self, i: usize, mut value: T) -> T {
+        if let Some((scale, offset)) = &self.input_scaling {
+            value = value * scale[i] + offset[i];
+        }
+        if let Some(transforms) = &self.input_transforms {
+            value = transforms[i].invert(value);
+        }
+        value
+    }
+
+    /// Apply the non-finite policy to a point with NaN or infinite
+    /// coordinates. Returns the clamped point, `None` if the point should be
+    /// skipped, or an error.
+    fn sanitize(&self, mut point: Vec<T>) -> Result<Option<Vec<T>>, &'static str> {
+        match self.non_finite_policy {
+            NonFinitePolicy::Error => Err("Input point contains NaN or infinite coordinates."),
+            NonFinitePolicy::Skip => Ok(None),
+            NonFinitePolicy::Clamp(bound) => {
+                let bound = T::from(bound).unwrap();
+                for value in point.iter_mut() {
+                    *value = if value.is_nan() {
+                        Zero::zero()
+                    } else {
+                        Float::max(Float::min(*value, bound), -bound)
+                    };
+                }
+                Ok(Some(point))
+            }
+        }
+    }
+
+    /// Returns the anomaly score of a group of points scored jointly.
+    ///
+    /// Once a short burst of similar points is retained by the forest, its
+    /// points mask each other and each looks normal while the burst as a
+    /// whole is unusual. The group score is the expected number of points
+    /// displaced by inserting the whole group into each tree with the group
+    /// points removed, computed with shadow boxes. See
+    /// [`Tree::group_displacement`](crate::Tree::group_displacement). As for
+    /// [`ScoreFunction::Displacement`], the displacement is normalized by
+    /// the mass of the tree and averaged across trees, so the score of a
+    /// single point that is not retained equals its displacement score.
+    ///
+    /// The points are checked like query points of
+    /// [`anomaly_score`](Self::anomaly_score), including the
+    /// [`NonFinitePolicy`] of the forest. The score is zero if the group is
+    /// empty or if there is not yet enough data.
+    ///
+    /// # Panics
+    ///
+    /// If the dimensionality of a point does not match the dimensionality of
+    /// the forest.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use random_cut_forest::{RandomCutForest, RandomCutForestBuilder};
+    ///
+    /// let mut forest: RandomCutForest<f32> = RandomCutForestBuilder::new(2)
+    ///     .num_trees(20)
+    ///     .sample_size(64)
+    ///     .build();
+    /// for i in 0..200 {
+    ///     forest.update(vec![(i % 10) as f32, (i % 7) as f32]);
+    /// }
+    ///
+    /// let normal = vec![vec![2.0, 3.0], vec![4.0, 5.0]];
+    /// let burst = vec![vec![20.0, 20.0], vec![21.0, 20.0], vec![20.0, 21.0]];
+    /// assert!(forest.score_group(&burst) > forest.score_group(&normal));
+    /// ```
+    pub fn score_group(&self, points: &[Vec<T>]) -> T {
+        if self.num_observations <= self.output_after {
+            return Zero::zero();
+        }
+
+        let mut group: Vec<Vec<T>> = Vec::with_capacity(points.len());
+        for point in points.iter() {
+            assert_eq!(point.len(), self.dimension,
+                "Dimension mismatch. Expected {}-dimensional input.",
+                self.dimension);
+            match self.sanitize_query(point) {
+                Ok(Some(point)) => group.push(point),
+                Ok(None) => {},
+                Err(message) => panic!("{}", message),
+            }
+        }
+        if group.is_empty() {
+            return Zero::zero();
+        }
+
+        let mut score: T = Zero::zero();
+        for sampled_tree in self.trees.iter() {
+            let tree = sampled_tree.tree();
+            let displacement = tree.group_displacement(&group);
+            score = score + ScoreFunction::Displacement.normalize(displacement, tree.mass());
+        }
+        self.finalize_score(score / T::from(self.num_trees()).unwrap())
+    }
+
+    /// Returns the anomaly score of the input point in each tree of the
+    /// forest, tagged with the tree's identifier.
+    ///
+    /// These are the raw per-tree scores, before weighting, transform and
+    /// upper bound: [`anomaly_score`](Self::anomaly_score) is their mean only
+    /// without weighted voting, a score transform or a maximum score. The
+    /// identifiers, see [`SampledTree::id`], let debugging tools follow a
+    /// specific tree, for example one that consistently disagrees with the
+    /// others. Trees are numbered from zero in construction order. The result
+    /// is empty if there is not yet enough data or if the point is skipped
+    /// under the forest's [`NonFinitePolicy`].
+    ///
+    /// # Panics
+    ///
+    /// If the dimensionality of the point does not match the dimensionality
+    /// of the forest.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use random_cut_forest::{RandomCutForest, RandomCutForestBuilder};
+    ///
+    /// let mut forest: RandomCutForest<f32> = RandomCutForestBuilder::new(2)
+    ///     .num_trees(10)
+    ///     .build();
+    /// for i in 0..100 {
+    ///     forest.update(vec![(i % 10) as f32, 0.0]);
+    /// }
+    ///
+    /// let point = vec![5.0, 3.0];
+    /// let tree_scores = forest.tree_scores(&point);
+    /// let ids: Vec<usize> = tree_scores.iter().map(|(id, _)| *id).collect();
+    /// assert_eq!(ids, (0..10).collect::<Vec<usize>>());
+    ///
+    /// let mean = tree_scores.iter().map(|(_, score)| score).sum::<f32>() / 10.0;
+    /// assert!((mean - forest.anomaly_score(&point)).abs() < 1e-5);
+    /// ```
+    pub fn tree_scores(&self, point: &[T]) -> Vec<(usize, T)> {
+        assert_eq!(point.len(), self.dimension,
+            "Dimension mismatch. Expected {}-dimensional input.",
+            self.dimension);
+        if self.num_observations <= self.output_after {
+            return Vec::new();
+        }
+        let point = match self.sanitize_query(point) {
+            Ok(Some(point)) => point,
+            Ok(None) => return Vec::new(),
+            Err(message) => panic!("{}", message),
+        };
+
+        let masked_dimensions = active_mask(&self.masked_dimensions);
+        self.trees.iter()
+            .map(|tree| {
+                (tree.id(), tree_anomaly_score(tree, &point, &self.score_function, masked_dimensions))
+            })
+            .collect()
+    }
+
+    /// Returns the expected depth of the input point in the trees, averaged
+    /// over the trees.
+    ///
+    /// This is the path length score of an isolation forest, computed on the
+    /// trees of this forest with the separation probabilities of the RCF
+    /// score, see [`ExpectedDepthVisitor`]. Outliers have a small depth.
+    /// Masked dimensions are not ignored. Returns zero if there is not yet
+    /// enough data or if the point is skipped under the forest's
+    /// [`NonFinitePolicy`].
+    ///
+    /// # Panics
+    ///
+    /// If the dimensionality of the point does not match the dimensionality
+    /// of the forest.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use random_cut_forest::{RandomCutForest, RandomCutForestBuilder};
+    /// use random_cut_forest::visitor::average_path_length;
+    ///
+    /// let mut forest: RandomCutForest<f32> = RandomCutForestBuilder::new(2)
+    ///     .sample_size(64)
+    ///     .seed(0)
+    ///     .build();
+    /// for i in 0..500 {
+    ///     forest.update(vec![(i % 10) as f32, (i % 7) as f32]);
+    /// }
+    ///
+    /// // the isolation forest score of a point
+    /// let isolation_score = |point: &[f32]| {
+    ///     let depth = forest.expected_depth(point) as f64;
+    ///     2f64.powf(-depth / average_path_length(forest.sample_size()))
+    /// };
+    /// assert!(forest.expected_depth(&[50.0, 0.0]) < forest.expected_depth(&[5.0, 3.0]));
+    /// assert!(isolation_score(&[50.0, 0.0]) > 0.7);
+    /// ```
+    pub fn expected_depth(&self, point: &[T]) -> T {
+        assert_eq!(point.len(), self.dimension,
+            "Dimension mismatch. Expected {}-dimensional input.",
+            self.dimension);
+        if self.num_observations <= self.output_after {
+            return Zero::zero();
+        }
+        let query = match self.sanitize_query(point) {
+            Ok(Some(query)) => query,
+            Ok(None) => return Zero::zero(),
+            Err(message) => panic!("{}", message),
+        };
+
+        let depths: Vec<T> = self.trees.iter()
+            .filter(|tree| tree.tree().mass() > 0)
+            .map(|tree| tree.traverse(&query, &mut ExpectedDepthVisitor::new(tree.tree(), &query)))
+            .collect();
+        match depths.len() {
+            0 => Zero::zero(),
+            num_trees => depths.into_iter().sum::<T>() / T::from(num_trees).unwrap(),
+        }
+    }
+
+    /// Returns the dimensions in which the point lies outside of the valid
+    /// ranges set with [`RandomCutForestBuilder::dimension_bounds`].
+    ///
+    /// Values outside of physical limits usually indicate faulty sensors or
+    /// corrupted records rather than statistical anomalies. This flags them
+    /// independently of the anomaly score. The result is empty if no bounds
+    /// are set.
+    ///
+    /// # Panics
+    ///
+    /// If the dimensionality of the point does not match the dimensionality
+    /// of the forest.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use random_cut_forest::{RandomCutForest, RandomCutForestBuilder};
+    ///
+    /// let forest: RandomCutForest<f32> = RandomCutForestBuilder::new(3)
+    ///     .dimension_bounds(vec![0.0, -1.0, 0.0], vec![100.0, 1.0, 1.0])
+    ///     .build();
+    ///
+    /// assert!(forest.out_of_bounds_dimensions(&vec![50.0, 0.0, 0.5]).is_empty());
+    /// assert_eq!(forest.out_of_bounds_dimensions(&vec![-5.0, 0.0, 2.0]), vec![0, 2]);
+    /// ```
+    pub fn out_of_bounds_dimensions(&self, point: &[T]) -> Vec<usize> {
+        assert_eq!(point.len(), self.dimension,
+            "Dimension mismatch. Expected {}-dimensional input.",
+            self.dimension);
+        match &self.bounds {
+            Some(bounds) => (0..self.dimension)
+                .filter(|&i| !(bounds.min_values()[i] <= point[i] && point[i] <= bounds.max_values()[i]))
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Returns the number of bytes used by the forest.
+    ///
+    /// This is the size of the forest itself and of all heap memory it owns:
+    /// the trees with their node stores, bounding boxes, point stores and
+    /// samplers, the random number generators, and optional state such as
+    /// the dimension bounds, the covariance estimator, the input scaling, the
+    /// raw input history or the latency histograms. Allocated capacity is
+    /// counted rather than used length, so the result reflects the memory
+    /// actually requested from the allocator, up to the allocator's own
+    /// bookkeeping. Use it for capacity planning.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use random_cut_forest::{RandomCutForest, RandomCutForestBuilder};
+    ///
+    /// let mut forest: RandomCutForest<f32> = RandomCutForestBuilder::new(4)
+    ///     .num_trees(10)
+    ///     .sample_size(64)
+    ///     .build();
+    /// let empty_size = forest.size_in_bytes();
+    ///
+    /// for i in 0..100 {
+    ///     forest.update(vec![i as f32; 4]);
+    /// }
+    ///
+    /// // each tree stores at least 64 points of 4 coordinates
+    /// assert!(forest.size_in_bytes() > empty_size + 10 * 64 * 4 * std::mem::size_of::<f32>());
+    /// ```
+    pub fn size_in_bytes(&self) -> usize {
+        let trees: usize = self.trees.iter().map(|tree| tree.heap_size()).sum();
+        let bounds = self.bounds.as_ref().map_or(0, |bounds| bounds.heap_size());
+        let covariance = self.covariance.as_ref().map_or(0, |covariance| covariance.heap_size());
+        let input_scaling = self.input_scaling.as_ref().map_or(0, |(scale, offset)| {
+            (scale.capacity() + offset.capacity()) * std::mem::size_of::<T>()
+        });
+        let input_transforms = self.input_transforms.as_ref()
+            .map_or(0, |transforms| transforms.capacity() * std::mem::size_of::<InputTransform>());
+        let tree_reliability = self.tree_reliability.as_ref().map_or(0, |reliability| {
+            reliability.capacity() * std::mem::size_of::<Covariance<T>>() +
+                reliability.iter().map(|covariance| covariance.heap_size()).sum::<usize>()
+        });
+        let raw_inputs: usize = self.raw_inputs.iter()
+            .map(|(_, point)| point.capacity() * std::mem::size_of::<T>())
+            .sum();
+        let pre_filter = self.pre_filter.as_ref().map_or(0, |(_, filter)| filter.borrow().heap_size());
+        let latency = self.latency.as_ref().map_or(0, |latency| {
+            latency.capacity() * std::mem::size_of::<RefCell<LatencyHistogram>>() +
+                latency.iter().map(|histogram| histogram.borrow().heap_size()).sum::<usize>()
+        });
+
+        std::mem::size_of::<Self>() +
+            self.trees.capacity() * std::mem::size_of::<SampledTree<T>>() + trees +
+            std::mem::size_of_val(self.rng.as_ref()) + bounds + covariance + input_scaling + input_transforms +
+            self.raw_inputs.capacity() * std::mem::size_of::<(usize, Vec<T>)>() + raw_inputs +
+            self.masked_dimensions.capacity() * std::mem::size_of::<bool>() + tree_reliability + pre_filter +
+            latency
+    }
+
+    /// Add Laplace noise to each coordinate of a point if privacy noise is
+    /// enabled. Otherwise, the point is returned unchanged.
+    ///
+    /// A Laplace sample with scale `b` is an exponential sample with mean `b`
+    /// and a random sign.
+    fn perturb(&mut self, mut point: Vec<T>) -> Vec<T> {
+        if self.noise_scale > 0.0 {
+            let scale = self.noise_scale as f64;
+            for value in point.iter_mut() {
+                let magnitude: f64 = self.rng.sample(Exp1);
+                let noise = if self.rng.gen::<bool>() { magnitude } else { -magnitude };
+                *value = *value + T::from(scale * noise).unwrap();
+            }
+        }
+        point
+    }
+
+    /// Return the dimension of the data accepted by this random cut forest.
+    pub fn dimension(&self) -> usize { self.dimension }
+
+    /// Return the decay factor of the random samplers used by the forest's trees.
+    pub fn time_decay(&self) -> f32 { self.time_decay }
+
+    /// Change the time decay of every tree without rebuilding the forest.
+    ///
+    /// The sampler weights of the retained points are rescaled consistently,
+    /// see [`StreamSampler::set_time_decay`](crate::StreamSampler::set_time_decay),
+    /// so the forest switches between long memory and fast forgetting from
+    /// the next update on, for example around a maintenance window. Per-tree
+    /// time decays set with
+    /// [`RandomCutForestBuilder::tree_time_decays`] are replaced.
+    ///
+    /// # Panics
+    ///
+    /// If `time_decay` is negative or not finite.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use random_cut_forest::{RandomCutForest, RandomCutForestBuilder};
+    ///
+    /// let mut forest: RandomCutForest<f32> = RandomCutForestBuilder::new(1)
+    ///     .num_trees(10)
+    ///     .sample_size(64)
+    ///     .seed(6)
+    ///     .build();
+    /// for i in 0..2000 {
+    ///     forest.update(vec![(i % 10) as f32]);
+    /// }
+    ///
+    /// // forget quickly from now on
+    /// forest.set_time_decay(0.05);
+    /// assert_eq!(forest.time_decay(), 0.05);
+    /// for i in 0..500 {
+    ///     forest.update(vec![(i % 10) as f32]);
+    /// }
+    /// assert!(forest.sample_ages().iter().all(|(_, age)| *age < 200.0));
+    /// ```
+    pub fn set_time_decay(&mut self, time_decay: f32) {
+        assert!(time_decay >= 0.0 && time_decay.is_finite(),
+            "Time decay must be non-negative and finite");
+        for tree in self.trees.iter_mut() {
+            tree.set_time_decay(time_decay);
+        }
+        self.time_decay = time_decay;
+    }
+
+    /// Return the total number of observations made by this forest.
+    pub fn num_observations(&self) -> usize { self.num_observations }
+
+    /// Return the number of trees in this forest.
+    pub fn num_trees(&self) -> usize { self.trees.len() }
+
+    /// Return the number of samples/observations stored in each tree.
+    pub fn sample_size(&self) -> usize { self.sample_size }
+
+    /// Return a vector of references to the trees of the forest.
+    pub fn trees(&self) -> &Vec<SampledTree<T>> { &self.trees }
+
+    /// Return the output after threshold for this forest.
+    pub fn output_after(&self) -> usize { self.output_after }
+
+    /// Return the scale of the Laplace noise added to stored points. A scale
+    /// of zero means that points are stored exactly.
+    pub fn noise_scale(&self) -> f32 { self.noise_scale }
+
+    /// Return the upper bound on reported anomaly scores, if any.
+    pub fn max_score(&self) -> Option<T> { self.max_score }
+
+    /// Return the transformation applied to anomaly scores, if any.
+    pub fn score_transform(&self) -> Option<ScoreTransform<T>> { self.score_transform }
+
+    /// Return the model summary passed to the score transformation.
+    pub fn score_context(&self) -> ScoreContext {
+        ScoreContext {
+            dimension: self.dimension,
+            num_trees: self.num_trees(),
+            sample_size: self.sample_size,
+            num_observations: self.num_observations,
+        }
+    }
+
+    /// Return the score function used by [`anomaly_score`](Self::anomaly_score).
+    pub fn score_function(&self) -> &ScoreFunction { &self.score_function }
+
+    /// Return the strategy used by the trees to choose random cuts.
+    pub fn cut_strategy(&self) -> CutStrategy { self.cut_strategy }
+
+    /// Return the tie breaking policy of the cuts in the trees.
+    pub fn tie_breaking(&self) -> TieBreaking { self.tie_breaking }
+
+    /// Return the policy for input points with NaN or infinite coordinates.
+    pub fn non_finite_policy(&self) -> NonFinitePolicy { self.non_finite_policy }
+
+    /// Return the number of points with NaN or infinite coordinates passed to
+    /// [`update`](Self::update), whether they were skipped, clamped, or
+    /// returned as errors.
+    pub fn num_rejected_points(&self) -> usize { self.num_rejected_points }
+
+    /// Return the valid ranges of the input dimensions, if any.
+    pub fn bounds(&self) -> Option<&BoundingBox<T>> { self.bounds.as_ref() }
+
+    /// Return true if points outside of the valid ranges are clamped to them.
+    pub fn clamp_to_bounds(&self) -> bool { self.clamp_to_bounds }
+
+    /// Return the number of points passed to [`update`](Self::update) that
+    /// were outside of the valid ranges of the input dimensions.
+    pub fn num_out_of_bounds_points(&self) -> usize { self.num_out_of_bounds_points }
+
+    /// Return the per-dimension transformations applied to input points, if
+    /// any. See [`RandomCutForestBuilder::input_transforms`].
+    pub fn input_transforms(&self) -> Option<&[InputTransform]> { self.input_transforms.as_deref() }
+
+    /// Return the per-dimension scale and offset applied to input points, if
+    /// any. See [`RandomCutForestBuilder::input_scaling`].
+    pub fn input_scaling(&self) -> Option<(&Vec<T>, &Vec<T>)> {
+        self.input_scaling.as_ref().map(|(scale, offset)| (scale, offset))
+    }
+
+    /// Return the covariance of the points added to the forest, if tracked.
+    ///
+    /// See [`RandomCutForestBuilder::track_covariance`].
+    pub fn covariance(&self) -> Option<&Covariance<T>> { self.covariance.as_ref() }
+
+    /// Mask or unmask input dimensions, for example while the feed of a
+    /// dimension is known to be broken.
+    ///
+    /// Dimension `i` is masked if `masked_dimensions[i]` is true. Masked
+    /// dimensions are treated as missing by
+    /// [`anomaly_score`](Self::anomaly_score): they neither separate a query
+    /// point from the points in the trees nor distinguish it from them. New
+    /// nodes in the trees avoid cutting masked dimensions. Points are still
+    /// stored with all of their coordinates, and unmasking a dimension takes
+    /// effect immediately, without rebuilding the forest.
+    ///
+    /// # Panics
+    ///
+    /// If the length of `masked_dimensions` does not match the dimension of
+    /// the forest.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use random_cut_forest::{RandomCutForest, RandomCutForestBuilder};
+    ///
+    /// let mut forest: RandomCutForest<f32> = RandomCutForestBuilder::new(2).build();
+    /// for i in 0..100 {
+    ///     forest.update(vec![(i % 10) as f32, 0.0]);
+    /// }
+    ///
+    /// // the second sensor reports garbage
+    /// let point = vec![5.0, 1000.0];
+    /// assert!(forest.anomaly_score(&point) > 1.0);
+    ///
+    /// forest.set_masked_dimensions(vec![false, true]);
+    /// assert!(forest.anomaly_score(&point) < 1.0);
+    /// ```
+    pub fn set_masked_dimensions(&mut self, masked_dimensions: Vec<bool>) {
+        assert_eq!(masked_dimensions.len(), self.dimension,
+            "Dimension mismatch. Expected {} masked dimension flags.", self.dimension);
+        for tree in self.trees.iter_mut() {
+            tree.set_masked_dimensions(masked_dimensions.clone());
+        }
+        self.masked_dimensions = masked_dimensions;
+    }
+
+    /// Return the masked input dimensions. See
+    /// [`set_masked_dimensions`](Self::set_masked_dimensions).
+    pub fn masked_dimensions(&self) -> &[bool] { &self.masked_dimensions }
+
+    /// Return the reliability of each tree, tagged with the tree's
+    /// identifier, if tracked.
+    ///
+    /// The reliability of a tree is the decayed correlation between its
+    /// anomaly scores and the mean score of all trees over the points passed
+    /// to [`score_and_update`](Self::score_and_update), or zero if negative.
+    /// Degenerate trees, such as trees whose sample holds few distinct
+    /// points, have a low reliability. See
+    /// [`RandomCutForestBuilder::tree_reliability`].
+    pub fn tree_reliability(&self) -> Option<Vec<(usize, T)>> {
+        self.tree_reliability.as_ref().map(|reliability| {
+            self.trees.iter()
+                .zip(reliability.iter())
+                .map(|(tree, covariance)| (tree.id(), reliability_weight(covariance)))
+                .collect()
+        })
+    }
+
+    /// Returns a copy of the state of the pre-filter, or `None` if the
+    /// forest has no pre-filter.
+    ///
+    /// See [`RandomCutForestBuilder::pre_filter`].
+    pub fn pre_filter_state(&self) -> Option<ZScoreFilter<T>> {
+        self.pre_filter.as_ref().map(|(_, filter)| filter.borrow().clone())
+    }
+
+    /// Returns the governor of the rate of updates, or `None` if updates
+    /// are not rate limited.
+    ///
+    /// See [`RandomCutForestBuilder::update_rate_limit`].
+    pub fn update_governor(&self) -> Option<&UpdateGovernor> {
+        self.governor.as_ref().map(|(_, governor)| governor)
+    }
+
+    /// Return true if tree scores are weighted by reliability.
+    pub fn weighted_voting(&self) -> bool { self.weighted_voting }
+
+    /// Return the most recent points added to the forest, oldest first, as
+    /// they were passed to [`update`](Self::update), each with its
+    /// observation number. See [`RandomCutForestBuilder::raw_input_history`].
+    pub fn raw_inputs(&self) -> &VecDeque<(usize, Vec<T>)> { &self.raw_inputs }
+
+}
+
+/// Anomaly score of a point, in the units of the trees, in a single tree.
+fn tree_anomaly_score<T: Float + Sum>(
+    sampled_tree: &SampledTree<T>,
+    point: &Vec<T>,
+    score_function: &ScoreFunction,
+    masked_dimensions: Option<&[bool]>,
+) -> T {
+    let mut visitor = AnomalyScoreVisitor::new_with_score_function(
+        sampled_tree.tree(), point, score_function);
+    if let Some(masked_dimensions) = masked_dimensions {
+        visitor = visitor.with_masked_dimensions(masked_dimensions);
+    }
+    sampled_tree.traverse(point, &mut visitor)
+}
+
+/// Reliability weight of a tree from the covariance of its scores and the
+/// mean scores of the forest.
+fn reliability_weight<T: Float>(covariance: &Covariance<T>) -> T {
+    Float::max(covariance.correlation(0, 1), Zero::zero())
+}
+
+/// Returns the mask of the dimensions if at least one dimension is masked.
+fn active_mask(masked_dimensions: &[bool]) -> Option<&[bool]> {
+    match masked_dimensions.iter().any(|&m| m) {
+        true => Some(masked_dimensions),
+        false => None,
+    }
+}
+
+/// Clamp each coordinate of a point to the range of a bounding box.
+fn clamp_to_box<T: Float + Sum>(mut point: Vec<T>, bounding_box: &BoundingBox<T>) -> Vec<T> {
+    let min = bounding_box.min_values();
+    let max = bounding_box.max_values();
+    for (i, value) in point.iter_mut().enumerate() {
+        *value = Float::max(Float::min(*value, max[i]), min[i]);
+    }
+    point
+}
+
+
+/// Convenient mechanism for creating [`RandomCutForest`]s.
+///
+/// Random cut forests are highly configurable and come with a large number of
+/// parameters, many of which have reasonable default values. This builder
+/// makes it easier to construct a random cut forest model
+///
+/// The builder has the following required parameters for initialization:
+///
+/// * `dimension`
+///
+/// The builder uses the following defaults for the remaining parameters:
+///
+/// * `num_trees = 50`
+/// * `sample_size = 256`, the same for every tree
+/// * `time_decay = 0.0`, the same for every tree
+/// * `output_after = 0`
+/// * no privacy noise and no bound on anomaly scores
+/// * `score_function = ScoreFunction::Standard`
+/// * `cut_strategy = CutStrategy::RangeProportional`
+/// * `tie_breaking = TieBreaking::Left`
+/// * `non_finite_policy = NonFinitePolicy::Error`
+/// * no bounds on the input dimensions
+/// * the covariance of the input dimensions is not tracked
+/// * no input scaling and no input transformations
+/// * no raw input history
+/// * tree reliability is not tracked and trees have equal weights
+/// * no score transformation
+/// * latency is not tracked
+/// * no pre-filter, every point is scored
+/// * updates are not rate limited
+/// * random number generators are seeded from system entropy
+///
+/// # Examples
+///
+/// ```
+/// use random_cut_forest::{RandomCutForest, RandomCutForestBuilder};
+///
+/// // create the default random cut forest on three-dimensional data points
+/// let dimension = 3;
+/// let forest = RandomCutForestBuilder::<f32>::new(dimension).build();
+/// assert_eq!(forest.dimension(), 3);
+/// assert_eq!(forest.num_trees(), 50);
+/// assert_eq!(forest.sample_size(), 256);
+/// assert_eq!(forest.time_decay(), 0.0);
+/// assert_eq!(forest.output_after(), 0);
+///
+/// // create a forest with specified parameters. you can also specify the base
+/// // type by annotating the target variable
+/// let forest: RandomCutForest<f32> = RandomCutForestBuilder::new(dimension)
+///     .num_trees(20)
+///     .sample_size(128)
+///     .time_decay(0.01)
+///     .output_after(100)
+///     .build();
+/// assert_eq!(forest.dimension(), 3);
+/// assert_eq!(forest.num_trees(), 20);
+/// assert_eq!(forest.sample_size(), 128);
+/// assert_eq!(forest.time_decay(), 0.01);
+/// assert_eq!(forest.output_after(), 100);
+/// ```
+///
+pub struct RandomCutForestBuilder<T> {
+    dimension: usize,
+    num_trees: usize,
+    sample_size: usize,
+    tree_sample_sizes: Option<Vec<usize>>,
+    time_decay: f32,
+    tree_time_decays: Option<Vec<f32>>,
+    _point_type: PhantomData<T>,
+    output_after: usize,
+    noise_scale: f32,
+    max_score: Option<T>,
+    score_transform: Option<ScoreTransform<T>>,
+    score_function: ScoreFunction,
+    cut_strategy: CutStrategy,
+    tie_breaking: TieBreaking,
+    non_finite_policy: NonFinitePolicy,
+    bounds: Option<(Vec<T>, Vec<T>)>,
+    clamp_to_bounds: bool,
+    covariance_decay: Option<T>,
+    input_scaling: Option<(Vec<T>, Vec<T>)>,
+    input_transforms: Option<Vec<InputTransform>>,
+    raw_input_history: usize,
+    tree_reliability_decay: Option<T>,
+    weighted_voting: bool,
+    track_latency: bool,
+    pre_filter: Option<PreFilter<T>>,
+    update_rate_limit: Option<UpdateRateLimit>,
+    seed: Option<u64>,
+    rng_source: Option<RngSource>,
+}
+
+/// A function returning the random number generator with a given index.
+type RngSource = Box<dyn Fn(usize) -> Box<dyn RngCore>>;
+
+impl<T> RandomCutForestBuilder<T>
+    where T: Float + Sum
+{
+
+    /// Initialize a random cut forest builder.
+    ///
+    /// The primary required parameter is the dimensionality of the forest.
+    /// Reasonable defaults are used for other parameters.
+    pub fn new(dimension: usize) -> RandomCutForestBuilder<T> {
+        RandomCutForestBuilder {
+            dimension: dimension,
+            time_decay: 0.0,
+            num_trees: 50,
+            sample_size: 256,
+            tree_sample_sizes: None,
+            tree_time_decays: None,
+            _point_type: PhantomData::<T>,
+            output_after: 0,
+            noise_scale: 0.0,
+            max_score: None,
+            score_transform: None,
+            score_function: ScoreFunction::Standard,
+            cut_strategy: CutStrategy::RangeProportional,
+            tie_breaking: TieBreaking::Left,
+            non_finite_policy: NonFinitePolicy::Error,
+            bounds: None,
+            clamp_to_bounds: false,
+            covariance_decay: None,
+            input_scaling: None,
+            input_transforms: None,
+            raw_input_history: 0,
+            tree_reliability_decay: None,
+            weighted_voting: false,
+            track_latency: false,
+            pre_filter: None,
+            update_rate_limit: None,
+            seed: None,
+            rng_source: None,
+        }
+    }
+
+    /// Set the dimension of the random cut forest.
+    pub fn dimension(mut self, dimension: usize) -> RandomCutForestBuilder<T> {
+        self.dimension = dimension;
+        self
+    }
+
+    /// Set the number of trees used in the random cut forest.
+    pub fn num_trees(mut self, num_trees: usize) -> RandomCutForestBuilder<T> {
+        self.num_trees = num_trees;
+        self
+    }
+
+    /// Set the number of samples retained by each tree in the random cut forest.
+    pub fn sample_size(mut self, sample_size: usize) -> RandomCutForestBuilder<T> {
+        self.sample_size = sample_size;
+        self
+    }
+
+    /// Set the sample size of each tree, making a forest of trees with
+    /// different capacities.
+    ///
+    /// Small trees adapt quickly to changes in the stream while large trees
+    /// give stable scores; a mix of both balances reaction speed and
+    /// stability in one model. The number of trees is set to the number of
+    /// sample sizes. Scores are normalized by the mass of each tree, so trees
+    /// of different sizes contribute comparable scores to the average. The
+    /// forest's [`sample_size`](RandomCutForest::sample_size) remains the
+    /// value set by [`sample_size`](Self::sample_size).
+    ///
+    /// # Panics
+    ///
+    /// If `sample_sizes` is empty or contains zero.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use random_cut_forest::{RandomCutForest, RandomCutForestBuilder};
+    ///
+    /// // a few large slow trees and many small fast ones
+    /// let mut sample_sizes = vec![512; 5];
+    /// sample_sizes.extend(vec![32; 20]);
+    /// let mut forest: RandomCutForest<f32> = RandomCutForestBuilder::new(2)
+    ///     .tree_sample_sizes(sample_sizes.clone())
+    ///     .seed(7)
+    ///     .build();
+    /// assert_eq!(forest.num_trees(), 25);
+    /// assert_eq!(forest.parameters().tree_sample_sizes, Some(sample_sizes));
+    ///
+    /// for i in 0..1000 {
+    ///     forest.update(vec![(i % 10) as f32, (i % 7) as f32]);
+    /// }
+    /// assert!(forest.anomaly_score(&vec![50.0, 50.0]) > forest.anomaly_score(&vec![4.0, 3.0]));
+    /// ```
+    pub fn tree_sample_sizes(mut self, sample_sizes: Vec<usize>) -> RandomCutForestBuilder<T> {
+        assert!(!sample_sizes.is_empty(), "At least one tree sample size is required");
+        assert!(!sample_sizes.contains(&0), "Tree sample sizes must be positive");
+        self.num_trees = sample_sizes.len();
+        self.tree_sample_sizes = Some(sample_sizes);
+        self
+    }
+
+    /// Set the random sampling decay factor of the random cut forest.
+    pub fn time_decay(mut self, time_decay: f32) -> RandomCutForestBuilder<T> {
+        self.time_decay = time_decay;
+        self
+    }
+
+    /// Set the time decay of each tree, making a forest that covers several
+    /// memory horizons.
+    ///
+    /// Trees with a large decay forget quickly and follow recent data, while
+    /// trees with a small decay remember a long history. The number of trees
+    /// is set to the number of time decays. The anomaly score blends all
+    /// horizons; [`RandomCutForest::horizon_scores`] reports the score of
+    /// each group of trees sharing a time decay. The forest's
+    /// [`time_decay`](RandomCutForest::time_decay) remains the value set by
+    /// [`time_decay`](Self::time_decay).
+    ///
+    /// # Panics
+    ///
+    /// If `time_decays` is empty or contains a negative or non-finite value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use random_cut_forest::{RandomCutForest, RandomCutForestBuilder};
+    ///
+    /// // log-spaced decays, ten trees per horizon
+    /// let time_decays: Vec<f32> = [1e-4, 1e-3, 1e-2].iter()
+    ///     .flat_map(|decay| vec![*decay; 10])
+    ///     .collect();
+    /// let forest: RandomCutForest<f32> = RandomCutForestBuilder::new(2)
+    ///     .tree_time_decays(time_decays.clone())
+    ///     .build();
+    /// assert_eq!(forest.num_trees(), 30);
+    /// assert_eq!(forest.parameters().tree_time_decays, Some(time_decays));
+    /// ```
+    pub fn tree_time_decays(mut self, time_decays: Vec<f32>) -> RandomCutForestBuilder<T> {
+        assert!(!time_decays.is_empty(), "At least one tree time decay is required");
+        assert!(time_decays.iter().all(|decay| *decay >= 0.0 && decay.is_finite()),
+            "Tree time decays must be non-negative and finite");
+        self.num_trees = time_decays.len();
+        self.tree_time_decays = Some(time_decays);
+        self
+    }
+
+    /// Set the output_after threshold of the random cut forest.
+    pub fn output_after(mut self, output_after: usize) -> RandomCutForestBuilder<T> {
+        self.output_after = output_after;
+        self
+    }
+
+    /// Enable differentially private storage of points.
+    ///
+    /// Every coordinate of a point sent to [`RandomCutForest::update`] is
+    /// perturbed with Laplace noise of scale `sensitivity / epsilon` before
+    /// it is offered to the trees, so the exact input records are never
+    /// retained by the forest. Here `sensitivity` is the largest change in a
+    /// single coordinate that a record can cause. Smaller values of `epsilon`
+    /// give stronger privacy at the cost of noisier scores.
+    ///
+    /// # Panics
+    ///
+    /// If `epsilon` is not positive or `sensitivity` is negative.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use random_cut_forest::{RandomCutForest, RandomCutForestBuilder};
+    ///
+    /// let forest: RandomCutForest<f32> = RandomCutForestBuilder::new(2)
+    ///     .privacy_noise(0.5, 1.0)
+    ///     .max_score(3.0)
+    ///     .build();
+    /// assert_eq!(forest.noise_scale(), 2.0);
+    /// assert_eq!(forest.max_score(), Some(3.0));
+    /// ```
+    pub fn privacy_noise(mut self, epsilon: f32, sensitivity: f32) -> RandomCutForestBuilder<T> {
+        assert!(epsilon > 0.0, "Privacy parameter epsilon must be positive");
+        assert!(sensitivity >= 0.0, "Sensitivity must be non-negative");
+        self.noise_scale = sensitivity / epsilon;
+        self
+    }
+
+    /// Set an upper bound on the anomaly scores reported by the forest.
+    ///
+    /// Scores larger than `max_score` are clamped to `max_score`. Together
+    /// with [`privacy_noise`](Self::privacy_noise) this bounds how much a
+    /// single query can reveal about an individual retained record.
+    ///
+    /// # Panics
+    ///
+    /// If `max_score` is not positive.
+    pub fn max_score(mut self, max_score: T) -> RandomCutForestBuilder<T> {
+        assert!(max_score > T::zero(), "Maximum score must be positive");
+        self.max_score = Some(max_score);
+        self
+    }
+
+    /// Set a transformation applied to every anomaly score of the forest.
+    ///
+    /// The transformation receives the averaged score and a [`ScoreContext`]
+    /// describing the forest, and is applied before the upper bound on
+    /// scores. Since it is stored with the model, every consumer of the
+    /// forest sees the same transformed scores. It applies to
+    /// [`anomaly_score`](RandomCutForest::anomaly_score),
+    /// [`score_group`](RandomCutForest::score_group) and
+    /// [`score_and_update`](RandomCutForest::score_and_update), but not to
+    /// per-tree scores nor to the zero score reported before the forest has
+    /// enough data.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use random_cut_forest::{RandomCutForest, RandomCutForestBuilder, ScoreContext};
+    ///
+    /// // normalize scores by the dimension of the input
+    /// fn per_dimension(score: f64, context: &ScoreContext) -> f64 {
+    ///     score / context.dimension as f64
+    /// }
+    ///
+    /// let mut forest: RandomCutForest<f64> = RandomCutForestBuilder::new(4)
+    ///     .score_transform(per_dimension)
+    ///     .seed(5)
+    ///     .build();
+    /// let mut plain: RandomCutForest<f64> = RandomCutForestBuilder::new(4)
+    ///     .seed(5)
+    ///     .build();
+    /// for i in 0..300 {
+    ///     let point = vec![(i % 10) as f64; 4];
+    ///     forest.update(point.clone());
+    ///     plain.update(point);
+    /// }
+    ///
+    /// let point = vec![20.0; 4];
+    /// assert_eq!(forest.anomaly_score(&point), plain.anomaly_score(&point) / 4.0);
+    /// ```
+    pub fn score_transform(mut self, transform: ScoreTransform<T>) -> RandomCutForestBuilder<T> {
+        self.score_transform = Some(transform);
+        self
+    }
+
+    /// Set the score function used to compute anomaly scores.
+    ///
+    /// See [`ScoreFunction`] for the available presets.
+    pub fn score_function(mut self, score_function: ScoreFunction) -> RandomCutForestBuilder<T> {
+        self.score_function = score_function;
+        self
+    }
+
+    /// Set the strategy used by the trees to choose random cuts.
+    ///
+    /// See [`CutStrategy`] for the available strategies.
+    ///
+    /// # Panics
+    ///
+    /// If the strategy is `DimensionSubsample(0)`.
+    pub fn cut_strategy(mut self, cut_strategy: CutStrategy) -> RandomCutForestBuilder<T> {
+        assert!(cut_strategy != CutStrategy::DimensionSubsample(0),
+            "Dimension subsample size must be positive");
+        self.cut_strategy = cut_strategy;
+        self
+    }
+
+    /// Set the policy deciding on which side of a cut points equal to the
+    /// cut value lie.
+    ///
+    /// See [`TieBreaking`] for the available policies.
+    pub fn tie_breaking(mut self, tie_breaking: TieBreaking) -> RandomCutForestBuilder<T> {
+        self.tie_breaking = tie_breaking;
+        self
+    }
+
+    /// Set the policy for input points with NaN or infinite coordinates.
+    ///
+    /// See [`NonFinitePolicy`] for the available policies.
+    ///
+    /// # Panics
+    ///
+    /// If the bound of a `Clamp` policy is not positive and finite.
+    pub fn non_finite_policy(mut self, non_finite_policy: NonFinitePolicy) -> RandomCutForestBuilder<T> {
+        if let NonFinitePolicy::Clamp(bound) = non_finite_policy {
+            assert!(bound > 0.0 && bound.is_finite(), "Clamp bound must be positive and finite");
+        }
+        self.non_finite_policy = non_finite_policy;
+        self
+    }
+
+    /// Set the valid range of each input dimension.
+    ///
+    /// Coordinate `i` of a valid point lies in `[lower[i], upper[i]]`. Points
+    /// outside of these ranges are counted by
+    /// [`RandomCutForest::num_out_of_bounds_points`] and can be flagged with
+    /// [`RandomCutForest::out_of_bounds_dimensions`]. They are retained as
+    /// given unless [`clamp_to_bounds`](Self::clamp_to_bounds) is set. Use
+    /// infinite limits for dimensions without a bound.
+    ///
+    /// # Panics
+    ///
+    /// If a lower bound is larger than the corresponding upper bound.
+    pub fn dimension_bounds(mut self, lower: Vec<T>, upper: Vec<T>) -> RandomCutForestBuilder<T> {
+        assert_eq!(lower.len(), upper.len(), "Lower and upper bounds must have the same length");
+        assert!(lower.iter().zip(upper.iter()).all(|(l, u)| l <= u),
+            "Lower bounds must not exceed upper bounds");
+        self.bounds = Some((lower, upper));
+        self
+    }
+
+    /// Set whether points outside of the valid ranges set with
+    /// [`dimension_bounds`](Self::dimension_bounds) are clamped to them
+    /// before they are added to the forest or scored.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use random_cut_forest::{RandomCutForest, RandomCutForestBuilder};
+    ///
+    /// let mut forest: RandomCutForest<f32> = RandomCutForestBuilder::new(2)
+    ///     .num_trees(1)
+    ///     .dimension_bounds(vec![0.0, 0.0], vec![1.0, f32::INFINITY])
+    ///     .clamp_to_bounds(true)
+    ///     .build();
+    ///
+    /// forest.update(vec![3.0, 3.0]);
+    /// assert_eq!(forest.num_out_of_bounds_points(), 1);
+    ///
+    /// let point_store = forest.trees()[0].borrow_point_store();
+    /// assert!(point_store.iter().all(|(_, point)| *point == vec![1.0, 3.0]));
+    /// ```
+    pub fn clamp_to_bounds(mut self, clamp_to_bounds: bool) -> RandomCutForestBuilder<T> {
+        self.clamp_to_bounds = clamp_to_bounds;
+        self
+    }
+
+    /// Track the mean and covariance of the input dimensions.
+    ///
+    /// The forest updates a streaming [`Covariance`] estimator with the given
+    /// `decay` with every point it adds to its trees, after clamping and
+    /// privacy noise. The estimate is available from
+    /// [`RandomCutForest::covariance`] for diagnostics, or to normalize
+    /// correlated inputs with [`Covariance::whiten`].
+    ///
+    /// # Panics
+    ///
+    /// If `decay` is not in `[0, 1)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use random_cut_forest::{RandomCutForest, RandomCutForestBuilder};
+    ///
+    /// let mut forest: RandomCutForest<f32> = RandomCutForestBuilder::new(2)
+    ///     .track_covariance(0.01)
+    ///     .build();
+    ///
+    /// for i in 0..100 {
+    ///     let x = (i % 10) as f32;
+    ///     forest.update(vec![x, -x]);
+    /// }
+    /// let covariance = forest.covariance().unwrap();
+    /// assert!(covariance.correlation(0, 1) < -0.99);
+    /// ```
+    pub fn track_covariance(mut self, decay: T) -> RandomCutForestBuilder<T> {
+        assert!(decay >= T::zero() && decay < T::one(), "Decay must be in [0, 1)");
+        self.covariance_decay = Some(decay);
+        self
+    }
+
+    /// Set a per-dimension affine scaling of the input points.
+    ///
+    /// Coordinate `i` of every point passed to the forest is stored in the
+    /// trees as `(x[i] - offset[i]) / scale[i]`, for example to standardize
+    /// dimensions with very different units. The scaling is an internal
+    /// detail: query points are scaled the same way, and every point
+    /// returned by the forest, such as by
+    /// [`RandomCutForest::heavy_hitters`] or
+    /// [`RandomCutForest::find_similar_windows`], is converted back to input
+    /// units. Dimension bounds, privacy noise and the tracked covariance also
+    /// use input units. Only the points read directly from the trees are in
+    /// scaled units.
+    ///
+    /// # Panics
+    ///
+    /// If the lengths of `scale` and `offset` differ, or if a scale is not
+    /// positive and finite.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use random_cut_forest::{RandomCutForest, RandomCutForestBuilder};
+    ///
+    /// // temperatures in Kelvin and pressures in Pascal
+    /// let mut forest: RandomCutForest<f64> = RandomCutForestBuilder::new(2)
+    ///     .input_scaling(vec![1.0, 1000.0], vec![273.15, 101325.0])
+    ///     .build();
+    ///
+    /// for i in 0..100 {
+    ///     forest.update(vec![290.0 + (i % 3) as f64, 101325.0]);
+    /// }
+    /// let heavy_hitter = &forest.heavy_hitters(1)[0].0;
+    /// assert!((heavy_hitter[1] - 101325.0).abs() < 1e-6);
+    /// ```
+    pub fn input_scaling(mut self, scale: Vec<T>, offset: Vec<T>) -> RandomCutForestBuilder<T> {
+        assert_eq!(scale.len(), offset.len(), "Scale and offset must have the same length");
+        assert!(scale.iter().all(|s| *s > T::zero() && s.is_finite()),
+            "Scales must be positive and finite");
+        self.input_scaling = Some((scale, offset));
+        self
+    }
+
+    /// Set a per-dimension variance-stabilizing transformation of the input
+    /// points, see [`InputTransform`].
+    ///
+    /// Coordinate `i` of every point passed to the forest is transformed by
+    /// `transforms[i]` before the input scaling, if any. Like the scaling,
+    /// the transformation is an internal detail: query points are
+    /// transformed the same way and points returned by the forest are
+    /// mapped back to input units.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use random_cut_forest::{InputTransform, RandomCutForest, RandomCutForestBuilder};
+    ///
+    /// // a request counter next to a temperature
+    /// let mut forest: RandomCutForest<f64> = RandomCutForestBuilder::new(2)
+    ///     .input_transforms(vec![InputTransform::Sqrt, InputTransform::Identity])
+    ///     .seed(4)
+    ///     .build();
+    ///
+    /// for i in 0..500 {
+    ///     forest.update(vec![(i % 5) as f64, 20.0 + (i % 3) as f64]);
+    /// }
+    /// // a burst of events at a low rate stands out
+    /// assert!(forest.anomaly_score(&vec![40.0, 21.0]) > forest.anomaly_score(&vec![3.0, 21.0]));
+    /// let heavy_hitter = &forest.heavy_hitters(1)[0].0;
+    /// assert!((heavy_hitter[0] - heavy_hitter[0].round()).abs() < 1e-9);
+    /// ```
+    pub fn input_transforms(mut self, transforms: Vec<InputTransform>) -> RandomCutForestBuilder<T> {
+        self.input_transforms = Some(transforms);
+        self
+    }
+
+    /// Keep the last `capacity` points added to the forest for debugging.
+    ///
+    /// The points are kept exactly as they were passed to
+    /// [`RandomCutForest::update`], before any clamping, privacy noise or
+    /// input scaling, together with their observation numbers. This shows
+    /// the raw context of an anomaly without an external store. Points that
+    /// are skipped or rejected are not kept. A capacity of zero disables the
+    /// history.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use random_cut_forest::{RandomCutForest, RandomCutForestBuilder};
+    ///
+    /// let mut forest: RandomCutForest<f32> = RandomCutForestBuilder::new(1)
+    ///     .raw_input_history(3)
+    ///     .build();
+    ///
+    /// for i in 0..10 {
+    ///     forest.update(vec![i as f32]);
+    /// }
+    /// let recent: Vec<_> = forest.raw_inputs().iter().cloned().collect();
+    /// assert_eq!(recent, vec![(8, vec![7.0]), (9, vec![8.0]), (10, vec![9.0])]);
+    /// ```
+    pub fn raw_input_history(mut self, capacity: usize) -> RandomCutForestBuilder<T> {
+        self.raw_input_history = capacity;
+        self
+    }
+
+    /// Track the reliability of each tree.
+    ///
+    /// Every call to [`RandomCutForest::score_and_update`] updates, for each
+    /// tree, a [`Covariance`] estimator with the given `decay` of the tree's
+    /// score and the mean score of all trees. The reliability of a tree is
+    /// the resulting correlation, see [`RandomCutForest::tree_reliability`].
+    /// Other scoring methods do not change the forest and do not update the
+    /// reliabilities.
+    ///
+    /// # Panics
+    ///
+    /// If `decay` is not in `(0, 1)`.
+    pub fn tree_reliability(mut self, decay: T) -> RandomCutForestBuilder<T> {
+        assert!(decay > T::zero() && decay < T::one(), "Decay must be in (0, 1)");
+        self.tree_reliability_decay = Some(decay);
+        self
+    }
+
+    /// Set whether tree scores are weighted by tree reliability when they
+    /// are combined into the anomaly score of the forest.
+    ///
+    /// With weighted voting, the anomaly score is the mean of the tree scores
+    /// weighted by [`RandomCutForest::tree_reliability`], which down-weights
+    /// degenerate trees. Requires [`tree_reliability`](Self::tree_reliability).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use random_cut_forest::{RandomCutForest, RandomCutForestBuilder};
+    ///
+    /// let mut forest: RandomCutForest<f32> = RandomCutForestBuilder::new(2)
+    ///     .num_trees(10)
+    ///     .tree_reliability(0.01)
+    ///     .weighted_voting(true)
+    ///     .seed(1)
+    ///     .build();
+    ///
+    /// for i in 0..500 {
+    ///     forest.score_and_update(vec![(i % 10) as f32, (i % 7) as f32]).unwrap();
+    /// }
+    /// let reliability = forest.tree_reliability().unwrap();
+    /// assert!(reliability.iter().all(|(_, weight)| (0.0..=1.0).contains(weight)));
+    /// assert!(reliability.iter().any(|(_, weight)| *weight > 0.0));
+    /// assert!(forest.anomaly_score(&vec![50.0, 50.0]) > 1.0);
+    /// ```
+    pub fn weighted_voting(mut self, weighted_voting: bool) -> RandomCutForestBuilder<T> {
+        self.weighted_voting = weighted_voting;
+        self
+    }
+
+    /// Record the latency of forest operations.
+    ///
+    /// Each [`TimedOperation`] has a [`LatencyHistogram`] of the wall-clock
+    /// durations of its calls, including failed calls, queried with
+    /// [`RandomCutForest::latency_percentiles`]. Timing adds two clock reads
+    /// per call.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use random_cut_forest::{RandomCutForest, RandomCutForestBuilder, TimedOperation};
+    ///
+    /// let mut forest: RandomCutForest<f32> = RandomCutForestBuilder::new(2)
+    ///     .track_latency(true)
+    ///     .build();
+    ///
+    /// for i in 0..100 {
+    ///     forest.update(vec![(i % 10) as f32, 0.0]);
+    ///     forest.anomaly_score(&vec![0.0, 0.0]);
+    /// }
+    /// let latency = forest.latency_percentiles(TimedOperation::Update, &[50.0, 99.0]).unwrap();
+    /// assert!(latency[0] <= latency[1]);
+    /// assert_eq!(forest.latency_histogram(TimedOperation::Score).unwrap().count(), 100);
+    /// assert!(forest.latency_percentiles(TimedOperation::ScoreAndUpdate, &[50.0]).is_none());
+    /// ```
+    pub fn track_latency(mut self, track_latency: bool) -> RandomCutForestBuilder<T> {
+        self.track_latency = track_latency;
+        self
+    }
+
+    /// Skip the scoring of clearly normal points.
+    ///
+    /// A [`ZScoreFilter`] tracks the mean and variance of each dimension of
+    /// the points the forest is updated with, decayed at the forest's time
+    /// decay. Points whose coordinates are all within `z_bound` standard
+    /// deviations of the mean get a score of zero from
+    /// [`anomaly_score`](RandomCutForest::anomaly_score) and
+    /// [`score_and_update`](RandomCutForest::score_and_update) without
+    /// traversing the trees; the trees are still updated. A fraction of
+    /// these points is audited with a full score, and the bound is tightened
+    /// whenever an audited score exceeds `score_threshold`, so that the
+    /// rate of such false negatives settles at `false_negative_budget`.
+    /// Other scoring methods are not filtered.
+    ///
+    /// # Panics
+    ///
+    /// If `z_bound` is not positive and finite or `false_negative_budget` is
+    /// not in `(0, 1)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use random_cut_forest::{PreFilter, RandomCutForest, RandomCutForestBuilder};
+    ///
+    /// let mut forest: RandomCutForest<f32> = RandomCutForestBuilder::new(2)
+    ///     .pre_filter(PreFilter { z_bound: 2.0, score_threshold: 1.5, false_negative_budget: 0.01 })
+    ///     .seed(0)
+    ///     .build();
+    /// for i in 0..1000 {
+    ///     forest.score_and_update(vec![(i % 10) as f32, (i % 7) as f32]).unwrap();
+    /// }
+    ///
+    /// // most points were clearly normal, but an outlier is still scored
+    /// let state = forest.pre_filter_state().unwrap();
+    /// assert!(state.num_skipped() > 500);
+    /// assert!(forest.anomaly_score(&vec![100.0, 0.0]) > 1.5);
+    /// ```
+    pub fn pre_filter(mut self, pre_filter: PreFilter<T>) -> RandomCutForestBuilder<T> {
+        assert!(pre_filter.z_bound > T::zero() && pre_filter.z_bound.is_finite(),
+            "Z-score bound must be positive and finite");
+        assert!(pre_filter.false_negative_budget > 0.0 && pre_filter.false_negative_budget < 1.0,
+            "False negative budget must be in (0, 1)");
+        self.pre_filter = Some(pre_filter);
+        self
+    }
+
+    /// Limit the rate of updates by shedding updates under bursts of
+    /// traffic.
+    ///
+    /// An [`UpdateGovernor`] counts the updates, from
+    /// [`update`](RandomCutForest::update),
+    /// [`try_update`](RandomCutForest::try_update) and
+    /// [`score_and_update`](RandomCutForest::score_and_update), in windows
+    /// of the given duration. Beyond `max_updates` per window, each update
+    /// reaches the trees with a probability chosen to keep about
+    /// `max_updates` of them, instead of blocking the caller, and the
+    /// samplers count an admitted point as `1 / p` points. Every point is
+    /// therefore as likely to be sampled as without the limit, in
+    /// expectation, while the cost of updates stays bounded. Shed points
+    /// still count as observations, so time decay and `output_after` are
+    /// unaffected, and `score_and_update` still scores them. Points passed
+    /// to [`build_from_slice`](Self::build_from_slice) are not limited.
+    ///
+    /// Which updates are shed depends on their arrival times, read from the
+    /// limit's clock. With the system clock, a seeded forest is therefore
+    /// not reproducible once updates are shed; a deterministic clock makes
+    /// it so.
+    ///
+    /// # Panics
+    ///
+    /// If `max_updates` is zero or `window` is empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::cell::Cell;
+    /// use std::time::{Duration, Instant};
+    /// use random_cut_forest::{RandomCutForest, RandomCutForestBuilder, UpdateRateLimit};
+    ///
+    /// // a clock advancing by one millisecond at every reading
+    /// thread_local! {
+    ///     static EPOCH: Instant = Instant::now();
+    ///     static TICKS: Cell<u64> = const { Cell::new(0) };
+    /// }
+    /// fn clock() -> Instant {
+    ///     let ticks = TICKS.with(|ticks| { ticks.set(ticks.get() + 1); ticks.get() });
+    ///     EPOCH.with(|epoch| *epoch + Duration::from_millis(ticks))
+    /// }
+    ///
+    /// // 1000 updates per second against a limit of 100
+    /// let limit = UpdateRateLimit { clock, ..UpdateRateLimit::new(100, Duration::from_secs(1)) };
+    /// let mut forest: RandomCutForest<f32> = RandomCutForestBuilder::new(1)
+    ///     .update_rate_limit(limit)
+    ///     .seed(0)
+    ///     .build();
+    /// for i in 0..10000 {
+    ///     forest.update(vec![(i % 10) as f32]);
+    /// }
+    ///
+    /// let governor = forest.update_governor().unwrap();
+    /// assert_eq!(forest.num_observations(), 10000);
+    /// assert!(governor.num_admitted() > 900 && governor.num_admitted() < 1600);
+    /// assert_eq!(governor.num_admitted() + governor.num_shed(), 10000);
+    /// ```
+    pub fn update_rate_limit(mut self, limit: UpdateRateLimit) -> RandomCutForestBuilder<T> {
+        assert!(limit.max_updates > 0, "Maximum number of updates must be positive");
+        assert!(limit.window > Duration::from_secs(0), "Window must not be empty");
+        self.update_rate_limit = Some(limit);
+        self
+    }
+
+    /// Set the random seed of the forest.
+    ///
+    /// The random number generators of every tree and sampler in the forest
+    /// are seeded from this seed, making model construction reproducible.
+    pub fn seed(mut self, seed: u64) -> RandomCutForestBuilder<T> {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Set the source of the random number generators used by the forest.
+    ///
+    /// The forest needs a random number generator for the cuts of each tree,
+    /// for the sampler of each tree, and one for itself. The function
+    /// `rng_source` is called once for each of these with a distinct index:
+    /// `2*i` for the cuts of tree `i`, `2*i + 1` for its sampler, and
+    /// `2*num_trees` for the forest. This makes it possible to use any
+    /// [`RngCore`], such as a counter-based generator whose streams are
+    /// selected by index. Takes precedence over [`seed`](Self::seed).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rand::SeedableRng;
+    /// use rand_chacha::ChaCha20Rng;
+    /// use random_cut_forest::{RandomCutForest, RandomCutForestBuilder};
+    ///
+    /// let forest: RandomCutForest<f32> = RandomCutForestBuilder::new(2)
+    ///     .rng_source(|index| {
+    ///         let mut rng = ChaCha20Rng::seed_from_u64(42);
+    ///         rng.set_stream(index as u64);
+    ///         Box::new(rng)
+    ///     })
+    ///     .build();
+    /// ```
+    pub fn rng_source<F>(mut self, rng_source: F) -> RandomCutForestBuilder<T>
+        where F: Fn(usize) -> Box<dyn RngCore> + 'static
+    {
+        self.rng_source = Some(Box::new(rng_source));
+        self
+    }
+
+    /// Build a random cut forest using the parameters set by the builder.
+    ///
+    /// # Panics
+    ///
+    /// If dimension bounds, an input scaling or input transformations are set
+    /// and their length does not match the dimension of the forest, if the
+    /// number of tree sample sizes or time decays does not match the number
+    /// of trees, or if weighted voting is set without tracking tree
+    /// reliability.
+    pub fn build(self) -> RandomCutForest<T> {
+        assert!(!self.weighted_voting || self.tree_reliability_decay.is_some(),
+            "Weighted voting requires tracking tree reliability");
+        let bounds = self.bounds.as_ref().map(|(lower, upper)| {
+            assert_eq!(lower.len(), self.dimension,
+                "Dimension mismatch. Expected {}-dimensional bounds.", self.dimension);
+            BoundingBox::new(lower, upper)
+        });
+        if let Some((scale, _)) = &self.input_scaling {
+            assert_eq!(scale.len(), self.dimension,
+                "Dimension mismatch. Expected {}-dimensional input scaling.", self.dimension);
+        }
+        if let Some(transforms) = &self.input_transforms {
+            assert_eq!(transforms.len(), self.dimension,
+                "Dimension mismatch. Expected {}-dimensional input transforms.", self.dimension);
+        }
+
+        let mut seeds = self.seed.map(ChaCha8Rng::seed_from_u64);
+        let mut new_rng = |index: usize| -> Box<dyn RngCore> {
+            match (&self.rng_source, seeds.as_mut()) {
+                (Some(rng_source), _) => rng_source(index),
+                (None, Some(seeds)) => Box::new(ChaCha8Rng::seed_from_u64(seeds.gen())),
+                (None, None) => Box::new(ChaCha8Rng::from_entropy()),
+            }
+        };
+
+        if let Some(sizes) = &self.tree_sample_sizes {
+            assert_eq!(sizes.len(), self.num_trees,
+                "Expected {} tree sample sizes, one per tree.", self.num_trees);
+        }
+        if let Some(decays) = &self.tree_time_decays {
+            assert_eq!(decays.len(), self.num_trees,
+                "Expected {} tree time decays, one per tree.", self.num_trees);
+        }
+
+        let mut trees: Vec<SampledTree<T>> = Vec::with_capacity(self.num_trees);
+        for i in 0..self.num_trees {
+            let sample_size = self.tree_sample_sizes.as_ref().map_or(self.sample_size, |sizes| sizes[i]);
+            let time_decay = self.tree_time_decays.as_ref().map_or(self.time_decay, |decays| decays[i]);
+            let mut tree = SampledTree::new(sample_size, time_decay);
+            tree.set_rng(new_rng(2*i), new_rng(2*i + 1));
+            tree.set_id(i);
+            tree.set_cut_strategy(self.cut_strategy);
+            tree.set_tie_breaking(self.tie_breaking);
+            trees.push(tree);
+        }
+        let rng = new_rng(2*self.num_trees);
+        let tree_reliability = self.tree_reliability_decay
+            .map(|decay| (0..self.num_trees).map(|_| Covariance::new(2, decay)).collect());
+        let pre_filter = self.pre_filter.map(|pre_filter| {
+            let decay = T::from(self.time_decay).unwrap();
+            let filter = ZScoreFilter::new(self.dimension, pre_filter.z_bound, pre_filter.false_negative_budget, decay);
+            (pre_filter, RefCell::new(filter))
+        });
+
+        RandomCutForest {
+            dimension: self.dimension,
+            sample_size: self.sample_size,
+            time_decay: self.time_decay,
+            trees: trees,
+            num_observations: 0,
+            output_after: self.output_after,
+            noise_scale: self.noise_scale,
+            max_score: self.max_score,
+            score_transform: self.score_transform,
+            score_function: self.score_function.clone(),
+            cut_strategy: self.cut_strategy,
+            tie_breaking: self.tie_breaking,
+            non_finite_policy: self.non_finite_policy,
+            num_rejected_points: 0,
+            bounds,
+            clamp_to_bounds: self.clamp_to_bounds,
+            num_out_of_bounds_points: 0,
+            covariance: self.covariance_decay.map(|decay| Covariance::new(self.dimension, decay)),
+            input_scaling: self.input_scaling,
+            input_transforms: self.input_transforms,
+            raw_input_history: self.raw_input_history,
+            raw_inputs: VecDeque::with_capacity(self.raw_input_history),
+            masked_dimensions: vec![false; self.dimension],
+            tree_reliability,
+            weighted_voting: self.weighted_voting,
+            latency: match self.track_latency {
+                true => Some((0..3).map(|_| RefCell::new(LatencyHistogram::new())).collect()),
+                false => None,
+            },
+            pre_filter,
+            governor: self.update_rate_limit
+                .map(|limit| (limit, UpdateGovernor::new(limit.max_updates, limit.window))),
+            num_delivered_updates: 0,
+            next_tree_id: self.num_trees,
+            seed: if self.rng_source.is_none() { self.seed } else { None },
+            rng,
+        }
+    }
+
+    /// Build the random cut forest and update it with a slice of historical
+    /// points.
+    ///
+    /// The result is equivalent to calling [`build`](Self::build) and then
+    /// [`RandomCutForest::update`] with every point of `data` in order, but
+    /// much faster on long histories: each tree samples the whole slice
+    /// first and is then built from its sample in one pass, see
+    /// [`SampledTree::bulk_load`], instead of inserting and deleting points
+    /// one at a time.
+    ///
+    /// # Panics
+    ///
+    /// As for [`build`](Self::build), and if a point of `data` would make
+    /// [`RandomCutForest::update`] panic.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use random_cut_forest::{RandomCutForest, RandomCutForestBuilder};
+    ///
+    /// let history: Vec<Vec<f32>> = (0..10000)
+    ///     .map(|i| vec![(i % 10) as f32, (i % 7) as f32])
+    ///     .collect();
+    ///
+    /// let mut forest: RandomCutForest<f32> = RandomCutForestBuilder::new(2)
+    ///     .num_trees(20)
+    ///     .sample_size(64)
+    ///     .seed(3)
+    ///     .build_from_slice(&history);
+    ///
+    /// assert_eq!(forest.num_observations(), 10000);
+    /// assert!(forest.trees().iter().all(|tree| tree.tree().mass() == 64));
+    /// assert!(forest.anomaly_score(&vec![50.0, 50.0]) > forest.anomaly_score(&vec![5.0, 3.0]));
+    ///
+    /// // the forest continues with the stream
+    /// forest.update(vec![0.0, 0.0]);
+    /// assert_eq!(forest.num_observations(), 10001);
+    /// ```
+    pub fn build_from_slice(self, data: &[Vec<T>]) -> RandomCutForest<T> {
+        let mut forest = self.build();
+        forest.bulk_update(data);
+        forest
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::Node;
+    use rand::thread_rng;
+    use rand_distr::StandardNormal;
+
+    /// Utility function for generating random vectors from a multi-dimensional
+    /// Gaussian distribution.
+    fn randn(num_points: usize, dimension: usize) -> Vec<Vec<f32>> {
+        let mut points: Vec<Vec<f32>> = Vec::with_capacity(num_points);
+        let mut rng = thread_rng();
+        for _ in 0..num_points {
+            let mut point: Vec<f32> = Vec::with_capacity(dimension);
+            for _ in 0..dimension {
+                point.push(rng.sample(StandardNormal));
+            }
+            points.push(point);
+        }
+
+        points
+    }
+
+    /// Like `randn`, but the points are drawn from a seeded generator and are
+    /// the same on every run.
+    pub(super) fn seeded_randn(num_points: usize, dimension: usize, seed: u64) -> Vec<Vec<f32>> {
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        (0..num_points)
+            .map(|_| (0..dimension).map(|_| rng.sample(StandardNormal)).collect())
+            .collect()
+    }
+
+    #[test]
+    fn update() {
+        let mut forest = RandomCutForestBuilder::new(2)
+            .num_trees(2)
+            .sample_size(4)
+            .time_decay(8.0)  // large positive value means updates are almost forced
+            .build();
+
+        // update until full
+        forest.update(vec![0.0, 0.0]);
+        forest.update(vec![1.0, 0.0]);
+        forest.update(vec![0.0, 1.0]);
+        forest.update(vec![1.0, 1.0]);
+
+        // updates that may require deletion
+        forest.update(vec![2.0, 0.0]);
+        forest.update(vec![0.0, 2.0]);
+        forest.update(vec![-2.0, 0.0]);
+        forest.update(vec![0.0, -2.0]);
+    }
+
+    #[test]
+    fn gaussian_blob() {
+        let num_points = 1000;
+        let dimension = 3;
+        let mut forest: RandomCutForest<f32> = RandomCutForestBuilder::new(dimension).build();
+
+        let points = randn(num_points, dimension);
+        for point in points.iter() {
+            forest.update(point.clone());
+        }
+
+        let scores: Vec<f32> = points.iter().map(|p| forest.anomaly_score(&p)).collect();
+        let scores_mean: f32 = scores.iter().sum::<f32>() / num_points as f32;
+        let scores_max: f32 = scores.iter().fold(0.0, |max_s, s| f32::max(max_s, *s));
+
+        let anomaly = vec![5.0; dimension];
+        let anomalous_score = forest.anomaly_score(&anomaly);
+
+        println!("[gaussian_blob] scores mean      = {}", scores_mean);
+        println!("[gaussian_blob] scores max       = {}", scores_max);
+        println!("[gaussian_blob] anomalous score  = {}", anomalous_score);
+
+        assert!(anomalous_score > scores_mean);
+        assert!(anomalous_score > scores_max);
+    }
+
+    #[test]
+    fn double_gaussian() {
+        let num_points = 2000;
+        let dimension = 3;
+        let mut forest: RandomCutForest<f32> = RandomCutForestBuilder::new(dimension).build();
+
+        // create a "barbell" of points: two gaussian blobs centered at \pm 20
+        let mut points = randn(num_points, dimension);
+        for (i, point) in points.iter_mut().enumerate() {
+            if i < num_points / 2 {
+                point[0] -= 20.0;
+            } else {
+                point[0] += 20.0
+            }
+        }
+
+        for point in points.iter() {
+            forest.update(point.clone());
+        }
+
+        let scores: Vec<f32> = points.iter().map(|p| forest.anomaly_score(&p)).collect();
+        let scores_mean: f32 = scores.iter().sum::<f32>() / num_points as f32;
+        let scores_max: f32 = scores.iter().fold(0.0, |max_s, s| f32::max(max_s, *s));
+
+        let anomaly = vec![0.0; dimension];
+        let anomalous_score = forest.anomaly_score(&anomaly);
+
+        println!("[double_gaussian] scores mean      = {}", scores_mean);
+        println!("[double_gaussian] scores max       = {}", scores_max);
+        println!("[double_gaussian] anomalous score  = {}", anomalous_score);
+
+        assert!(anomalous_score > scores_mean);
+        assert!(anomalous_score > scores_max);
+    }
+
+    #[test]
+    fn output_after() {
+        let num_points = 20;
+        let output_after = 10;
+        let dimension = 3;
+        let mut forest: RandomCutForest<f32> = RandomCutForestBuilder::new(dimension)
+            .output_after(output_after).build();
+
+        let points = randn(num_points, dimension);
+        let anomaly = vec![0.0; dimension];
+        for (i, point) in points.iter().enumerate() {
+            forest.update(point.clone());
+
+            if i < output_after {
+                let anomalous_score = forest.anomaly_score(&anomaly);
+                assert!(anomalous_score == 0.0);
+            }
+        }
+
+        let anomalous_score = forest.anomaly_score(&anomaly);
+        assert!(anomalous_score != 0.0);
+    }
+
+    #[test]
+    fn privacy_noise() {
+        let dimension = 2;
+        let mut forest: RandomCutForest<f32> = RandomCutForestBuilder::new(dimension)
+            .num_trees(1)
+            .sample_size(16)
+            .privacy_noise(1.0, 1.0)
+            .max_score(0.5)
+            .seed(1)
+            .build();
+
+        let points = seeded_randn(16, dimension, 1);
+        for point in points.iter() {
+            forest.update(point.clone());
+        }
+
+        // none of the input points should be stored exactly
+        let point_store = forest.trees()[0].borrow_point_store();
+        assert_eq!(point_store.len(), points.len());
+        for (_, stored) in point_store.iter() {
+            assert!(points.iter().all(|p| p != stored));
+        }
+
+        let anomalous_score = forest.anomaly_score(&vec![100.0; dimension]);
+        assert_eq!(anomalous_score, 0.5);
+    }
+
+    #[test]
+    fn score_functions() {
+        let num_points = 1000;
+        let dimension = 3;
+        let points = seeded_randn(num_points, dimension, 2);
+        let anomaly = vec![5.0; dimension];
+
+        for score_function in [ScoreFunction::Standard, ScoreFunction::Displacement,
+                               ScoreFunction::ExpectedInverseDepth].iter() {
+            let mut forest: RandomCutForest<f32> = RandomCutForestBuilder::new(dimension)
+                .score_function(score_function.clone())
+                .seed(2)
+                .build();
+            for point in points.iter() {
+                forest.update(point.clone());
+            }
+
+            let scores: Vec<f32> = points.iter().map(|p| forest.anomaly_score(p)).collect();
+            let scores_mean: f32 = scores.iter().sum::<f32>() / num_points as f32;
+            let anomalous_score = forest.anomaly_score(&anomaly);
+            assert!(anomalous_score > scores_mean, "{:?}", score_function);
+        }
+    }
+
+    #[test]
+    fn custom_score_function() {
+        use crate::visitor::CustomScoreFunction;
+
+        // the standard score, written as closures
+        let log2_mass = |mass: usize| ((mass + 1) as f64).log2();
+        let standard = CustomScoreFunction::new(
+            move |depth, mass| 1.0 / (depth as f64 + log2_mass(mass)),
+            |depth, _| 1.0 / (depth as f64 + 1.0),
+        )
+            .with_damp(|leaf_mass, tree_mass| 1.0 - leaf_mass as f64 / (2.0 * tree_mass as f64))
+            .with_normalizer(move |tree_mass, _| log2_mass(tree_mass));
+        assert_eq!(ScoreFunction::Custom(standard.clone()), ScoreFunction::Custom(standard.clone()));
+
+        let points = seeded_randn(500, 2, 7);
+        let build = |score_function: ScoreFunction| -> RandomCutForest<f64> {
+            let mut forest = RandomCutForestBuilder::new(2)
+                .score_function(score_function)
+                .seed(7)
+                .build();
+            for point in points.iter() {
+                forest.update(point.iter().map(|&x| x as f64).collect());
+            }
+            forest
+        };
+        let forest = build(ScoreFunction::Standard);
+        let custom = build(ScoreFunction::Custom(standard));
+        for query in [vec![0.0, 0.0], vec![3.0, -3.0], vec![10.0, 10.0]].iter() {
+            let expected = forest.anomaly_score(query);
+            assert!((custom.anomaly_score(query) - expected).abs() < 1e-9 * expected.max(1.0));
+        }
+    }
+
+    #[test]
+    fn seed() {
+        let dimension = 3;
+        let points = seeded_randn(500, dimension, 42);
+        let build = || -> RandomCutForest<f32> {
+            RandomCutForestBuilder::new(dimension)
+                .num_trees(10)
+                .sample_size(64)
+                .time_decay(0.01)
+                .privacy_noise(10.0, 1.0)
+                .seed(42)
+                .build()
+        };
+
+        let mut forest1 = build();
+        let mut forest2 = build();
+        for point in points.iter() {
+            forest1.update(point.clone());
+            forest2.update(point.clone());
+        }
+
+        for point in points.iter().take(50) {
+            assert_eq!(forest1.anomaly_score(point), forest2.anomaly_score(point));
+        }
+    }
+
+    #[test]
+    fn dimension_subsample() {
+        let num_points = 1000;
+        let dimension = 3;
+        let mut forest: RandomCutForest<f32> = RandomCutForestBuilder::new(dimension)
+            .cut_strategy(CutStrategy::DimensionSubsample(1))
+            .seed(4)
+            .build();
+
+        // a heavy-tailed first dimension dominates the range of every box
+        let mut points = seeded_randn(num_points, dimension, 4);
+        for point in points.iter_mut() {
+            point[0] = point[0].powi(5) * 100.0;
+        }
+        for point in points.iter() {
+            forest.update(point.clone());
+        }
+
+        let scores: Vec<f32> = points.iter().map(|p| forest.anomaly_score(p)).collect();
+        let scores_mean: f32 = scores.iter().sum::<f32>() / num_points as f32;
+        let anomaly = vec![0.0, 10.0, 10.0];
+        assert!(forest.anomaly_score(&anomaly) > scores_mean);
+    }
+
+    #[test]
+    fn non_finite_clamp() {
+        let dimension = 2;
+        let mut forest: RandomCutForest<f32> = RandomCutForestBuilder::new(dimension)
+            .num_trees(5)
+            .sample_size(32)
+            .non_finite_policy(NonFinitePolicy::Clamp(100.0))
+            .seed(5)
+            .build();
+
+        let points = seeded_randn(100, dimension, 5);
+        for (i, point) in points.iter().enumerate() {
+            forest.update(point.clone());
+            if i % 10 == 0 {
+                forest.update(vec![f32::NAN, f32::INFINITY]);
+            }
+        }
+        assert_eq!(forest.num_observations(), 110);
+        assert_eq!(forest.num_rejected_points(), 10);
+
+        // clamped points are retained as finite points and scores stay finite
+        for tree in forest.trees() {
+            for (_, stored) in tree.borrow_point_store().iter() {
+                assert!(stored.iter().all(|v| v.is_finite()));
+            }
+        }
+        let score = forest.anomaly_score(&vec![f32::NEG_INFINITY, 0.0]);
+        assert!(score.is_finite() && score > 0.0);
+    }
+
+    #[test]
+    fn dimension_bounds() {
+        let dimension = 2;
+        let build = |clamp: bool| -> RandomCutForest<f32> {
+            RandomCutForestBuilder::new(dimension)
+                .num_trees(10)
+                .sample_size(64)
+                .dimension_bounds(vec![-5.0, -5.0], vec![5.0, 5.0])
+                .clamp_to_bounds(clamp)
+                .seed(6)
+                .build()
+        };
+        let mut clamped = build(true);
+        let mut unclamped = build(false);
+
+        let points = seeded_randn(500, dimension, 6);
+        for point in points.iter() {
+            clamped.update(point.clone());
+            unclamped.update(point.clone());
+        }
+        clamped.update(vec![1000.0, 0.0]);
+        unclamped.update(vec![1000.0, 0.0]);
+        assert_eq!(clamped.num_out_of_bounds_points(), 1);
+        assert_eq!(unclamped.num_out_of_bounds_points(), 1);
+
+        // a glitch far outside of the physical limits scores like a point
+        // on the boundary once clamped
+        let glitch = vec![1.0e6, 0.0];
+        assert_eq!(clamped.out_of_bounds_dimensions(&glitch), vec![0]);
+        assert_eq!(clamped.anomaly_score(&glitch), clamped.anomaly_score(&vec![5.0, 0.0]));
+        assert_ne!(unclamped.anomaly_score(&glitch), unclamped.anomaly_score(&vec![5.0, 0.0]));
+
+        let bounds = clamped.bounds().unwrap();
+        for tree in clamped.trees() {
+            assert!(tree.borrow_point_store().iter().all(|(_, p)| bounds.contains_point(p)));
+        }
+    }
+
+    #[test]
+    fn large_offset_f64() {
+        let num_points = 1000;
+        let dimension = 3;
+        let offset = 1.0e9;
+        let mut forest: RandomCutForest<f64> = RandomCutForestBuilder::new(dimension)
+            .seed(9)
+            .build();
+
+        // unit variations on a large offset all round to the same f32 value
+        let points: Vec<Vec<f64>> = seeded_randn(num_points, dimension, 9).into_iter()
+            .map(|p| p.into_iter().map(|x| offset + x as f64).collect())
+            .collect();
+        assert!(points.iter().all(|p| p[0] as f32 == offset as f32));
+        for point in points.iter() {
+            forest.update(point.clone());
+        }
+
+        let scores: Vec<f64> = points.iter().map(|p| forest.anomaly_score(p)).collect();
+        let scores_max: f64 = scores.iter().fold(0.0, |max_s, s| f64::max(max_s, *s));
+        let anomaly = vec![offset + 5.0; dimension];
+        assert!(forest.anomaly_score(&anomaly) > scores_max);
+    }
+
+    #[test]
+    fn input_scaling() {
+        let dimension = 2;
+        let scale = vec![1.0e-3, 1.0e6];
+        let offset = vec![5.0, -2.0e6];
+        let build = |scaled: bool| -> RandomCutForest<f64> {
+            let builder = RandomCutForestBuilder::new(dimension)
+                .num_trees(10)
+                .sample_size(64)
+                .seed(3);
+            match scaled {
+                true => builder.input_scaling(scale.clone(), offset.clone()).build(),
+                false => builder.build(),
+            }
+        };
+        let to_input = |p: &Vec<f64>| -> Vec<f64> {
+            (0..dimension).map(|i| p[i] * scale[i] + offset[i]).collect()
+        };
+
+        // a forest with input scaling behaves like a forest fed scaled points
+        let mut scaled = build(true);
+        let mut manual = build(false);
+        let points: Vec<Vec<f64>> = seeded_randn(200, dimension, 3).into_iter()
+            .map(|p| p.into_iter().map(|x| x.round() as f64).collect())
+            .collect();
+        for point in points.iter() {
+            scaled.update(to_input(point));
+            manual.update(point.clone());
+        }
+
+        for point in points.iter().take(20) {
+            let score = scaled.anomaly_score(&to_input(point));
+            assert!((score - manual.anomaly_score(point)).abs() < 1e-9);
+        }
+
+        // returned points are in input units
+        let heavy_hitter = &scaled.heavy_hitters(1)[0];
+        let manual_heavy_hitter = &manual.heavy_hitters(1)[0];
+        assert_eq!(heavy_hitter.1, manual_heavy_hitter.1);
+        for i in 0..dimension {
+            let expected = to_input(&manual_heavy_hitter.0)[i];
+            assert!((heavy_hitter.0[i] - expected).abs() <= 1e-9 * expected.abs());
+        }
+    }
+
+    #[test]
+    fn input_transforms() {
+        let transforms = vec![InputTransform::Log1p, InputTransform::Sqrt];
+        let build = |transformed: bool| -> RandomCutForest<f64> {
+            let builder = RandomCutForestBuilder::new(2)
+                .num_trees(10)
+                .sample_size(64)
+                .seed(8);
+            match transformed {
+                true => builder
+                    .input_transforms(transforms.clone())
+                    .input_scaling(vec![2.0, 0.5], vec![1.0, 0.0])
+                    .build(),
+                false => builder.input_scaling(vec![2.0, 0.5], vec![1.0, 0.0]).build(),
+            }
+        };
+        let transform = |p: &Vec<f64>| -> Vec<f64> {
+            p.iter().zip(transforms.iter()).map(|(x, t)| t.apply(*x)).collect()
+        };
+
+        // a forest with input transforms behaves like a forest fed
+        // transformed points
+        let mut transformed = build(true);
+        let mut manual = build(false);
+        let points: Vec<Vec<f64>> = seeded_randn(200, 2, 8).into_iter()
+            .map(|p| p.into_iter().map(|x| (x * 100.0).round() as f64).collect())
+            .collect();
+        for point in points.iter() {
+            transformed.update(point.clone());
+            manual.update(transform(point));
+        }
+        for point in points.iter().take(20) {
+            let score = transformed.anomaly_score(point);
+            assert!((score - manual.anomaly_score(&transform(point))).abs() < 1e-9);
+        }
+
+        // returned points are in input units
+        let heavy_hitter = &transformed.heavy_hitters(1)[0].0;
+        let expected = &manual.heavy_hitters(1)[0].0;
+        assert!((transform(heavy_hitter)[0] - expected[0]).abs() < 1e-9);
+        assert!((transform(heavy_hitter)[1] - expected[1]).abs() < 1e-9);
+        assert!(points.iter().any(|p| (p[0] - heavy_hitter[0]).abs() < 1e-6));
+    }
+
+    #[test]
+    fn score_group() {
+        let dimension = 2;
+        let mut forest: RandomCutForest<f32> = RandomCutForestBuilder::new(dimension)
+            .num_trees(20)
+            .sample_size(64)
+            .time_decay(0.01)
+            .score_function(ScoreFunction::Displacement)
+            .seed(10)
+            .build();
+        let points = seeded_randn(500, dimension, 10);
+        for point in points.iter() {
+            forest.update(point.clone());
+        }
+
+        // a single unseen point scores like its displacement score
+        for point in [vec![0.0, 0.0], vec![3.0, -1.0]].iter() {
+            let group_score = forest.score_group(std::slice::from_ref(point));
+            let score = forest.anomaly_score(point);
+            assert!((group_score - score).abs() < 1e-4, "{} != {}", group_score, score);
+        }
+
+        // once a burst of duplicates is retained, its points mask each other
+        // when scored one by one but not when scored jointly
+        let burst = vec![vec![4.0, 4.0]; 10];
+        for point in burst.iter() {
+            forest.update(point.clone());
+        }
+        let group_score = forest.score_group(&burst);
+        assert!(group_score > 5.0 * forest.anomaly_score(&burst[0]));
+        assert!(group_score > forest.score_group(&points[490..]));
+    }
+
+    #[test]
+    #[should_panic(expected = "NaN or infinite")]
+    fn non_finite_error() {
+        let mut forest: RandomCutForest<f32> = RandomCutForestBuilder::new(2).build();
+        forest.update(vec![0.0, f32::NAN]);
+    }
+
+    #[test]
+    fn masked_dimensions() {
+        let dimension = 3;
+        let mut forest: RandomCutForest<f32> = RandomCutForestBuilder::new(dimension)
+            .num_trees(20)
+            .sample_size(64)
+            .time_decay(0.1)
+            .seed(17)
+            .build();
+        let mut rng = ChaCha8Rng::seed_from_u64(17);
+        for _ in 0..200 {
+            let point: Vec<f32> = (0..dimension).map(|_| rng.sample(StandardNormal)).collect();
+            forest.update(point);
+        }
+        let baseline = forest.anomaly_score(&vec![0.0; dimension]);
+
+        // a broken feed on the last dimension replaces most of the samples
+        forest.set_masked_dimensions(vec![false, false, true]);
+        for _ in 0..200 {
+            let mut point: Vec<f32> = (0..dimension).map(|_| rng.sample(StandardNormal)).collect();
+            point[2] = rng.gen_range(-1000.0..1000.0);
+            forest.update(point);
+        }
+
+        // the broken dimension does not make normal points anomalous
+        let score = forest.anomaly_score(&vec![0.0, 0.0, 500.0]);
+        assert!(score < 2.0 * baseline, "{} >= 2 * {}", score, baseline);
+        assert!(forest.anomaly_score(&vec![8.0, 0.0, 0.0]) > 2.0 * baseline);
+
+        // new nodes avoid cutting the masked dimension
+        let num_cuts = forest.trees().iter()
+            .flat_map(|tree| tree.tree().node_store().iter()
+                .filter_map(|(_, node)| match node {
+                    Node::Internal(internal) => Some(internal.cut().dimension()),
+                    Node::Leaf(_) => None,
+                })
+                .collect::<Vec<usize>>())
+            .fold(vec![0; dimension], |mut counts, d| { counts[d] += 1; counts });
+        assert!(num_cuts[2] < num_cuts[0] / 2, "{:?}", num_cuts);
+    }
+
+    #[test]
+    fn score_and_update() {
+        let new_forest = || -> RandomCutForest<f32> {
+            RandomCutForestBuilder::new(2)
+                .num_trees(10)
+                .sample_size(32)
+                .output_after(16)
+                .privacy_noise(10.0, 0.1)
+                .non_finite_policy(NonFinitePolicy::Skip)
+                .dimension_bounds(vec![-2.0, -2.0], vec![2.0, 2.0])
+                .clamp_to_bounds(true)
+                .input_scaling(vec![2.0, 0.5], vec![1.0, 0.0])
+                .seed(11)
+                .build()
+        };
+        let mut forest = new_forest();
+        let mut expected_forest = new_forest();
+        forest.set_masked_dimensions(vec![false, true]);
+        expected_forest.set_masked_dimensions(vec![false, true]);
+
+        let mut points = seeded_randn(200, 2, 11);
+        points[50][1] = f32::NAN;
+        points[100][0] = 10.0;
+        for point in points.into_iter() {
+            let expected = expected_forest.try_anomaly_score(&point).unwrap();
+            expected_forest.try_update(point.clone()).unwrap();
+            assert_eq!(forest.score_and_update(point).unwrap(), expected);
+        }
+        assert_eq!(forest.num_observations(), 199);
+        assert_eq!(forest.num_rejected_points(), 1);
+        assert!(forest.score_and_update(vec![0.0]).is_err());
+    }
+
+    #[test]
+    fn tree_reliability() {
+        let mut forest: RandomCutForest<f64> = RandomCutForestBuilder::new(2)
+            .num_trees(20)
+            .sample_size(64)
+            .tree_reliability(0.02)
+            .weighted_voting(true)
+            .seed(11)
+            .build();
+        let mut rng = ChaCha8Rng::seed_from_u64(3);
+        for _ in 0..1000 {
+            let point = vec![rng.gen::<f64>(), rng.gen::<f64>()];
+            forest.score_and_update(point).unwrap();
+        }
+
+        let reliability = forest.tree_reliability().unwrap();
+        assert_eq!(reliability.len(), 20);
+        for (i, (id, weight)) in reliability.iter().enumerate() {
+            assert_eq!(*id, i);
+            assert!(*weight >= 0.0 && *weight <= 1.0);
+        }
+        assert!(reliability.iter().any(|(_, weight)| *weight > 0.5));
+        assert!(forest.anomaly_score(&vec![5.0, 5.0]) > forest.anomaly_score(&vec![0.5, 0.5]));
+
+        let forest: RandomCutForest<f64> = RandomCutForestBuilder::new(2).build();
+        assert!(forest.tree_reliability().is_none());
+        assert!(!forest.weighted_voting());
+    }
+
+    #[test]
+    fn build_from_slice() {
+        let dimension = 3;
+        let data = seeded_randn(5000, dimension, 17);
+        let builder = || RandomCutForestBuilder::new(dimension)
+            .num_trees(50)
+            .sample_size(128)
+            .time_decay(0.0001)
+            .seed(17);
+
+        let bulk: RandomCutForest<f32> = builder().build_from_slice(&data);
+        let mut sequential: RandomCutForest<f32> = builder().build();
+        for point in data.iter() {
+            sequential.update(point.clone());
+        }
+
+        assert_eq!(bulk.num_observations(), sequential.num_observations());
+        for tree in bulk.trees() {
+            assert_eq!(tree.tree().mass(), 128);
+            assert_eq!(tree.num_samples(), 128);
+        }
+        assert!(bulk.tree_divergence().iter().all(|tree| !tree.is_diverged()));
+
+        // the forests differ only by their random choices
+        for query in [vec![0.0, 0.0, 0.0], vec![2.0, -1.0, 0.5], vec![10.0, 10.0, 10.0]].iter() {
+            let (bulk_score, sequential_score) = (bulk.anomaly_score(query), sequential.anomaly_score(query));
+            assert!((bulk_score - sequential_score).abs() < 0.25 * sequential_score,
+                "{} vs {}", bulk_score, sequential_score);
+        }
+    }
+
+    #[test]
+    fn pre_filter() {
+        let points = seeded_randn(2000, 2, 3);
+        let pre_filter = PreFilter { z_bound: 3.0, score_threshold: 0.0, false_negative_budget: 0.1 };
+        let mut forest: RandomCutForest<f32> = RandomCutForestBuilder::new(2)
+            .pre_filter(pre_filter)
+            .seed(3)
+            .build();
+        let mut num_zero_scores = 0;
+        for point in points.iter() {
+            if forest.score_and_update(point.clone()).unwrap() == 0.0 {
+                num_zero_scores += 1;
+            }
+        }
+
+        // every audited point is a false negative, which shrinks the bound
+        // until few points are skipped
+        let state = forest.pre_filter_state().unwrap();
+        assert_eq!(state.num_false_negatives(), state.num_audited());
+        assert!(state.z_bound() < 1.0);
+        assert_eq!(num_zero_scores, state.num_skipped() + 1);
+        assert!(forest.anomaly_score(&vec![10.0, 10.0]) > 0.0);
+        assert_eq!(forest.parameters().pre_filter, Some(pre_filter));
+        assert_ne!(forest.parameters().fingerprint(),
+                   ForestParameters { pre_filter: None, ..forest.parameters() }.fingerprint());
+    }
+
+    thread_local! {
+        static EPOCH: Instant = Instant::now();
+        static TICKS: std::cell::Cell<u64> = const { std::cell::Cell::new(0) };
+    }
+
+    /// A clock advancing by one millisecond at every reading.
+    fn fake_clock() -> Instant {
+        let ticks = TICKS.with(|ticks| { ticks.set(ticks.get() + 1); ticks.get() });
+        EPOCH.with(|epoch| *epoch + Duration::from_millis(ticks))
+    }
+
+    #[test]
+    fn update_rate_limit() {
+        let limit = UpdateRateLimit { clock: fake_clock, ..UpdateRateLimit::new(50, Duration::from_secs(3600)) };
+        let mut forest: RandomCutForest<f64> = RandomCutForestBuilder::new(1)
+            .num_trees(20)
+            .sample_size(64)
+            .update_rate_limit(limit)
+            .seed(3)
+            .build();
+        // two halves of a stream in one window: most of the first half is
+        // admitted one for one, the second half rarely but with a large
+        // multiplicity
+        for i in 0..4000 {
+            let value = if i < 2000 { 0.0 } else { 1.0 };
+            forest.score_and_update(vec![value]).unwrap();
+        }
+
+        let governor = forest.update_governor().unwrap();
+        assert!(governor.num_shed() > 3000);
+        assert_eq!(forest.num_observations(), 4000);
+        assert!(forest.tree_divergence().iter().all(|tree| !tree.is_diverged()));
+
+        // both halves are still equally represented in the samples
+        let samples: Vec<f64> = forest.trees().iter()
+            .flat_map(|tree| tree.retained_points().into_iter().map(|(_, point)| point[0]))
+            .collect();
+        let second_half = samples.iter().filter(|&&value| value == 1.0).count() as f64 / samples.len() as f64;
+        assert!(second_half > 0.35 && second_half < 0.65, "{}", second_half);
+
+        let parameters = forest.parameters();
+        assert_eq!(parameters.update_rate_limit, Some(limit));
+        assert_eq!(parameters.fingerprint(),
+                   ForestParameters { update_rate_limit: None, ..parameters.clone() }.fingerprint());
+        let builder = RandomCutForestBuilder::<f64>::from_parameters(&parameters).unwrap();
+        assert_eq!(builder.build().parameters().update_rate_limit, Some(limit));
+
+        // added trees count the delivered updates as seen, not the shed ones
+        forest.add_trees(2);
+        forest.resync_tree(0);
+        for _ in 0..1000 {
+            forest.score_and_update(vec![0.5]).unwrap();
+        }
+        let divergence = forest.tree_divergence();
+        assert!(divergence.iter().all(|tree| !tree.is_diverged()));
+        assert!(divergence.iter().all(|tree| tree.entries_seen == divergence[1].entries_seen));
+        let governor = forest.update_governor().unwrap();
+        assert_eq!(divergence[0].entries_seen, governor.num_admitted());
+    }
+
+    #[test]
+    #[should_panic(expected = "Dimension subsample size must be positive")]
+    fn zero_dimension_subsample() {
+        // no dimension could be cut, so building a tree of two distinct
+        // points would fail
+        let mut parameters = RandomCutForestBuilder::<f32>::new(2).build().parameters();
+        parameters.cut_strategy = CutStrategy::DimensionSubsample(0);
+        let error = RandomCutForestBuilder::from_parameters(&parameters).err().unwrap();
+        assert!(error.starts_with("cut_strategy:"));
+
+        RandomCutForestBuilder::<f32>::new(2).cut_strategy(CutStrategy::DimensionSubsample(0));
+    }
+
+    #[test]
+    fn update_rate_limit_with_fake_clock() {
+        let limit = UpdateRateLimit { clock: fake_clock, ..UpdateRateLimit::new(100, Duration::from_secs(1)) };
+        let run = || {
+            TICKS.with(|ticks| ticks.set(0));
+            let mut forest: RandomCutForest<f64> = RandomCutForestBuilder::new(1)
+                .num_trees(10)
+                .sample_size(64)
+                .update_rate_limit(limit)
+                .seed(5)
+                .build();
+            // 1000 updates per window of the clock, ten times the limit
+            for i in 0..5000 {
+                forest.score_and_update(vec![(i % 10) as f64]).unwrap();
+            }
+            forest
+        };
+
+        let forest = run();
+        let governor = forest.update_governor().unwrap();
+        // the first window reacts within a logarithmic factor of the limit,
+        // the next four admit about the limit each
+        assert!(governor.num_admitted() > 500 && governor.num_admitted() < 1000,
+            "{}", governor.num_admitted());
+
+        // with a deterministic clock, a seeded forest is reproducible
+        let again = run();
+        assert_eq!(again.update_governor().unwrap().num_admitted(), governor.num_admitted());
+        assert_eq!(again.anomaly_score(&vec![20.0]), forest.anomaly_score(&vec![20.0]));
+    }
+
+    #[test]
+    #[should_panic(expected = "Dimension mismatch. Expected 3-dimensional input.")]
+    fn out_of_bounds_dimension_mismatch() {
+        let forest: RandomCutForest<f32> = RandomCutForestBuilder::new(3)
+            .dimension_bounds(vec![0.0; 3], vec![1.0; 3])
+            .build();
+        forest.out_of_bounds_dimensions(&[0.5, 0.5]);
+    }
+
+    #[test]
+    fn latency_of_failed_calls() {
+        let mut forest: RandomCutForest<f32> = RandomCutForestBuilder::new(2)
+            .track_latency(true)
+            .seed(3)
+            .build();
+        assert!(forest.try_update(vec![1.0]).is_err());
+        assert!(forest.try_update(vec![1.0, f32::NAN]).is_err());
+        forest.try_update(vec![1.0, 2.0]).unwrap();
+        assert!(forest.score_and_update(vec![1.0]).is_err());
+
+        assert_eq!(forest.latency_histogram(TimedOperation::Update).unwrap().count(), 3);
+        assert_eq!(forest.latency_histogram(TimedOperation::ScoreAndUpdate).unwrap().count(), 1);
+        assert_eq!(forest.num_observations(), 1);
+    }
+}
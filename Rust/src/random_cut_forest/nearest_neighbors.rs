@@ -0,0 +1,174 @@
+extern crate num_traits;
+use num_traits::{Float, Zero};
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::iter::Sum;
+
+use crate::random_cut_forest::RandomCutForest;
+use crate::visitor::NearNeighborVisitor;
+
+/// A point retained by the forest close to a query point, see
+/// [`RandomCutForest::nearest_neighbors`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Neighbor<T> {
+    /// The neighbor, in input units.
+    pub point: Vec<T>,
+    /// Euclidean distance from the query point, in input units.
+    pub distance: T,
+    /// Number of trees that proposed the neighbor as a candidate.
+    pub num_votes: usize,
+}
+
+impl<T> RandomCutForest<T>
+    where T: Float + Sum + Zero
+{
+
+    /// Returns the `k` points retained by the forest closest to the input
+    /// point, closest first.
+    ///
+    /// Each tree proposes the points of the smallest subtree around the
+    /// point that holds `k` points, see [`NearNeighborVisitor`]. Candidates
+    /// are merged across trees by value and ranked by Euclidean distance in
+    /// input units, ties going to the candidate proposed by more trees.
+    /// Since every tree samples the stream, the neighbors are among the
+    /// points retained by the forest, not the whole stream, and a close
+    /// point is only missed if every tree separated it from the query early.
+    /// Returns an empty vector if there is not yet enough data or if the
+    /// point is skipped under the forest's [`NonFinitePolicy`].
+    ///
+    /// # Panics
+    ///
+    /// If the dimensionality of the point does not match the dimensionality
+    /// of the forest.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use random_cut_forest::{RandomCutForest, RandomCutForestBuilder};
+    ///
+    /// let mut forest: RandomCutForest<f32> = RandomCutForestBuilder::new(2)
+    ///     .num_trees(20)
+    ///     .sample_size(64)
+    ///     .seed(0)
+    ///     .build();
+    /// for i in 0..50 {
+    ///     forest.update(vec![i as f32, 0.0]);
+    /// }
+    ///
+    /// let neighbors = forest.nearest_neighbors(&[20.2, 0.0], 3);
+    /// let points: Vec<Vec<f32>> = neighbors.iter().map(|n| n.point.clone()).collect();
+    /// assert_eq!(points, vec![vec![20.0, 0.0], vec![21.0, 0.0], vec![19.0, 0.0]]);
+    /// assert!((neighbors[0].distance - 0.2).abs() < 1e-5);
+    /// assert!(neighbors[0].num_votes > 0);
+    /// ```
+    ///
+    /// [`NonFinitePolicy`]: crate::NonFinitePolicy
+    pub fn nearest_neighbors(&self, point: &[T], k: usize) -> Vec<Neighbor<T>> {
+        assert_eq!(point.len(), self.dimension,
+            "Dimension mismatch. Expected {}-dimensional input.",
+            self.dimension);
+        if self.num_observations <= self.output_after || k == 0 {
+            return Vec::new();
+        }
+        let query = match self.sanitize_query(point) {
+            Ok(Some(query)) => query,
+            Ok(None) => return Vec::new(),
+            Err(message) => panic!("{}", message),
+        };
+
+        let candidates = self.trees.iter()
+            .filter(|tree| tree.tree().mass() > 0)
+            .map(|tree| tree.traverse(&query, &mut NearNeighborVisitor::new(tree.tree(), k)));
+        self.merge_neighbors(point, candidates, k)
+    }
+
+    /// Merge the candidate neighbors of a point proposed by each tree, in
+    /// the units of the trees, into the `k` closest, see
+    /// [`nearest_neighbors`](Self::nearest_neighbors).
+    pub(super) fn merge_neighbors<I>(&self, point: &[T], candidates: I, k: usize) -> Vec<Neighbor<T>>
+        where I: Iterator<Item=Vec<Vec<T>>>
+    {
+        // candidates proposed by several trees are found by the bit
+        // patterns of their coordinates
+        let mut neighbors: Vec<Neighbor<T>> = Vec::new();
+        let mut indexes: HashMap<Vec<u64>, usize> = HashMap::new();
+        for tree_candidates in candidates {
+            for candidate in tree_candidates {
+                let candidate = self.to_input_units(candidate);
+                let key = candidate.iter().map(|x| x.to_f64().unwrap().to_bits()).collect();
+                match indexes.get(&key) {
+                    Some(&index) => neighbors[index].num_votes += 1,
+                    None => {
+                        let distance = candidate.iter().zip(point.iter())
+                            .map(|(&x, &y)| (x - y) * (x - y))
+                            .sum::<T>()
+                            .sqrt();
+                        indexes.insert(key, neighbors.len());
+                        neighbors.push(Neighbor { point: candidate, distance, num_votes: 1 });
+                    }
+                }
+            }
+        }
+        neighbors.sort_by(|a, b| {
+            a.distance.partial_cmp(&b.distance).unwrap_or(Ordering::Equal)
+                .then(b.num_votes.cmp(&a.num_votes))
+        });
+        neighbors.truncate(k);
+        neighbors
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::random_cut_forest::RandomCutForestBuilder;
+    use crate::random_cut_forest::tests::seeded_randn;
+
+    #[test]
+    fn nearest_neighbors() {
+        let points = seeded_randn(300, 3, 11);
+        let mut forest: RandomCutForest<f32> = RandomCutForestBuilder::new(3)
+            .num_trees(30)
+            .sample_size(128)
+            .input_scaling(vec![2.0; 3], vec![1.0; 3])
+            .seed(11)
+            .build();
+        for point in points.iter() {
+            forest.update(point.clone());
+        }
+
+        // compare with an exhaustive search of the retained points
+        let retained: Vec<Vec<f32>> = forest.pooled_points(None).into_iter()
+            .map(|(_, point)| forest.to_input_units(point))
+            .collect();
+        let distance = |a: &Vec<f32>, b: &[f32]| -> f32 {
+            a.iter().zip(b.iter()).map(|(x, y)| (x - y) * (x - y)).sum::<f32>().sqrt()
+        };
+        for query in points.iter().take(20) {
+            let query: Vec<f32> = query.iter().map(|x| x + 0.01).collect();
+            let neighbors = forest.nearest_neighbors(&query, 4);
+            assert_eq!(neighbors.len(), 4);
+            assert!(neighbors.windows(2).all(|pair| pair[0].distance <= pair[1].distance));
+            let closest = retained.iter().map(|point| distance(point, &query)).fold(f32::INFINITY, f32::min);
+            assert!((neighbors[0].distance - closest).abs() < 1e-4);
+            assert!((distance(&neighbors[0].point, &query) - neighbors[0].distance).abs() < 1e-4);
+        }
+        assert!(forest.nearest_neighbors(&points[0], 0).is_empty());
+
+        // every tree retains a short stream, and proposes the same closest point
+        let mut forest: RandomCutForest<f32> = RandomCutForestBuilder::new(3)
+            .num_trees(10)
+            .sample_size(64)
+            .seed(3)
+            .build();
+        for point in points.iter().take(20) {
+            forest.update(point.clone());
+        }
+        let neighbors = forest.nearest_neighbors(&points[0], 3);
+        assert_eq!(neighbors[0].point, points[0]);
+        assert_eq!(neighbors[0].num_votes, 10);
+        assert!(neighbors[1..].iter().all(|neighbor| neighbor.point != points[0]));
+    }
+}
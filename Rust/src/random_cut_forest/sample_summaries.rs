@@ -0,0 +1,380 @@
+extern crate num_traits;
+use num_traits::{Float, Zero};
+
+use std::cmp::Ordering;
+use std::iter::Sum;
+
+use crate::Node;
+use crate::random_cut_forest::RandomCutForest;
+
+/// Aggregate of the cuts of the forest along one dimension at one depth,
+/// see [`RandomCutForest::root_cut_summary`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct CutSummary<T> {
+    /// Depth of the cut nodes, zero at the root.
+    pub depth: usize,
+    pub dimension: usize,
+    /// Number of nodes at this depth cutting along this dimension.
+    pub num_cuts: usize,
+    /// Smallest and largest cut values, in input units.
+    pub min_value: T,
+    pub max_value: T,
+    /// Mean fraction of the mass of the cut nodes that lies left of the cut.
+    pub mean_left_fraction: T,
+}
+
+impl<T> RandomCutForest<T>
+    where T: Float + Sum + Zero
+{
+
+    /// Returns the histogram of the depths of the leaves reached by the
+    /// input point across the trees of the forest.
+    ///
+    /// Entry `d` of the result is the number of trees in which the traversal
+    /// of the point ends at a leaf of depth `d`; the root has depth zero. Two
+    /// points with similar anomaly scores can reach very different depths,
+    /// for example when one is isolated early in a few trees and the other
+    /// sits moderately deep in all of them. Trees without points are not
+    /// counted, so the profile is empty for an empty forest.
+    ///
+    /// The point is checked like a query point of
+    /// [`anomaly_score`](Self::anomaly_score), including the
+    /// [`NonFinitePolicy`] of the forest; skipped points have an empty
+    /// profile.
+    ///
+    /// # Panics
+    ///
+    /// If the dimensionality of the point does not match the dimensionality
+    /// of the forest.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use random_cut_forest::{RandomCutForest, RandomCutForestBuilder};
+    ///
+    /// let mut forest: RandomCutForest<f32> = RandomCutForestBuilder::new(2)
+    ///     .num_trees(20)
+    ///     .sample_size(64)
+    ///     .seed(17)
+    ///     .build();
+    /// for i in 0..200 {
+    ///     forest.update(vec![(i % 10) as f32, (i % 7) as f32]);
+    /// }
+    ///
+    /// let profile = forest.depth_profile(&[4.0, 3.0]);
+    /// assert_eq!(profile.iter().sum::<usize>(), 20);
+    ///
+    /// // an outlier is separated close to the root
+    /// let outlier_profile = forest.depth_profile(&[100.0, 100.0]);
+    /// assert!(outlier_profile.len() < profile.len());
+    /// ```
+    ///
+    /// [`NonFinitePolicy`]: crate::NonFinitePolicy
+    pub fn depth_profile(&self, point: &[T]) -> Vec<usize> {
+        assert_eq!(point.len(), self.dimension,
+            "Dimension mismatch. Expected {}-dimensional input.",
+            self.dimension);
+        let point = match self.sanitize_query(point) {
+            Ok(Some(point)) => point,
+            Ok(None) => return Vec::new(),
+            Err(message) => panic!("{}", message),
+        };
+
+        let mut profile: Vec<usize> = Vec::new();
+        for sampled_tree in self.trees.iter() {
+            let tree = sampled_tree.tree();
+            if tree.root_node().is_none() {
+                continue;
+            }
+            let depth = tree.iter(&point).count() - 1;
+            if profile.len() <= depth {
+                profile.resize(depth + 1, 0);
+            }
+            profile[depth] += 1;
+        }
+        profile
+    }
+
+    /// Returns a summary of the cuts in the first `levels` levels of the
+    /// trees, aggregated by depth and cut dimension.
+    ///
+    /// The coarse partition learned by the forest is a quick sanity check of
+    /// its input: a dimension that is never cut near the root is constant or
+    /// negligible in range, while a dimension that takes most of the cuts has
+    /// an outlying range, such as a garbage or unscaled feature. The mass
+    /// split shows whether cuts isolate a few points or divide the sample.
+    /// The summaries are sorted by depth and then by dimension.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use random_cut_forest::{RandomCutForest, RandomCutForestBuilder};
+    ///
+    /// let mut forest: RandomCutForest<f64> = RandomCutForestBuilder::new(3)
+    ///     .num_trees(20)
+    ///     .sample_size(64)
+    ///     .seed(5)
+    ///     .build();
+    /// for i in 0..200 {
+    ///     // the last dimension has a much larger range than the others
+    ///     forest.update(vec![(i % 10) as f64, 5.0, (i % 7) as f64 * 1000.0]);
+    /// }
+    ///
+    /// let summary = forest.root_cut_summary(1);
+    /// assert_eq!(summary.iter().map(|cut| cut.num_cuts).sum::<usize>(), 20);
+    /// // the constant dimension is never cut
+    /// assert!(summary.iter().all(|cut| cut.dimension != 1));
+    /// let dominant = summary.iter().max_by_key(|cut| cut.num_cuts).unwrap();
+    /// assert_eq!(dominant.dimension, 2);
+    /// ```
+    pub fn root_cut_summary(&self, levels: usize) -> Vec<CutSummary<T>> {
+        let mut summaries: Vec<CutSummary<T>> = Vec::new();
+        for sampled_tree in self.trees.iter() {
+            let tree = sampled_tree.tree();
+            let mut level: Vec<usize> = tree.root_node().into_iter().collect();
+            for depth in 0..levels {
+                let mut next_level: Vec<usize> = Vec::new();
+                for node_key in level {
+                    let node = match tree.get_node(node_key) {
+                        Node::Internal(node) => node,
+                        Node::Leaf(_) => continue,
+                    };
+                    let cut = node.cut();
+                    let value = self.coordinate_to_input_units(cut.dimension(), cut.value());
+                    let left_fraction = T::from(tree.get_node(node.left()).mass()).unwrap() /
+                        T::from(node.mass()).unwrap();
+
+                    match summaries.iter_mut()
+                        .find(|summary| summary.depth == depth && summary.dimension == cut.dimension())
+                    {
+                        Some(summary) => {
+                            summary.num_cuts += 1;
+                            summary.min_value = Float::min(summary.min_value, value);
+                            summary.max_value = Float::max(summary.max_value, value);
+                            summary.mean_left_fraction = summary.mean_left_fraction + left_fraction;
+                        },
+                        None => summaries.push(CutSummary {
+                            depth,
+                            dimension: cut.dimension(),
+                            num_cuts: 1,
+                            min_value: value,
+                            max_value: value,
+                            mean_left_fraction: left_fraction,
+                        }),
+                    }
+                    next_level.push(node.left());
+                    next_level.push(node.right());
+                }
+                level = next_level;
+            }
+        }
+
+        for summary in summaries.iter_mut() {
+            summary.mean_left_fraction = summary.mean_left_fraction / T::from(summary.num_cuts).unwrap();
+        }
+        summaries.sort_by_key(|summary| (summary.depth, summary.dimension));
+        summaries
+    }
+
+    /// Returns the `k` retained points with the largest total mass across the
+    /// trees of the forest.
+    ///
+    /// Each tree stores repeated points in a single leaf whose mass counts
+    /// the repetitions. Summing these leaf masses across trees gives an
+    /// approximate count of the most frequent points in the recent stream.
+    /// The result is a vector of `(point, mass)` pairs sorted by decreasing
+    /// mass.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use random_cut_forest::{RandomCutForest, RandomCutForestBuilder};
+    ///
+    /// let mut forest: RandomCutForest<f32> = RandomCutForestBuilder::new(2)
+    ///     .num_trees(10)
+    ///     .sample_size(32)
+    ///     .build();
+    ///
+    /// for i in 0..32 {
+    ///     forest.update(vec![i as f32, 0.0]);
+    ///     forest.update(vec![-1.0, -1.0]);
+    /// }
+    ///
+    /// let heavy_hitters = forest.heavy_hitters(1);
+    /// assert_eq!(heavy_hitters.len(), 1);
+    /// assert_eq!(heavy_hitters[0].0, vec![-1.0, -1.0]);
+    /// ```
+    pub fn heavy_hitters(&self, k: usize) -> Vec<(Vec<T>, u32)> {
+        let mut merged = self.retained_points();
+        merged.sort_by(|(_, a), (_, b)| b.cmp(a));
+        merged.truncate(k);
+        merged
+    }
+
+    /// Returns the `k` distinct retained points closest to a query window in
+    /// the L1 norm, together with their distances.
+    ///
+    /// When the forest is updated with shingled points, that is, windows of
+    /// consecutive stream values, the retained points are a sample of past
+    /// subsequences. This answers "have we seen this pattern before" by
+    /// searching them for the windows most similar to `query_window`. The
+    /// result is sorted by increasing distance.
+    ///
+    /// # Panics
+    ///
+    /// If the dimensionality of the query does not match the dimensionality
+    /// of the forest.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use random_cut_forest::{RandomCutForest, RandomCutForestBuilder};
+    ///
+    /// let mut forest: RandomCutForest<f32> = RandomCutForestBuilder::new(3)
+    ///     .num_trees(10)
+    ///     .sample_size(64)
+    ///     .build();
+    ///
+    /// // shingles of size 3 of a sawtooth stream 0, 1, ..., 9, 0, 1, ...
+    /// let stream: Vec<f32> = (0..100).map(|i| (i % 10) as f32).collect();
+    /// for window in stream.windows(3) {
+    ///     forest.update(window.to_vec());
+    /// }
+    ///
+    /// let similar = forest.find_similar_windows(&vec![2.0, 3.0, 4.5], 1);
+    /// assert_eq!(similar[0].0, vec![2.0, 3.0, 4.0]);
+    /// assert_eq!(similar[0].1, 0.5);
+    /// ```
+    pub fn find_similar_windows(&self, query_window: &[T], k: usize) -> Vec<(Vec<T>, T)> {
+        assert_eq!(query_window.len(), self.dimension,
+            "Dimension mismatch. Expected {}-dimensional input.",
+            self.dimension);
+
+        let mut similar: Vec<(Vec<T>, T)> = self.retained_points().into_iter()
+            .map(|(point, _)| {
+                let distance = point.iter().zip(query_window.iter())
+                    .map(|(x, y)| (*x - *y).abs())
+                    .sum();
+                (point, distance)
+            })
+            .collect();
+        similar.sort_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+        similar.truncate(k);
+        similar
+    }
+
+    /// Returns the motif of the retained points: the point that is most
+    /// repeated or lies in the densest region, with its effective depth.
+    ///
+    /// The effective depth of a leaf is `depth + log2(mass + 1)`, the
+    /// quantity whose inverse is the score of a seen point. It is large for
+    /// leaves deep in the tree, which lie in dense regions, and for leaves
+    /// with a large mass, which hold repeated points. The effective depth of
+    /// a retained point is the mean over the trees retaining it. The motif is
+    /// the retained point with the largest effective depth and the
+    /// [`discord`](Self::discord) the one with the smallest. Together they
+    /// give a quick structural summary of the stream from the sample already
+    /// held by the forest, in particular for shingled points.
+    ///
+    /// Returns `None` if the forest retains no points.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use random_cut_forest::{RandomCutForest, RandomCutForestBuilder};
+    ///
+    /// let mut forest: RandomCutForest<f32> = RandomCutForestBuilder::new(2)
+    ///     .num_trees(20)
+    ///     .sample_size(64)
+    ///     .build();
+    ///
+    /// for i in 0..60 {
+    ///     forest.update(vec![(i % 6) as f32, 0.0]);
+    /// }
+    /// forest.update(vec![3.0, 50.0]);
+    ///
+    /// let (motif, _) = forest.motif().unwrap();
+    /// let (discord, _) = forest.discord().unwrap();
+    /// assert_eq!(motif[1], 0.0);
+    /// assert_eq!(discord, vec![3.0, 50.0]);
+    /// ```
+    pub fn motif(&self) -> Option<(Vec<T>, T)> {
+        self.effective_depths().into_iter()
+            .max_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap_or(Ordering::Equal))
+    }
+
+    /// Returns the discord of the retained points: the most isolated point,
+    /// with its effective depth.
+    ///
+    /// See [`motif`](Self::motif) for the definition of the effective depth.
+    /// Returns `None` if the forest retains no points.
+    pub fn discord(&self) -> Option<(Vec<T>, T)> {
+        self.effective_depths().into_iter()
+            .min_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap_or(Ordering::Equal))
+    }
+
+    /// Returns the distinct retained points with their mean effective leaf
+    /// depth across the trees retaining them, sorted by point.
+    fn effective_depths(&self) -> Vec<(Vec<T>, T)> {
+        let mut leaves: Vec<(Vec<T>, T)> = Vec::new();
+        for sampled_tree in self.trees.iter() {
+            let tree = sampled_tree.tree();
+            let point_store = sampled_tree.borrow_point_store();
+            for (_, node) in tree.node_store().iter() {
+                if let Node::Leaf(leaf) = node {
+                    let mut depth = 0;
+                    let mut parent = leaf.parent();
+                    while let Some(key) = parent {
+                        depth += 1;
+                        parent = tree.get_parent(key);
+                    }
+                    let effective_depth = T::from(depth).unwrap() +
+                        T::from(leaf.mass() + 1).unwrap().log2();
+                    let point = point_store.get(leaf.point()).unwrap();
+                    leaves.push((self.to_input_units(point.clone()), effective_depth));
+                }
+            }
+        }
+
+        leaves.sort_by(|(p, _), (q, _)| p.partial_cmp(q).unwrap_or(Ordering::Equal));
+        let mut merged: Vec<(Vec<T>, T, usize)> = Vec::new();
+        for (point, depth) in leaves.into_iter() {
+            match merged.last_mut() {
+                Some((last_point, last_depth, count)) if *last_point == point => {
+                    *last_depth = *last_depth + depth;
+                    *count += 1;
+                },
+                _ => merged.push((point, depth, 1)),
+            }
+        }
+        merged.into_iter()
+            .map(|(point, depth, count)| (point, depth / T::from(count).unwrap()))
+            .collect()
+    }
+
+    /// Returns the distinct points retained by the trees of the forest and
+    /// their total leaf mass across trees, sorted by point.
+    fn retained_points(&self) -> Vec<(Vec<T>, u32)> {
+        let mut leaves: Vec<(Vec<T>, u32)> = Vec::new();
+        for sampled_tree in self.trees.iter() {
+            let point_store = sampled_tree.borrow_point_store();
+            for (_, node) in sampled_tree.tree().node_store().iter() {
+                if let Node::Leaf(leaf) = node {
+                    let point = point_store.get(leaf.point()).unwrap();
+                    leaves.push((self.to_input_units(point.clone()), leaf.mass()));
+                }
+            }
+        }
+
+        // sort the leaves so that equal points are adjacent and merge them
+        leaves.sort_by(|(p, _), (q, _)| p.partial_cmp(q).unwrap_or(Ordering::Equal));
+        let mut merged: Vec<(Vec<T>, u32)> = Vec::new();
+        for (point, mass) in leaves.into_iter() {
+            match merged.last_mut() {
+                Some((last_point, last_mass)) if *last_point == point => *last_mass += mass,
+                _ => merged.push((point, mass)),
+            }
+        }
+        merged
+    }
+}
@@ -0,0 +1,301 @@
+extern crate num_traits;
+use num_traits::{Float, Zero};
+
+extern crate rand;
+use rand::{Rng, SeedableRng};
+
+extern crate rand_chacha;
+use rand_chacha::ChaCha8Rng;
+
+use std::cmp::Ordering;
+use std::iter::Sum;
+
+use crate::random_cut_forest::{reliability_weight, RandomCutForest};
+
+/// Number of resamples used by [`RandomCutForest::score_with_confidence`].
+const BOOTSTRAP_RESAMPLES: usize = 200;
+
+/// Distribution of the anomaly scores of a point across the trees of a
+/// forest, see [`RandomCutForest::score_with_distribution`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScoreDistribution<T> {
+    pub mean: T,
+    /// Population standard deviation of the tree scores.
+    pub std_dev: T,
+    pub min: T,
+    pub max: T,
+    /// Tree scores at the requested quantiles, in the same order.
+    pub quantiles: Vec<T>,
+}
+
+impl<T> RandomCutForest<T>
+    where T: Float + Sum + Zero
+{
+
+    /// Returns the anomaly score of the input point together with the lower
+    /// and upper bounds of a bootstrap confidence interval at the given
+    /// level.
+    ///
+    /// The interval is the percentile interval of the forest score over
+    /// resamples, with replacement, of the per-tree scores, widened if needed
+    /// to contain the score. It is wide when the trees disagree, for example
+    /// during warm-up or after trees were replaced, so thresholding logic can
+    /// ignore scores with a wide interval. Resamples use a fixed seed, so the
+    /// result is deterministic.
+    /// The score and both bounds are zero if there is not yet enough data or
+    /// if the point is skipped under the forest's [`NonFinitePolicy`].
+    ///
+    /// # Panics
+    ///
+    /// If the dimensionality of the point does not match the dimensionality
+    /// of the forest, or if `level` is not in `(0, 1)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use random_cut_forest::{RandomCutForest, RandomCutForestBuilder};
+    ///
+    /// let mut forest: RandomCutForest<f64> = RandomCutForestBuilder::new(2)
+    ///     .num_trees(30)
+    ///     .sample_size(64)
+    ///     .seed(9)
+    ///     .build();
+    /// for i in 0..20 {
+    ///     forest.update(vec![(i % 10) as f64, (i % 7) as f64]);
+    /// }
+    /// let (score, lower, upper) = forest.score_with_confidence(&[20.0, 20.0], 0.9);
+    /// assert!(lower <= score && score <= upper);
+    /// let warm_up_width = upper - lower;
+    ///
+    /// for i in 20..2000 {
+    ///     forest.update(vec![(i % 10) as f64, (i % 7) as f64]);
+    /// }
+    /// let (_, lower, upper) = forest.score_with_confidence(&[20.0, 20.0], 0.9);
+    /// assert!(upper - lower < warm_up_width);
+    /// ```
+    ///
+    /// [`NonFinitePolicy`]: crate::NonFinitePolicy
+    pub fn score_with_confidence(&self, point: &[T], level: f64) -> (T, T, T) {
+        assert!(level > 0.0 && level < 1.0, "Confidence level must be in (0, 1)");
+        let tree_scores: Vec<T> = self.tree_scores(point).into_iter()
+            .map(|(_, score)| score)
+            .collect();
+        if tree_scores.is_empty() {
+            return (Zero::zero(), Zero::zero(), Zero::zero());
+        }
+
+        // resample the trees with their voting weights, see combine_scores
+        let mut weights: Vec<T> = match (self.weighted_voting, &self.tree_reliability) {
+            (true, Some(reliability)) => reliability.iter().map(reliability_weight).collect(),
+            _ => vec![T::one(); tree_scores.len()],
+        };
+        if weights.iter().all(|weight| *weight == T::zero()) {
+            weights = vec![T::one(); tree_scores.len()];
+        }
+
+        let num_trees = tree_scores.len();
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let mut resampled: Vec<T> = (0..BOOTSTRAP_RESAMPLES)
+            .map(|_| {
+                let mut weighted_sum: T = Zero::zero();
+                let mut weight_sum: T = Zero::zero();
+                for _ in 0..num_trees {
+                    let i = rng.gen_range(0..num_trees);
+                    weighted_sum = weighted_sum + weights[i] * tree_scores[i];
+                    weight_sum = weight_sum + weights[i];
+                }
+                match weight_sum > Zero::zero() {
+                    true => self.finalize_score(weighted_sum / weight_sum),
+                    false => Zero::zero(),
+                }
+            })
+            .collect();
+        resampled.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+
+        let tail = (1.0 - level) / 2.0;
+        let last = (BOOTSTRAP_RESAMPLES - 1) as f64;
+        let lower = resampled[(tail * last).floor() as usize];
+        let upper = resampled[((1.0 - tail) * last).ceil() as usize];
+        let score = self.combine_scores(tree_scores.into_iter());
+        (score, Float::min(lower, score), Float::max(upper, score))
+    }
+
+    /// Returns the distribution of the anomaly scores of the input point
+    /// across the trees: mean, standard deviation, extremes and the given
+    /// quantiles, in `[0, 1]`, linearly interpolated between tree scores.
+    ///
+    /// The spread of the tree scores is a measure of confidence in the
+    /// forest score that needs no resampling, unlike
+    /// [`score_with_confidence`](Self::score_with_confidence). The
+    /// statistics are those of the raw [`tree_scores`](Self::tree_scores):
+    /// the mean is the anomaly score before weighted voting, score
+    /// transformation and the upper bound on scores. Returns `None` if there
+    /// is not yet enough data or if the point is skipped under the forest's
+    /// [`NonFinitePolicy`].
+    ///
+    /// # Panics
+    ///
+    /// If the dimensionality of the point does not match the dimensionality
+    /// of the forest, or if a quantile is not in `[0, 1]`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use random_cut_forest::{RandomCutForest, RandomCutForestBuilder};
+    ///
+    /// let mut forest: RandomCutForest<f64> = RandomCutForestBuilder::new(2)
+    ///     .num_trees(30)
+    ///     .sample_size(64)
+    ///     .seed(4)
+    ///     .build();
+    /// for i in 0..1000 {
+    ///     forest.update(vec![(i % 10) as f64, (i % 7) as f64]);
+    /// }
+    ///
+    /// let point = vec![20.0, 20.0];
+    /// let distribution = forest.score_with_distribution(&point, &[0.05, 0.5, 0.95]).unwrap();
+    /// assert!((distribution.mean - forest.anomaly_score(&point)).abs() < 1e-9);
+    /// assert!(distribution.min <= distribution.quantiles[0]);
+    /// assert!(distribution.quantiles[0] <= distribution.quantiles[1]);
+    /// assert!(distribution.quantiles[2] <= distribution.max);
+    /// assert!(distribution.std_dev > 0.0);
+    /// ```
+    ///
+    /// [`NonFinitePolicy`]: crate::NonFinitePolicy
+    pub fn score_with_distribution(&self, point: &[T], quantiles: &[f64]) -> Option<ScoreDistribution<T>> {
+        assert!(quantiles.iter().all(|q| (0.0..=1.0).contains(q)), "Quantiles must be in [0, 1]");
+        let mut tree_scores: Vec<T> = self.tree_scores(point).into_iter()
+            .map(|(_, score)| score)
+            .collect();
+        if tree_scores.is_empty() {
+            return None;
+        }
+        tree_scores.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+
+        let num_trees = T::from(tree_scores.len()).unwrap();
+        let mean = tree_scores.iter().cloned().sum::<T>() / num_trees;
+        let variance = tree_scores.iter().map(|&score| (score - mean) * (score - mean)).sum::<T>() / num_trees;
+        let last = (tree_scores.len() - 1) as f64;
+        let quantiles = quantiles.iter()
+            .map(|q| {
+                let position = q * last;
+                let (lower, upper) = (position.floor() as usize, position.ceil() as usize);
+                let fraction = T::from(position - lower as f64).unwrap();
+                tree_scores[lower] + fraction * (tree_scores[upper] - tree_scores[lower])
+            })
+            .collect();
+        Some(ScoreDistribution {
+            mean,
+            std_dev: variance.sqrt(),
+            min: tree_scores[0],
+            max: tree_scores[tree_scores.len() - 1],
+            quantiles,
+        })
+    }
+
+    /// Returns the anomaly score of the input point for each memory horizon
+    /// of the forest, see [`RandomCutForestBuilder::tree_time_decays`].
+    ///
+    /// Trees are grouped by time decay and each group reports the mean score
+    /// of its trees, with the score transformation and upper bound applied
+    /// as for [`anomaly_score`](Self::anomaly_score). Groups are sorted by
+    /// increasing time decay, that is from the longest memory to the
+    /// shortest. A forest with a single time decay has a single group, whose
+    /// score is the anomaly score of the forest without weighted voting. The
+    /// result is empty if there is not yet enough data or if the point is
+    /// skipped under the forest's [`NonFinitePolicy`].
+    ///
+    /// # Panics
+    ///
+    /// If the dimensionality of the point does not match the dimensionality
+    /// of the forest.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use random_cut_forest::{RandomCutForest, RandomCutForestBuilder};
+    ///
+    /// let mut time_decays = vec![0.0; 10];
+    /// time_decays.extend(vec![0.05; 10]);
+    /// let mut forest: RandomCutForest<f32> = RandomCutForestBuilder::new(1)
+    ///     .tree_time_decays(time_decays)
+    ///     .sample_size(32)
+    ///     .seed(3)
+    ///     .build();
+    ///
+    /// // a level shift that the short horizon has already absorbed
+    /// for i in 0..2000 {
+    ///     forest.update(vec![(i % 5) as f32]);
+    /// }
+    /// for i in 0..300 {
+    ///     forest.update(vec![100.0 + (i % 5) as f32]);
+    /// }
+    ///
+    /// let horizons = forest.horizon_scores(&[102.0]);
+    /// assert_eq!(horizons.len(), 2);
+    /// let (long_decay, long_score) = horizons[0];
+    /// let (short_decay, short_score) = horizons[1];
+    /// assert!(long_decay < short_decay);
+    /// assert!(long_score > short_score);
+    /// ```
+    ///
+    /// [`RandomCutForestBuilder::tree_time_decays`]: crate::RandomCutForestBuilder::tree_time_decays
+    /// [`NonFinitePolicy`]: crate::NonFinitePolicy
+    pub fn horizon_scores(&self, point: &[T]) -> Vec<(f32, T)> {
+        let tree_scores = self.tree_scores(point);
+        let mut horizons: Vec<(f32, T, usize)> = Vec::new();
+        for (tree, (_, score)) in self.trees.iter().zip(tree_scores) {
+            match horizons.iter_mut().find(|(decay, _, _)| *decay == tree.time_decay()) {
+                Some((_, sum, count)) => {
+                    *sum = *sum + score;
+                    *count += 1;
+                },
+                None => horizons.push((tree.time_decay(), score, 1)),
+            }
+        }
+
+        horizons.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+        horizons.into_iter()
+            .map(|(decay, sum, count)| (decay, self.finalize_score(sum / T::from(count).unwrap())))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::random_cut_forest::RandomCutForestBuilder;
+
+    #[test]
+    fn score_with_distribution() {
+        let mut forest: RandomCutForest<f64> = RandomCutForestBuilder::new(1)
+            .num_trees(5)
+            .sample_size(16)
+            .output_after(10)
+            .seed(8)
+            .build();
+        for i in 0..10 {
+            forest.update(vec![i as f64]);
+        }
+        assert_eq!(forest.score_with_distribution(&[3.0], &[0.5]), None);
+
+        for i in 10..200 {
+            forest.update(vec![(i % 10) as f64]);
+        }
+        let mut scores: Vec<f64> = forest.tree_scores(&[3.5]).into_iter().map(|(_, score)| score).collect();
+        scores.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let distribution = forest.score_with_distribution(&[3.5], &[0.0, 0.5, 0.625, 1.0]).unwrap();
+        assert_eq!(distribution.min, scores[0]);
+        assert_eq!(distribution.max, scores[4]);
+        assert_eq!(distribution.quantiles[0], scores[0]);
+        assert_eq!(distribution.quantiles[1], scores[2]);
+        assert!((distribution.quantiles[2] - (scores[2] + scores[3]) / 2.0).abs() < 1e-12);
+        assert_eq!(distribution.quantiles[3], scores[4]);
+
+        let mean = scores.iter().sum::<f64>() / 5.0;
+        let variance = scores.iter().map(|score| (score - mean).powi(2)).sum::<f64>() / 5.0;
+        assert!((distribution.mean - mean).abs() < 1e-12);
+        assert!((distribution.std_dev - variance.sqrt()).abs() < 1e-12);
+    }
+}
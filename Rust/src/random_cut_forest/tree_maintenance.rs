@@ -0,0 +1,397 @@
+extern crate num_traits;
+use num_traits::{Float, Zero};
+
+extern crate rand;
+use rand::{Rng, RngCore, SeedableRng};
+
+extern crate rand_chacha;
+use rand_chacha::ChaCha8Rng;
+
+use std::iter::Sum;
+use std::ops::Range;
+
+use crate::SampledTree;
+use crate::common::Covariance;
+use crate::random_cut_forest::RandomCutForest;
+
+impl<T> RandomCutForest<T>
+    where T: Float + Sum + Zero
+{
+
+    /// Replace the tree at position `index` by a tree bootstrapped from the
+    /// samples of the other trees.
+    ///
+    /// The new tree keeps the identifier, sample size and time decay of the
+    /// tree it replaces. It is filled by offering it, in stream order, the
+    /// points retained by the other trees, and it counts the updates
+    /// delivered to the trees of the forest as seen. A forest of a single
+    /// tree has no other samples to bootstrap from, so it cannot resync its
+    /// tree.
+    ///
+    /// # Panics
+    ///
+    /// If `index` is not smaller than the number of trees, or if the forest
+    /// has a single tree.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use random_cut_forest::{RandomCutForest, RandomCutForestBuilder};
+    ///
+    /// let mut forest: RandomCutForest<f32> = RandomCutForestBuilder::new(2)
+    ///     .num_trees(10)
+    ///     .sample_size(32)
+    ///     .seed(4)
+    ///     .build();
+    /// for i in 0..500 {
+    ///     forest.update(vec![(i % 10) as f32, (i % 7) as f32]);
+    /// }
+    /// assert!(forest.tree_divergence().iter().all(|tree| !tree.is_diverged()));
+    ///
+    /// forest.resync_tree(3);
+    /// let divergence = &forest.tree_divergence()[3];
+    /// assert_eq!(divergence.id, 3);
+    /// assert_eq!(divergence.entries_seen, 500);
+    /// assert!(!divergence.is_diverged());
+    /// assert_eq!(forest.trees()[3].tree().mass(), 32);
+    /// ```
+    pub fn resync_tree(&mut self, index: usize) {
+        assert!(index < self.trees.len(), "Tree index {} out of range", index);
+        assert!(self.trees.len() > 1, "Cannot resync the only tree of a forest");
+        let old_tree = &self.trees[index];
+        let (id, sample_size, time_decay) = (old_tree.id(), old_tree.sample_size(), old_tree.time_decay());
+
+        let mut tree = self.new_tree(id, sample_size, time_decay);
+        let points = self.pooled_points(Some(index));
+        tree.bootstrap(&points, self.num_delivered_updates);
+        self.trees[index] = tree;
+    }
+
+    /// Rebuild the tree at position `index` from the points in its own
+    /// sample and return the number of points in the rebuilt tree.
+    ///
+    /// The nodes of the tree are discarded and new ones are built from the
+    /// sampled points still in the tree's point store, so that a corrupted
+    /// tree can be repaired without discarding the rest of the forest. Unlike
+    /// [`resync_tree`](Self::resync_tree), the tree keeps its sample and
+    /// sampler state, only its random cuts change.
+    ///
+    /// # Panics
+    ///
+    /// If `index` is not smaller than the number of trees.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use random_cut_forest::{RandomCutForest, RandomCutForestBuilder};
+    ///
+    /// let mut forest: RandomCutForest<f32> = RandomCutForestBuilder::new(2)
+    ///     .num_trees(10)
+    ///     .sample_size(32)
+    ///     .seed(4)
+    ///     .build();
+    /// for i in 0..500 {
+    ///     forest.update(vec![(i % 10) as f32, (i % 7) as f32]);
+    /// }
+    ///
+    /// assert_eq!(forest.rebuild_tree(3), 32);
+    /// assert_eq!(forest.trees()[3].tree().mass(), 32);
+    /// assert!(!forest.tree_divergence()[3].is_diverged());
+    /// ```
+    pub fn rebuild_tree(&mut self, index: usize) -> usize {
+        assert!(index < self.trees.len(), "Tree index {} out of range", index);
+        self.trees[index].rebuild()
+    }
+
+    /// Remove every retained point whose observation number lies in the
+    /// given range from the trees and from the raw input history, and return
+    /// the number of samples removed across trees.
+    ///
+    /// Observation numbers start at one with the first point passed to
+    /// [`update`](Self::update), as reported by
+    /// [`raw_inputs`](Self::raw_inputs). Forgetting a range supports
+    /// deletion requests and the removal of known-bad segments from a
+    /// trained forest. Trees hold fewer points until new updates refill
+    /// their samples. Aggregates that do not keep points, such as the
+    /// tracked covariance and the observation count, are not changed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use random_cut_forest::{RandomCutForest, RandomCutForestBuilder};
+    ///
+    /// let mut forest: RandomCutForest<f32> = RandomCutForestBuilder::new(1)
+    ///     .num_trees(10)
+    ///     .sample_size(64)
+    ///     .seed(5)
+    ///     .build();
+    /// for i in 1..=200 {
+    ///     let value = if (101..=110).contains(&i) { 1000.0 } else { (i % 10) as f32 };
+    ///     forest.update(vec![value]);
+    /// }
+    /// let before = forest.anomaly_score(&vec![1000.0]);
+    ///
+    /// // excise the bad segment
+    /// assert!(forest.forget_points(101..111) > 0);
+    /// assert!(forest.anomaly_score(&vec![1000.0]) > before);
+    /// assert!(forest.heavy_hitters(64).iter().all(|(point, _)| point[0] < 1000.0));
+    /// ```
+    pub fn forget_points(&mut self, sequence_range: Range<usize>) -> usize {
+        self.raw_inputs.retain(|(index, _)| !sequence_range.contains(index));
+        self.trees.iter_mut()
+            .map(|tree| tree.forget_range(sequence_range.clone()))
+            .sum()
+    }
+
+    /// Add `num_trees` trees to the forest, bootstrapped from the samples of
+    /// the existing trees.
+    ///
+    /// More trees give more stable scores at the cost of memory and latency.
+    /// Each new tree uses the sample size and time decay of the forest and is
+    /// filled as in [`resync_tree`](Self::resync_tree), so it scores like the
+    /// other trees right away. New trees get fresh identifiers; identifiers
+    /// of removed trees are not reused. If tree reliability is tracked, the
+    /// new trees start without history.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use random_cut_forest::{RandomCutForest, RandomCutForestBuilder};
+    ///
+    /// let mut forest: RandomCutForest<f32> = RandomCutForestBuilder::new(2)
+    ///     .num_trees(10)
+    ///     .sample_size(32)
+    ///     .seed(8)
+    ///     .build();
+    /// for i in 0..500 {
+    ///     forest.update(vec![(i % 10) as f32, (i % 7) as f32]);
+    /// }
+    ///
+    /// forest.remove_trees(4);
+    /// forest.add_trees(2);
+    /// assert_eq!(forest.num_trees(), 8);
+    /// let ids: Vec<usize> = forest.trees().iter().map(|tree| tree.id()).collect();
+    /// assert_eq!(ids, vec![0, 1, 2, 3, 4, 5, 10, 11]);
+    /// assert!(forest.anomaly_score(&vec![40.0, 40.0]) > 1.0);
+    /// ```
+    pub fn add_trees(&mut self, num_trees: usize) {
+        let points = self.pooled_points(None);
+        for _ in 0..num_trees {
+            let id = self.next_tree_id;
+            self.next_tree_id += 1;
+            let mut tree = self.new_tree(id, self.sample_size, self.time_decay);
+            tree.bootstrap(&points, self.num_delivered_updates);
+            self.trees.push(tree);
+            if let Some(reliability) = self.tree_reliability.as_mut() {
+                let decay = reliability[0].decay();
+                reliability.push(Covariance::new(2, decay));
+            }
+        }
+    }
+
+    /// Remove the `num_trees` most recently added trees from the forest,
+    /// releasing their samples.
+    ///
+    /// # Panics
+    ///
+    /// If the forest would be left without trees.
+    pub fn remove_trees(&mut self, num_trees: usize) {
+        assert!(num_trees < self.trees.len(), "A forest must keep at least one tree");
+        let num_kept = self.trees.len() - num_trees;
+        self.trees.truncate(num_kept);
+        if let Some(reliability) = self.tree_reliability.as_mut() {
+            reliability.truncate(num_kept);
+        }
+    }
+
+    /// Returns a deep copy of the forest with random number generators
+    /// seeded from `new_seed`.
+    ///
+    /// The copy has the same trees, samples, statistics and configuration as
+    /// this forest, but its own random streams, derived from `new_seed` as
+    /// for a forest built with [`RandomCutForestBuilder::seed`]. Updating or
+    /// reconfiguring the copy, for example with a different time decay,
+    /// leaves this forest untouched, which allows what-if experiments next
+    /// to a production model.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use random_cut_forest::{RandomCutForest, RandomCutForestBuilder};
+    ///
+    /// let builder = || RandomCutForestBuilder::new(1).num_trees(10).sample_size(64).seed(8);
+    /// let mut forest: RandomCutForest<f32> = builder().build();
+    /// let mut twin: RandomCutForest<f32> = builder().build();
+    /// for i in 0..500 {
+    ///     forest.update(vec![(i % 10) as f32]);
+    ///     twin.update(vec![(i % 10) as f32]);
+    /// }
+    ///
+    /// let mut experiment = forest.fork(99);
+    /// assert_eq!(experiment.anomaly_score(&vec![5.0]), forest.anomaly_score(&vec![5.0]));
+    /// experiment.set_time_decay(0.1);
+    /// for _ in 0..500 {
+    ///     experiment.update(vec![100.0]);
+    /// }
+    /// assert!(experiment.anomaly_score(&vec![100.0]) < forest.anomaly_score(&vec![100.0]));
+    ///
+    /// // the original forest is not disturbed by the experiment
+    /// for i in 0..100 {
+    ///     forest.update(vec![(i % 10) as f32]);
+    ///     twin.update(vec![(i % 10) as f32]);
+    /// }
+    /// assert_eq!(forest.anomaly_score(&vec![100.0]), twin.anomaly_score(&vec![100.0]));
+    /// ```
+    ///
+    /// [`RandomCutForestBuilder::seed`]: crate::RandomCutForestBuilder::seed
+    pub fn fork(&self, new_seed: u64) -> RandomCutForest<T> {
+        let mut seeds = ChaCha8Rng::seed_from_u64(new_seed);
+        let mut new_rng = || -> Box<dyn RngCore> { Box::new(ChaCha8Rng::seed_from_u64(seeds.gen())) };
+        let trees = self.trees.iter()
+            .map(|tree| {
+                let mut tree = tree.fork();
+                tree.set_rng(new_rng(), new_rng());
+                tree
+            })
+            .collect();
+
+        RandomCutForest {
+            dimension: self.dimension,
+            num_observations: self.num_observations,
+            sample_size: self.sample_size,
+            time_decay: self.time_decay,
+            trees,
+            output_after: self.output_after,
+            noise_scale: self.noise_scale,
+            max_score: self.max_score,
+            score_transform: self.score_transform,
+            score_function: self.score_function.clone(),
+            cut_strategy: self.cut_strategy,
+            tie_breaking: self.tie_breaking,
+            non_finite_policy: self.non_finite_policy,
+            num_rejected_points: self.num_rejected_points,
+            bounds: self.bounds.clone(),
+            clamp_to_bounds: self.clamp_to_bounds,
+            num_out_of_bounds_points: self.num_out_of_bounds_points,
+            covariance: self.covariance.clone(),
+            input_scaling: self.input_scaling.clone(),
+            input_transforms: self.input_transforms.clone(),
+            raw_input_history: self.raw_input_history,
+            raw_inputs: self.raw_inputs.clone(),
+            masked_dimensions: self.masked_dimensions.clone(),
+            tree_reliability: self.tree_reliability.clone(),
+            weighted_voting: self.weighted_voting,
+            latency: self.latency.clone(),
+            pre_filter: self.pre_filter.clone(),
+            governor: self.governor.clone(),
+            num_delivered_updates: self.num_delivered_updates,
+            next_tree_id: self.next_tree_id,
+            seed: Some(new_seed),
+            rng: new_rng(),
+        }
+    }
+
+    /// Create an empty tree with the configuration of the forest and random
+    /// number generators drawn from the forest's generator.
+    fn new_tree(&mut self, id: usize, sample_size: usize, time_decay: f32) -> SampledTree<T> {
+        let mut tree = SampledTree::new(sample_size, time_decay);
+        tree.set_rng(
+            Box::new(ChaCha8Rng::seed_from_u64(self.rng.gen())),
+            Box::new(ChaCha8Rng::seed_from_u64(self.rng.gen())));
+        tree.set_id(id);
+        tree.set_cut_strategy(self.cut_strategy);
+        tree.set_tie_breaking(self.tie_breaking);
+        tree.set_masked_dimensions(self.masked_dimensions.clone());
+        tree
+    }
+
+    /// Returns the points retained by the trees, except the tree at position
+    /// `exclude` if any, once per sequence index and sorted by it.
+    pub(super) fn pooled_points(&self, exclude: Option<usize>) -> Vec<(usize, Vec<T>)> {
+        let mut points: Vec<(usize, Vec<T>)> = self.trees.iter()
+            .enumerate()
+            .filter(|(i, _)| Some(*i) != exclude)
+            .flat_map(|(_, tree)| tree.retained_points())
+            .collect();
+        points.sort_by_key(|(sequence_index, _)| *sequence_index);
+        points.dedup_by_key(|(sequence_index, _)| *sequence_index);
+        points
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::random_cut_forest::RandomCutForestBuilder;
+
+    #[test]
+    fn add_and_remove_trees() {
+        let mut forest: RandomCutForest<f64> = RandomCutForestBuilder::new(2)
+            .num_trees(6)
+            .sample_size(32)
+            .tree_reliability(0.05)
+            .weighted_voting(true)
+            .seed(12)
+            .build();
+        let mut rng = ChaCha8Rng::seed_from_u64(1);
+        for _ in 0..300 {
+            forest.score_and_update(vec![rng.gen::<f64>(), rng.gen::<f64>()]).unwrap();
+        }
+
+        forest.add_trees(3);
+        assert_eq!(forest.num_trees(), 9);
+        assert_eq!(forest.tree_reliability().unwrap().len(), 9);
+        for tree in forest.trees().iter().skip(6) {
+            assert_eq!(tree.tree().mass(), 32);
+            assert_eq!(tree.num_observations(), 300);
+        }
+        assert!(forest.tree_divergence().iter().all(|tree| !tree.is_diverged()));
+
+        forest.remove_trees(5);
+        let ids: Vec<usize> = forest.tree_reliability().unwrap().iter().map(|(id, _)| *id).collect();
+        assert_eq!(ids, vec![0, 1, 2, 3]);
+        for _ in 0..10 {
+            forest.score_and_update(vec![rng.gen::<f64>(), rng.gen::<f64>()]).unwrap();
+        }
+        assert_eq!(forest.parameters().num_trees, 4);
+    }
+
+    #[test]
+    fn forget_all_points() {
+        for &time_decay in [0.0, 0.01].iter() {
+            let mut forest: RandomCutForest<f32> = RandomCutForestBuilder::new(2)
+                .num_trees(3)
+                .sample_size(32)
+                .time_decay(time_decay)
+                .seed(13)
+                .build();
+            let mut rng = ChaCha8Rng::seed_from_u64(13);
+            for _ in 0..100 {
+                forest.update(vec![rng.gen::<f32>(), rng.gen::<f32>()]);
+            }
+            assert_eq!(forest.forget_points(0..1000), 3 * 32);
+            assert!(forest.trees().iter().all(|tree| tree.tree().mass() == 0));
+
+            // new updates refill the samples
+            for _ in 0..1000 {
+                forest.update(vec![rng.gen::<f32>(), rng.gen::<f32>()]);
+            }
+            assert!(forest.trees().iter().all(|tree| tree.tree().mass() == 32));
+            assert!(forest.anomaly_score(&vec![5.0, 5.0]) > forest.anomaly_score(&vec![0.5, 0.5]));
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot resync the only tree of a forest")]
+    fn resync_single_tree() {
+        let mut forest: RandomCutForest<f32> = RandomCutForestBuilder::new(1)
+            .num_trees(1)
+            .seed(2)
+            .build();
+        for i in 0..100 {
+            forest.update(vec![i as f32]);
+        }
+        forest.resync_tree(0);
+    }
+}
@@ -1,13 +1,18 @@
 extern crate num_traits;
 use num_traits::Float;
 
+extern crate rand;
+use rand::RngCore;
+
 use std::cell::{Ref, RefCell, RefMut};
+use std::collections::{HashMap, HashSet};
 use std::iter::Sum;
+use std::ops::Range;
 use std::rc::Rc;
 
 use crate::{PointStore, SamplerResult, StreamSampler};
 use crate::visitor::Visitor;
-use crate::tree::{AddResult, NodeIterator, Tree};
+use crate::tree::{AddResult, CutStrategy, InvariantViolation, NodeIterator, TieBreaking, Tree};
 
 /// Combination of a tree and a reservoir sampler.
 ///
@@ -45,6 +50,7 @@ pub struct SampledTree<T> {
     point_store: Rc<RefCell<PointStore<T>>>,
     tree: Tree<T>,
     sampler: StreamSampler<usize>,
+    id: usize,
 }
 
 impl<T> SampledTree<T>
@@ -95,6 +101,7 @@ impl<T> SampledTree<T>
             point_store: point_store.clone(),
             tree: Tree::new_with_point_store(point_store.clone()),
             sampler: StreamSampler::new(sample_size, time_decay),
+            id: 0,
         }
     }
 
@@ -108,6 +115,69 @@ impl<T> SampledTree<T>
         self.sampler.seed(seed);
     }
 
+    /// Replaces the random number generators of the tree and the sampler.
+    ///
+    /// See [`Tree::set_rng`] and [`StreamSampler::set_rng`].
+    pub fn set_rng(&mut self, tree_rng: Box<dyn RngCore>, sampler_rng: Box<dyn RngCore>) {
+        self.tree.set_rng(tree_rng);
+        self.sampler.set_rng(sampler_rng);
+    }
+
+    /// Returns a copy of the sampled tree with its own copy of the point
+    /// store.
+    ///
+    /// The random number generators of the copy are seeded from system
+    /// entropy, see [`Tree::fork`] and [`StreamSampler::fork`]. Updates of
+    /// the copy do not affect this tree.
+    pub fn fork(&self) -> Self {
+        let point_store = Rc::new(RefCell::new(self.point_store.borrow().clone()));
+        SampledTree {
+            tree: self.tree.fork(point_store.clone()),
+            point_store,
+            sampler: self.sampler.fork(),
+            id: self.id,
+        }
+    }
+
+    /// Sets the identifier of the sampled tree.
+    ///
+    /// The identifier is not used by the tree itself. A forest assigns each
+    /// of its trees a distinct identifier that does not change when other
+    /// trees are added or removed, so that per-tree results can be traced to
+    /// a specific tree.
+    pub fn set_id(&mut self, id: usize) {
+        self.id = id;
+    }
+
+    /// Returns the identifier of the sampled tree. See
+    /// [`set_id`](Self::set_id).
+    pub fn id(&self) -> usize { self.id }
+
+    /// Sets the strategy used to choose the random cuts of new nodes.
+    ///
+    /// See [`Tree::set_cut_strategy`].
+    ///
+    /// # Panics
+    ///
+    /// If the strategy is `DimensionSubsample(0)`.
+    pub fn set_cut_strategy(&mut self, cut_strategy: CutStrategy) {
+        self.tree.set_cut_strategy(cut_strategy);
+    }
+
+    /// Sets the tie breaking policy of the cuts of new nodes.
+    ///
+    /// See [`Tree::set_tie_breaking`].
+    pub fn set_tie_breaking(&mut self, tie_breaking: TieBreaking) {
+        self.tree.set_tie_breaking(tie_breaking);
+    }
+
+    /// Sets the dimensions excluded from the cuts of new nodes.
+    ///
+    /// See [`Tree::set_masked_dimensions`].
+    pub fn set_masked_dimensions(&mut self, masked_dimensions: Vec<bool>) {
+        self.tree.set_masked_dimensions(masked_dimensions);
+    }
+
     /// Update the sampled tree with a new point.
     ///
     /// The stream sampler decides if the new point will be accepted into the
@@ -125,6 +195,18 @@ impl<T> SampledTree<T>
     /// assert_eq!(tree.num_observations(), 2);
     /// ```
     pub fn update(&mut self, point: Vec<T>, sequence_index: usize) {
+        self.update_weighted(point, sequence_index, 1.0)
+    }
+
+    /// Update the sampled tree with a point that stands for `multiplicity`
+    /// points with the same sequence index, as when updates are subsampled.
+    ///
+    /// See [`StreamSampler::sample_weighted`].
+    ///
+    /// # Panics
+    ///
+    /// If `multiplicity` is not positive and finite.
+    pub fn update_weighted(&mut self, point: Vec<T>, sequence_index: usize, multiplicity: f32) {
         // we need a point key that we can submit to the sampler. the strategy,
         // then, is to first add the point to the tree and then sample using
         // the output key. if the key is accepted by the sampler then we
@@ -138,7 +220,7 @@ impl<T> SampledTree<T>
             AddResult::MassIncreased(key) => key,
         };
 
-        match self.sampler.sample(point_key, sequence_index) {
+        match self.sampler.sample_weighted(point_key, sequence_index, multiplicity) {
             SamplerResult::Accepted(evicted) => match evicted {
                 Some(evicted) => {
                     // TODO: can we satisfy the borrow checker so that we can
@@ -222,6 +304,11 @@ impl<T> SampledTree<T>
     /// ```
     pub fn time_decay(&self) -> f32 { self.sampler.time_decay() }
 
+    /// Change the time decay factor of the random sampler.
+    ///
+    /// See [`StreamSampler::set_time_decay`] for more information.
+    pub fn set_time_decay(&mut self, time_decay: f32) { self.sampler.set_time_decay(time_decay) }
+
     /// Returns the total number of observations made by the tree.
     ///
     /// For every point sent to [`SampledTree::update`], the total number of
@@ -247,6 +334,174 @@ impl<T> SampledTree<T>
     /// ```
     pub fn num_observations(&self) -> usize { self.sampler.num_observations() }
 
+    /// Returns the mean age of the points in the tree's sample at the given
+    /// sequence index, or `None` if the sample is empty.
+    ///
+    /// See [`StreamSampler::mean_age`] for more information.
+    pub fn mean_sample_age(&self, sequence_index: usize) -> Option<f64> {
+        self.sampler.mean_age(sequence_index)
+    }
+
+    /// Returns the number of points accepted into the tree's sample so far,
+    /// including those evicted since.
+    pub fn num_accepted(&self) -> usize { self.sampler.num_accepted() }
+
+    /// Returns the number of points in the tree's sample.
+    pub fn num_samples(&self) -> usize { self.sampler.size() }
+
+    /// Returns the points in the tree's sample with the sequence indexes at
+    /// which they were observed, in arbitrary order.
+    ///
+    /// A point retained several times is returned once per sequence index.
+    pub fn retained_points(&self) -> Vec<(usize, Vec<T>)> {
+        let point_store = self.point_store.borrow();
+        self.sampler.iter()
+            .map(|sample| (sample.sequence_index(), point_store.get(*sample.value()).unwrap().clone()))
+            .collect()
+    }
+
+    /// Remove the points observed at a sequence index in the given range from
+    /// the tree's sample and from the tree, and return the number of points
+    /// removed.
+    pub fn forget_range(&mut self, sequence_range: Range<usize>) -> usize {
+        let removed = self.sampler.remove_range(sequence_range);
+        for sample in removed.iter() {
+            let point = self.point_store.borrow().get(*sample.value()).unwrap().clone();
+            self.tree.delete_point(&point);
+        }
+        removed.len()
+    }
+
+    /// Discard the nodes of the tree and rebuild it from the points in its
+    /// sample, and return the number of points in the rebuilt tree.
+    ///
+    /// The sampler, with its weights and sequence indexes, is kept. Sampled
+    /// points missing from the point store are dropped from the sample. The
+    /// rebuilt tree draws new random cuts, so it is a different tree on the
+    /// same sample. Used to repair a tree whose nodes are inconsistent, for
+    /// example after memory corruption, without traversing them.
+    pub fn rebuild(&mut self) -> usize {
+        let points: HashMap<usize, Vec<T>> = {
+            let mut point_store = self.point_store.borrow_mut();
+            let keys: HashSet<usize> = self.sampler.iter().map(|sample| *sample.value()).collect();
+            keys.into_iter()
+                .filter_map(|key| point_store.try_remove(key).map(|point| (key, point)))
+                .collect()
+        };
+
+        let tree = &mut self.tree;
+        tree.clear();
+        self.sampler.retain_map(|key| points.get(key).map(|point| {
+            match tree.add_point(point.clone()) {
+                AddResult::AddedPoint(key) => key,
+                AddResult::MassIncreased(key) => key,
+            }
+        }));
+        self.sampler.size()
+    }
+
+    /// Fill the tree from the samples of other trees of the same stream.
+    ///
+    /// The points, sorted by sequence index, are offered to the tree as
+    /// updates, so its sampler keeps a sample of them according to its own
+    /// size and time decay. The number of observations is then set to
+    /// `num_observations`, the length of the stream the points come from.
+    pub fn bootstrap(&mut self, points: &[(usize, Vec<T>)], num_observations: usize) {
+        for (sequence_index, point) in points.iter() {
+            self.update(point.clone(), *sequence_index);
+        }
+        self.sampler.set_num_observations(num_observations);
+    }
+
+    /// Fill an empty tree from the points of a stream in one pass.
+    ///
+    /// The points, with the sequence indexes at which they were observed in
+    /// increasing order, are offered to the sampler, which keeps the same
+    /// sample as a sequence of [`update`](Self::update) calls would. The
+    /// tree is then built from the sample with [`Tree::bulk_load`], so that
+    /// no point is inserted into or deleted from the tree along the way.
+    ///
+    /// # Panics
+    ///
+    /// If the tree has already been updated.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use random_cut_forest::SampledTree;
+    ///
+    /// let points: Vec<(usize, Vec<f32>)> = (1..=1000)
+    ///     .map(|i| (i, vec![(i % 10) as f32, (i % 7) as f32]))
+    ///     .collect();
+    ///
+    /// let mut tree: SampledTree<f32> = SampledTree::new(64, 0.01);
+    /// tree.bulk_load(&points);
+    /// assert_eq!(tree.num_observations(), 1000);
+    /// assert_eq!(tree.tree().mass(), 64);
+    /// ```
+    pub fn bulk_load(&mut self, points: &[(usize, Vec<T>)]) {
+        assert_eq!(self.num_observations(), 0, "Bulk loading requires an empty tree");
+
+        // sample the positions of the points, then replace them by their
+        // keys in the point store once the tree is built
+        for (position, (sequence_index, _)) in points.iter().enumerate() {
+            self.sampler.sample(position, *sequence_index);
+        }
+        let positions: Vec<usize> = {
+            let mut positions: Vec<usize> = self.sampler.iter().map(|sample| *sample.value()).collect();
+            positions.sort_unstable();
+            positions
+        };
+        let keys = self.tree.bulk_load(positions.iter().map(|&position| points[position].1.clone()).collect());
+        let point_keys: HashMap<usize, usize> = positions.into_iter().zip(keys).collect();
+        self.sampler.retain_map(|position| point_keys.get(position).copied());
+    }
+
+    /// Check the structure of the tree and its consistency with the sample,
+    /// and return the invariants that are broken.
+    ///
+    /// See [`Tree::validate`] for the checks on the tree. If the tree is
+    /// valid, the mass of each point in the tree must also be the number of
+    /// times the point is in the sample.
+    pub fn validate(&self) -> Vec<InvariantViolation> {
+        let mut violations = self.tree.validate();
+        if !violations.is_empty() {
+            return violations;
+        }
+
+        let mut num_samples: HashMap<usize, usize> = HashMap::new();
+        for sample in self.sampler.iter() {
+            *num_samples.entry(*sample.value()).or_insert(0) += 1;
+        }
+        let point_store = self.point_store.borrow();
+        let mut points: Vec<(usize, usize)> = num_samples.into_iter().collect();
+        points.sort_unstable();
+        for (point_key, num_samples) in points {
+            let mass = point_store.get(point_key).map_or(0, |point| self.tree.point_mass(point));
+            if mass as usize != num_samples {
+                violations.push(InvariantViolation::SampleMassMismatch { point: point_key, num_samples, mass });
+            }
+        }
+        if self.tree.mass() as usize != self.sampler.size() {
+            violations.push(InvariantViolation::SampleSizeMismatch {
+                num_samples: self.sampler.size(),
+                mass: self.tree.mass(),
+            });
+        }
+        violations
+    }
+
+    /// Returns the mass of the given point in the tree's sample.
+    ///
+    /// See [`Tree::point_mass`] for more information.
+    pub fn point_mass(&self, point: &Vec<T>) -> u32 { self.tree.point_mass(point) }
+
+    /// Returns the number of bytes allocated on the heap by the tree and its
+    /// sampler.
+    ///
+    /// See [`Tree::heap_size`] for what is counted.
+    pub fn heap_size(&self) -> usize { self.tree.heap_size() + self.sampler.heap_size() }
+
     /// Returns a reference to the tree in the sampled tree.
     pub fn tree(&self) -> &Tree<T> { &self.tree }
 
@@ -273,4 +528,56 @@ mod tests {
         // additional points that cause evictions
         tree.update(vec![0.0, 1.0], 100);
     }
+
+    #[test]
+    fn test_duplicate_heavy_stream() {
+        let sample_size = 16;
+        let mut tree: SampledTree<f32> = SampledTree::new(sample_size, 0.01);
+        tree.seed(42);
+
+        // a stream that cycles through a handful of distinct values
+        let values = [vec![0.0, 0.0], vec![1.0, 0.0], vec![0.0, 1.0]];
+        for i in 0..5000 {
+            tree.update(values[i % values.len()].clone(), i);
+        }
+
+        // the leaf masses must account for the full sample
+        let total_mass: u32 = values.iter().map(|v| tree.point_mass(v)).sum();
+        assert_eq!(total_mass, sample_size as u32);
+        assert_eq!(tree.tree().mass(), sample_size as u32);
+        assert!(tree.borrow_point_store().len() <= values.len());
+    }
+
+    #[test]
+    fn test_rebuild() {
+        let sample_size = 32;
+        let mut tree: SampledTree<f32> = SampledTree::new(sample_size, 0.01);
+        tree.seed(7);
+        for i in 0..1000 {
+            tree.update(vec![(i % 10) as f32, (i % 13) as f32], i);
+        }
+        let mut points = tree.retained_points();
+
+        // corrupt the nodes of the tree
+        let root = tree.tree.root_node().unwrap();
+        tree.tree.node_store_mut().remove(root);
+        tree.tree.set_root_node(None);
+        assert!(!tree.validate().is_empty());
+
+        assert_eq!(tree.rebuild(), sample_size);
+        assert!(tree.validate().is_empty());
+        assert_eq!(tree.tree().mass(), sample_size as u32);
+        assert_eq!(tree.tree().node_store().len(), 2 * tree.borrow_point_store().len() - 1);
+
+        let mut rebuilt_points = tree.retained_points();
+        points.sort_by_key(|(index, _)| *index);
+        rebuilt_points.sort_by_key(|(index, _)| *index);
+        assert_eq!(rebuilt_points, points);
+
+        // the rebuilt tree accepts updates
+        for i in 1000..1100 {
+            tree.update(vec![(i % 10) as f32, (i % 13) as f32], i);
+        }
+        assert_eq!(tree.tree().mass(), sample_size as u32);
+    }
 }
\ No newline at end of file
@@ -50,7 +50,7 @@
 //! ```
 
 extern crate rand;
-use rand::{Rng, SeedableRng};
+use rand::{Rng, RngCore, SeedableRng};
 
 extern crate rand_chacha;
 use rand_chacha::ChaCha8Rng;
@@ -58,6 +58,7 @@ use rand_chacha::ChaCha8Rng;
 use std::cmp::{Ord, PartialOrd, Eq, Ordering};
 use std::collections::BinaryHeap;
 use std::collections::binary_heap;
+use std::ops::Range;
 
 /// Weighted samples stored in a stream sampler.
 ///
@@ -82,16 +83,24 @@ use std::collections::binary_heap;
 /// assert!(x < y);
 /// assert!(x == z);
 /// ```
+#[derive(Clone)]
 pub struct WeightedSample<T> {
     value: T,
     weight: f32,
+    sequence_index: usize,
 }
 
 impl<T> WeightedSample<T> {
     pub fn new(value: T, weight: f32) -> Self {
+        WeightedSample::new_with_sequence_index(value, weight, 0)
+    }
+
+    /// Create a weighted sample observed at the given sequence index.
+    pub fn new_with_sequence_index(value: T, weight: f32, sequence_index: usize) -> Self {
         WeightedSample {
             value: value,
             weight: weight,
+            sequence_index: sequence_index,
         }
     }
 
@@ -100,6 +109,9 @@ impl<T> WeightedSample<T> {
 
     /// Get the weight of the sample.
     pub fn weight(&self) -> &f32 { &self.weight }
+
+    /// Get the sequence index at which the sample was observed.
+    pub fn sequence_index(&self) -> usize { self.sequence_index }
 }
 
 /// Weighted samples are ordered by their weight. Because weighted samples are
@@ -207,8 +219,9 @@ pub struct StreamSampler<T> {
     weighted_samples: BinaryHeap<WeightedSample<T>>,
     sample_size: usize,
     num_observations: usize,
+    num_accepted: usize,
     time_decay: f32,
-    rng: ChaCha8Rng,
+    rng: Box<dyn RngCore>,
 }
 
 
@@ -238,8 +251,9 @@ impl<T> StreamSampler<T> {
             weighted_samples: BinaryHeap::with_capacity(sample_size),
             sample_size: sample_size,
             num_observations: 0,
+            num_accepted: 0,
             time_decay: time_decay,
-            rng: ChaCha8Rng::from_entropy(),
+            rng: Box::new(ChaCha8Rng::from_entropy()),
         }
     }
 
@@ -254,7 +268,39 @@ impl<T> StreamSampler<T> {
     /// sampler.seed(42);
     /// ```
     pub fn seed(&mut self, seed: u64) {
-        self.rng = ChaCha8Rng::seed_from_u64(seed);
+        self.rng = Box::new(ChaCha8Rng::seed_from_u64(seed));
+    }
+
+    /// Replace the stream sampler's random number generator.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rand::SeedableRng;
+    /// use rand_chacha::ChaCha20Rng;
+    /// use random_cut_forest::StreamSampler;
+    ///
+    /// let mut sampler: StreamSampler<&str> = StreamSampler::new(2, 0.1);
+    /// sampler.set_rng(Box::new(ChaCha20Rng::seed_from_u64(42)));
+    /// ```
+    pub fn set_rng(&mut self, rng: Box<dyn RngCore>) {
+        self.rng = rng;
+    }
+
+    /// Returns a copy of the sampler with the same samples and counts.
+    ///
+    /// The random number generator cannot be copied: the copy has a new one
+    /// seeded from system entropy, which can be replaced with
+    /// [`seed`](Self::seed) or [`set_rng`](Self::set_rng).
+    pub fn fork(&self) -> Self where T: Clone {
+        StreamSampler {
+            weighted_samples: self.weighted_samples.clone(),
+            sample_size: self.sample_size,
+            num_observations: self.num_observations,
+            num_accepted: self.num_accepted,
+            time_decay: self.time_decay,
+            rng: Box::new(ChaCha8Rng::from_entropy()),
+        }
     }
 
     /// Sample a new value with a given sequence index.
@@ -287,11 +333,49 @@ impl<T> StreamSampler<T> {
     /// ```
     ///
     pub fn sample(&mut self, value: T, sequence_index: usize) -> SamplerResult<T> {
-        let weight = self.compute_weight(sequence_index);
+        self.sample_weighted(value, sequence_index, 1.0)
+    }
+
+    /// Sample a new value that stands for `multiplicity` observations with
+    /// the same sequence index.
+    ///
+    /// The value is as likely to be sampled as the first of `multiplicity`
+    /// copies sampled with [`sample`](Self::sample): its weight is lowered
+    /// by `ln(multiplicity)`. This keeps the sample unbiased when a stream
+    /// is subsampled with probability `p` and every retained value is given
+    /// a multiplicity of `1 / p`. It still counts as a single observation.
+    ///
+    /// # Panics
+    ///
+    /// If `multiplicity` is not positive and finite.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use random_cut_forest::StreamSampler;
+    ///
+    /// let mut sampler: StreamSampler<usize> = StreamSampler::new(100, 0.0);
+    /// sampler.seed(0);
+    /// for i in 0..10000 {
+    ///     // even values are kept one time in ten and stand for ten values
+    ///     match i % 2 {
+    ///         0 if i % 20 == 0 => { sampler.sample_weighted(i, i, 10.0); },
+    ///         0 => (),
+    ///         _ => { sampler.sample(i, i); },
+    ///     }
+    /// }
+    /// let num_even = sampler.iter().filter(|sample| sample.value() % 2 == 0).count();
+    /// assert!(num_even > 30 && num_even < 70);
+    /// ```
+    pub fn sample_weighted(&mut self, value: T, sequence_index: usize, multiplicity: f32) -> SamplerResult<T> {
+        assert!(multiplicity > 0.0 && multiplicity.is_finite(), "Multiplicity must be positive and finite");
+        let weight = self.compute_weight(sequence_index) - multiplicity.ln();
         self.num_observations += 1;
 
-        // determine if we should accept the new value into the sample
-        let under_sampled = self.num_observations <= self.sample_size;
+        // determine if we should accept the new value into the sample. A
+        // sampler that is not full, at the start of the stream or after
+        // samples were removed, accepts every value
+        let under_sampled = !self.is_full();
         let new_observation_has_smaller_weight = match self.weighted_samples.peek() {
             Some(sample) => weight < *sample.weight(),
             None => false,
@@ -304,8 +388,9 @@ impl<T> StreamSampler<T> {
                 true => self.weighted_samples.pop(),
                 false => None,
             };
-            let candidate_sample = WeightedSample { value: value, weight: weight };
+            let candidate_sample = WeightedSample::new_with_sequence_index(value, weight, sequence_index);
             self.weighted_samples.push(candidate_sample);
+            self.num_accepted += 1;
 
             return SamplerResult::Accepted(evicted_sample);
         }
@@ -342,6 +427,107 @@ impl<T> StreamSampler<T> {
         -(sequence_index as f32) * self.time_decay + (-random.ln()).ln()
     }
 
+    /// Change the time decay of the sampler.
+    ///
+    /// The weights of the retained samples are rescaled to the new decay,
+    /// using their sequence indexes, so that they rank against future
+    /// samples as if they had been observed with the new decay. The random
+    /// part of each weight is kept. Switching to a larger decay makes the
+    /// sampler forget its history faster, a smaller one slower.
+    ///
+    /// # Panics
+    ///
+    /// If `time_decay` is negative, NaN or infinite.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use random_cut_forest::StreamSampler;
+    ///
+    /// let mut sampler: StreamSampler<usize> = StreamSampler::new(64, 0.0);
+    /// sampler.seed(3);
+    /// for i in 0..2000 {
+    ///     sampler.sample(i, i);
+    /// }
+    /// assert!(sampler.mean_age(1999).unwrap() > 500.0);
+    ///
+    /// sampler.set_time_decay(0.05);
+    /// for i in 2000..2500 {
+    ///     sampler.sample(i, i);
+    /// }
+    /// assert!(sampler.mean_age(2499).unwrap() < 200.0);
+    /// ```
+    pub fn set_time_decay(&mut self, time_decay: f32) {
+        assert!(time_decay >= 0.0 && time_decay.is_finite(),
+            "Time decay must be non-negative and finite");
+
+        let change = time_decay - self.time_decay;
+        let mut samples = std::mem::take(&mut self.weighted_samples).into_vec();
+        for sample in samples.iter_mut() {
+            sample.weight -= sample.sequence_index as f32 * change;
+        }
+        self.weighted_samples = BinaryHeap::from(samples);
+        self.time_decay = time_decay;
+    }
+
+    /// Remove the samples observed at a sequence index in the given range and
+    /// return them, in arbitrary order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use random_cut_forest::StreamSampler;
+    ///
+    /// let mut sampler: StreamSampler<&str> = StreamSampler::new(4, 0.0);
+    /// sampler.sample("a", 1);
+    /// sampler.sample("b", 2);
+    /// sampler.sample("c", 3);
+    ///
+    /// let removed = sampler.remove_range(2..3);
+    /// assert_eq!(removed.len(), 1);
+    /// assert_eq!(removed[0].value(), &"b");
+    /// assert_eq!(sampler.size(), 2);
+    /// ```
+    pub fn remove_range(&mut self, sequence_range: Range<usize>) -> Vec<WeightedSample<T>> {
+        let (removed, kept): (Vec<WeightedSample<T>>, Vec<WeightedSample<T>>) =
+            std::mem::take(&mut self.weighted_samples).into_iter()
+                .partition(|sample| sequence_range.contains(&sample.sequence_index));
+        self.weighted_samples = BinaryHeap::from(kept);
+        self.weighted_samples.reserve(self.sample_size - self.weighted_samples.len());
+        removed
+    }
+
+    /// Replace the value of every sample by the result of `f`, keeping its
+    /// weight and sequence index, and remove the samples for which `f`
+    /// returns `None`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use random_cut_forest::StreamSampler;
+    ///
+    /// let mut sampler: StreamSampler<usize> = StreamSampler::new(4, 0.0);
+    /// sampler.sample(1, 1);
+    /// sampler.sample(2, 2);
+    /// sampler.sample(3, 3);
+    ///
+    /// sampler.retain_map(|value| if *value == 2 { None } else { Some(10 * value) });
+    /// let mut values: Vec<usize> = sampler.iter().map(|sample| *sample.value()).collect();
+    /// values.sort();
+    /// assert_eq!(values, vec![10, 30]);
+    /// ```
+    pub fn retain_map<F>(&mut self, mut f: F) where F: FnMut(&T) -> Option<T> {
+        let samples: Vec<WeightedSample<T>> = std::mem::take(&mut self.weighted_samples).into_iter()
+            .filter_map(|sample| f(&sample.value).map(|value| WeightedSample {
+                value,
+                weight: sample.weight,
+                sequence_index: sample.sequence_index,
+            }))
+            .collect();
+        self.weighted_samples = BinaryHeap::from(samples);
+        self.weighted_samples.reserve(self.sample_size - self.weighted_samples.len());
+    }
+
     /// Returns an iterator on the elements of the sampler.
     ///
     /// This simply returns the result of [`BinaryHeap.iter()`]. The weighted
@@ -350,9 +536,61 @@ impl<T> StreamSampler<T> {
         self.weighted_samples.iter()
     }
 
+    /// Returns the mean age of the retained samples at the given sequence
+    /// index, or `None` if the sampler is empty.
+    ///
+    /// The age of a sample is the difference between `sequence_index` and
+    /// the sequence index at which it was observed. Without time decay, the
+    /// samples are uniform over the stream and the mean age is about half of
+    /// its length; larger decays keep younger samples.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use random_cut_forest::StreamSampler;
+    ///
+    /// let mut uniform: StreamSampler<usize> = StreamSampler::new(64, 0.0);
+    /// let mut recent: StreamSampler<usize> = StreamSampler::new(64, 0.05);
+    /// uniform.seed(1);
+    /// recent.seed(1);
+    /// for i in 0..2000 {
+    ///     uniform.sample(i, i);
+    ///     recent.sample(i, i);
+    /// }
+    /// assert!(uniform.mean_age(1999).unwrap() > 500.0);
+    /// assert!(recent.mean_age(1999).unwrap() < 200.0);
+    /// ```
+    pub fn mean_age(&self, sequence_index: usize) -> Option<f64> {
+        if self.weighted_samples.is_empty() {
+            return None;
+        }
+        let total_age: f64 = self.weighted_samples.iter()
+            .map(|sample| sequence_index.saturating_sub(sample.sequence_index) as f64)
+            .sum();
+        Some(total_age / self.weighted_samples.len() as f64)
+    }
+
     pub fn num_observations(&self) -> usize { self.num_observations }
+
+    /// Returns the number of values accepted into the sample so far,
+    /// including those evicted since.
+    pub fn num_accepted(&self) -> usize { self.num_accepted }
+
+    /// Set the number of observations, for example after filling a sampler
+    /// with the samples of other samplers of the same stream.
+    pub fn set_num_observations(&mut self, num_observations: usize) {
+        self.num_observations = num_observations;
+    }
+
     pub fn is_full(&self) -> bool { self.sample_size == self.weighted_samples.len() }
     pub fn capacity(&self) -> usize { self.sample_size }
+
+    /// Returns the number of bytes allocated on the heap by the sampler,
+    /// including unused capacity and the random number generator.
+    pub fn heap_size(&self) -> usize {
+        self.weighted_samples.capacity() * std::mem::size_of::<WeightedSample<T>>() +
+            std::mem::size_of_val(self.rng.as_ref())
+    }
     pub fn size(&self) -> usize { self.weighted_samples.len() }
     pub fn time_decay(&self) -> f32 { self.time_decay }
 }
@@ -364,11 +602,11 @@ mod tests {
 
     #[test]
     fn test_weighted_sample() {
-        let x1 = WeightedSample { value: "string one", weight: 0.0 };
-        let x2 = WeightedSample { value: "string two", weight: 1.0 };
-        let x3 = WeightedSample { value: "string three", weight: -2.0 };
-        let x4 = WeightedSample { value: "string four", weight: 3.0 };
-        let x5 = WeightedSample { value: "double plus good", weight: 0.0 };
+        let x1 = WeightedSample::new("string one", 0.0);
+        let x2 = WeightedSample::new("string two", 1.0);
+        let x3 = WeightedSample::new("string three", -2.0);
+        let x4 = WeightedSample::new("string four", 3.0);
+        let x5 = WeightedSample::new("double plus good", 0.0);
 
         assert!(x3 < x1 && x1 < x2 && x2 < x4);
         assert!(x1 == x5);
@@ -437,4 +675,34 @@ mod tests {
             SamplerResult::Ignored => panic!("Expected data accepted")
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_set_time_decay() {
+        let mut sampler = StreamSampler::new(8, 0.01);
+        sampler.seed(5);
+        for i in 0..100 {
+            sampler.sample(i, i);
+        }
+        let weights: Vec<(usize, f32)> = sampler.iter()
+            .map(|sample| (sample.sequence_index(), *sample.weight()))
+            .collect();
+
+        sampler.set_time_decay(0.1);
+        assert_eq!(sampler.time_decay(), 0.1);
+        assert_eq!(sampler.size(), 8);
+        for sample in sampler.iter() {
+            let (_, weight) = weights.iter()
+                .find(|(index, _)| *index == sample.sequence_index())
+                .unwrap();
+            let expected = weight - sample.sequence_index() as f32 * 0.09;
+            assert!((sample.weight() - expected).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Time decay must be non-negative and finite")]
+    fn test_set_time_decay_nan() {
+        let mut sampler: StreamSampler<usize> = StreamSampler::new(8, 0.01);
+        sampler.set_time_decay(f32::NAN);
+    }
+}
@@ -7,4 +7,18 @@ use crate::Node;
 pub type PointStore<T> = Slab<Vec<T>>;
 
 /// A type for storing nodes by key.
-pub type NodeStore<T> = Slab<Node<T>>;
\ No newline at end of file
+pub type NodeStore<T> = Slab<Node<T>>;
+
+/// Mirror of the private entry type of a [`Slab`], used to compute the size
+/// of its slots.
+#[allow(dead_code)]
+enum SlabEntry<V> {
+    Vacant(usize),
+    Occupied(V),
+}
+
+/// Returns the number of bytes allocated for the slots of a slab, excluding
+/// any heap memory owned by the stored values.
+pub(crate) fn slab_heap_size<V>(slab: &Slab<V>) -> usize {
+    slab.capacity() * std::mem::size_of::<SlabEntry<V>>()
+}
\ No newline at end of file
@@ -32,6 +32,7 @@ use std::iter::Sum;
 /// assert!(merged_bbox.contains_point(&new_point));
 /// assert!(merged_bbox.contains_box(&bbox));
 /// ```
+#[derive(Clone)]
 pub struct BoundingBox<T> {
     min_values: Vec<T>,
     max_values: Vec<T>,
@@ -204,6 +205,11 @@ impl<T> BoundingBox<T>
     /// Get the sum across all dimensions of lengths of the bounding box.
     pub fn range_sum(&self) -> T { self.range_sum }
 
+    /// Returns the number of bytes allocated on the heap by the bounding box.
+    pub fn heap_size(&self) -> usize {
+        (self.min_values.capacity() + self.max_values.capacity()) * std::mem::size_of::<T>()
+    }
+
     /// Returns true if the given point is contained inside the bounding box
     ///
     /// # Examples
@@ -3,11 +3,77 @@ use num_traits::{Float, Zero};
 
 extern crate rand;
 use rand::distributions::Uniform;
+use rand::seq::index;
 
 use std::iter::Sum;
 
 use crate::BoundingBox;
 
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+
+/// Strategy used to choose random cuts in a bounding box.
+///
+/// * `RangeProportional` - the dimension of the cut is chosen with probability
+///   proportional to the range of the bounding box in that dimension. This is
+///   the strategy of the original RCF algorithm and the one assumed by the
+///   separation probabilities used in scoring.
+/// * `DimensionSubsample(n)` - at every node, up to `n` dimensions with
+///   positive range are sampled uniformly and the cut is chosen proportional
+///   to range among these dimensions only. This limits the influence of a
+///   single dimension with an outlying range, such as in heavy-tailed data.
+///   With `n = 1` the cut dimension is uniform, as in isolation forests.
+///   `n` must be positive.
+///   This strategy is experimental: scores still use range-proportional
+///   separation probabilities.
+///
+/// # Examples
+///
+/// ```
+/// use random_cut_forest::{BoundingBox, Cut};
+/// use random_cut_forest::tree::CutStrategy;
+///
+/// let bbox = BoundingBox::new(&vec![0.0, 0.0], &vec![1000.0, 1.0]);
+/// let mut rng = rand::thread_rng();
+///
+/// // with a single sampled dimension both dimensions are cut equally often
+/// let strategy = CutStrategy::DimensionSubsample(1);
+/// let cut = Cut::new_random_cut_with_strategy(&bbox, strategy, &mut rng).unwrap();
+/// assert!(bbox.min_values()[cut.dimension()] <= cut.value());
+/// assert!(cut.value() < bbox.max_values()[cut.dimension()]);
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum CutStrategy {
+    #[default]
+    RangeProportional,
+    DimensionSubsample(usize),
+}
+
+/// Policy deciding on which side of a cut a point equal to the cut value lies.
+///
+/// * `Left` - a point is left of the cut if its component is less than or
+///   equal to the cut value. This is the convention of the original RCF
+///   algorithm.
+/// * `Right` - a point is left of the cut only if its component is strictly
+///   less than the cut value, so points on the cut go right.
+/// * `Random` - each new cut draws `Left` or `Right` at random when it is
+///   created. The choice is stored with the cut, so the side of a given
+///   point is deterministic for the lifetime of the cut.
+///
+/// The choice matters for integer-valued or otherwise discrete streams,
+/// where points land on cut values often. With `Left`, such points always
+/// share a subtree with the smaller values, which biases the depths at which
+/// they are isolated and, therefore, their scores.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum TieBreaking {
+    #[default]
+    Left,
+    Right,
+    Random,
+}
 
 /// Hyperplane cut inside a bounding box.
 ///
@@ -54,10 +120,14 @@ use crate::BoundingBox;
 /// assert!(bbox.min_values()[random_cut.dimension()] <= random_cut.value());
 /// assert!(random_cut.value() <= bbox.max_values()[random_cut.dimension()]);
 /// ```
-#[derive(Debug)]
+///
+/// By default, points equal to the cut value are to the left of the cut. See
+/// [`TieBreaking`] for other options.
+#[derive(Clone, Debug)]
 pub struct Cut<T> {
     dimension: usize,
     value: T,
+    ties_left: bool,
 }
 
 impl<T> Cut<T> 
@@ -68,7 +138,28 @@ impl<T> Cut<T>
     pub fn new(dimension: usize, value: T) -> Self {
         Cut {
             dimension: dimension,
-            value: value
+            value: value,
+            ties_left: true,
+        }
+    }
+
+    /// Create a new cut from a given dimension and value, specifying whether
+    /// points equal to the cut value are to the left of the cut.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use random_cut_forest::Cut;
+    ///
+    /// let point = vec![1.0, 0.0];
+    /// assert!(Cut::is_left_of(&point, &Cut::new_with_ties(1, 0.0, true)));
+    /// assert!(!Cut::is_left_of(&point, &Cut::new_with_ties(1, 0.0, false)));
+    /// ```
+    pub fn new_with_ties(dimension: usize, value: T, ties_left: bool) -> Self {
+        Cut {
+            dimension,
+            value,
+            ties_left,
         }
     }
 
@@ -98,7 +189,7 @@ impl<T> Cut<T>
     /// assert!(min[cut.dimension()] <= cut.value());
     /// assert!(cut.value() <= max[cut.dimension()]);
     /// ```
-    pub fn new_random_cut<Rng: rand::Rng>(
+    pub fn new_random_cut<Rng: rand::Rng + ?Sized>(
         bounding_box: &BoundingBox<T>,
         rng: &mut Rng,
     ) -> Result<Self, &'static str> {
@@ -114,20 +205,121 @@ impl<T> Cut<T>
             if break_point <= range {
                 let mut cut_value = min[i] + break_point;
                 if cut_value == max[i] && range > Zero::zero() {
-                    cut_value = cut_value - Float::epsilon();
+                    cut_value = step_down(cut_value, min[i]);
+                }
+                return Ok(Cut::new(i, cut_value));
+            }
+            break_point = break_point - range;
+        }
+
+        match Cut::last_positive_range_cut(bounding_box, 0..bounding_box.dimensions()) {
+            Some(cut) => Ok(cut),
+            None => Err("The random cut break point did not lie in the bounding box range."),
+        }
+    }
+
+    /// Returns a random cut inside a bounding box chosen using the given
+    /// [`CutStrategy`].
+    ///
+    /// See [`Cut::new_random_cut`] for the guarantees on the returned cut.
+    pub fn new_random_cut_with_strategy<Rng: rand::Rng + ?Sized>(
+        bounding_box: &BoundingBox<T>,
+        strategy: CutStrategy,
+        rng: &mut Rng,
+    ) -> Result<Self, &'static str> {
+        let num_dimensions = match strategy {
+            CutStrategy::RangeProportional => return Cut::new_random_cut(bounding_box, rng),
+            CutStrategy::DimensionSubsample(num_dimensions) => num_dimensions,
+        };
+
+        let min = bounding_box.min_values();
+        let max = bounding_box.max_values();
+        let candidates: Vec<usize> = (0..bounding_box.dimensions())
+            .filter(|&i| max[i] > min[i])
+            .collect();
+        if candidates.is_empty() || num_dimensions == 0 {
+            return Err("The bounding box has no dimension with a positive range.");
+        }
+
+        let amount = usize::min(num_dimensions, candidates.len());
+        let mut dimensions: Vec<usize> = index::sample(rng, candidates.len(), amount)
+            .iter()
+            .map(|i| candidates[i])
+            .collect();
+        dimensions.sort_unstable();
+
+        let distribution = Uniform::new(0.0, 1.0);
+        let random: f64 = rng.sample(distribution);
+        let range_sum: T = dimensions.iter().map(|&i| max[i] - min[i]).sum();
+        let mut break_point: T = T::from(random).unwrap() * range_sum;
+
+        for &i in dimensions.iter() {
+            let range = max[i] - min[i];
+            if break_point <= range {
+                let mut cut_value = min[i] + break_point;
+                if cut_value == max[i] {
+                    cut_value = step_down(cut_value, min[i]);
                 }
                 return Ok(Cut::new(i, cut_value));
             }
             break_point = break_point - range;
         }
 
-        return Err("The random cut break point did not lie in the bounding box range.");
+        match Cut::last_positive_range_cut(bounding_box, dimensions.into_iter()) {
+            Some(cut) => Ok(cut),
+            None => Err("The random cut break point did not lie in the bounding box range."),
+        }
+    }
+
+    /// Returns a cut at the upper end of the last of the given dimensions
+    /// with a positive range.
+    ///
+    /// Rounding can make the random break point, a fraction of the range sum
+    /// of a box, slightly larger than the sum of the ranges subtracted from
+    /// it, for example when the fraction rounds to one. The break point then
+    /// lies at the very end of the last range.
+    fn last_positive_range_cut<I>(bounding_box: &BoundingBox<T>, dimensions: I) -> Option<Self>
+        where I: DoubleEndedIterator<Item=usize>
+    {
+        let min = bounding_box.min_values();
+        let max = bounding_box.max_values();
+        dimensions.rev()
+            .find(|&i| max[i] > min[i])
+            .map(|i| Cut::new(i, step_down(max[i], min[i])))
+    }
+
+    /// Returns a copy of this cut following the given tie breaking policy.
+    ///
+    /// Cuts created by [`Cut::new_random_cut`] send ties to the left and have
+    /// a value in `[min, max)` on the cut dimension of the bounding box they
+    /// were drawn from, where `min` is the input `min`. When ties are sent to
+    /// the right, a value equal to `min` is moved up (at most to `max`) so
+    /// that both sides of the cut remain non-empty.
+    pub fn with_tie_breaking<Rng: rand::Rng + ?Sized>(
+        &self,
+        tie_breaking: TieBreaking,
+        min: T,
+        max: T,
+        rng: &mut Rng,
+    ) -> Self {
+        let ties_left = match tie_breaking {
+            TieBreaking::Left => true,
+            TieBreaking::Right => false,
+            TieBreaking::Random => rng.gen(),
+        };
+
+        let mut value = self.value;
+        if !ties_left && value == min {
+            value = step_up(value, max);
+        }
+        Cut::new_with_ties(self.dimension, value, ties_left)
     }
 
     /// Returns true if `point` is to the left of `cut`.
     ///
     /// This simply checks if the component of the point in the cut's dimension
-    /// is less than or equal to the cut's value.alloc
+    /// is less than or equal to the cut's value. If the cut sends ties to the
+    /// right then the comparison is strict.
     ///
     /// # Examples
     ///
@@ -139,12 +331,68 @@ impl<T> Cut<T>
     /// assert!(Cut::is_left_of(&point, &cut));
     /// ```
     pub fn is_left_of(point: &Vec<T>, cut: &Cut<T>) -> bool {
-        point[cut.dimension] <= cut.value
+        cut.is_left_of_value(point[cut.dimension])
+    }
+
+    /// Returns true if a component `value` along the cut's dimension is to the
+    /// left of the cut.
+    pub fn is_left_of_value(&self, value: T) -> bool {
+        if self.ties_left {
+            value <= self.value
+        } else {
+            value < self.value
+        }
     }
 
+    /// Returns true if points equal to the cut value are to the left of the
+    /// cut.
+    pub fn ties_left(&self) -> bool { self.ties_left }
+
     /// Get the dimension of the cut.
     pub fn dimension(&self) -> usize { self.dimension }
 
     /// Get the value of the cut.
     pub fn value(&self) -> T { self.value }
-}
\ No newline at end of file
+}
+
+/// Returns a value smaller than `value` but at least `min`, where
+/// `min < value`.
+///
+/// Subtracting the machine epsilon leaves values larger than two unchanged,
+/// so the step is relative to the magnitude of the value.
+fn step_down<T: Float>(value: T, min: T) -> T {
+    let step = T::epsilon() * Float::max(value.abs(), T::one());
+    Float::max(value - step, min)
+}
+
+/// Returns a value larger than `value` but at most `max`, where
+/// `value < max`. See [`step_down`].
+fn step_up<T: Float>(value: T, max: T) -> T {
+    let step = T::epsilon() * Float::max(value.abs(), T::one());
+    Float::min(value + step, max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rand::Rng;
+    use rand::rngs::mock::StepRng;
+
+    #[test]
+    fn test_break_point_at_range_sum() {
+        // the largest uniform sample rounds to one as an f32, putting the
+        // break point at the range sum of the box
+        let mut rng = rand::thread_rng();
+        let mut max_rng = StepRng::new(u64::MAX, 0);
+        for _ in 0..1000 {
+            let min: Vec<f32> = (0..5).map(|_| rng.gen_range(-10.0..10.0)).collect();
+            let max: Vec<f32> = min.iter().map(|m| m + rng.gen_range(0.0..10.0)).collect();
+            let bbox = BoundingBox::new(&min, &max);
+
+            let cut = Cut::new_random_cut(&bbox, &mut max_rng).unwrap();
+            assert!(min[cut.dimension()] <= cut.value());
+            assert!(cut.value() < max[cut.dimension()]);
+        }
+    }
+}
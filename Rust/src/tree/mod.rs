@@ -4,7 +4,7 @@ mod bounding_box;
 pub use bounding_box::BoundingBox;
 
 mod cut;
-pub use cut::Cut;
+pub use cut::{Cut, CutStrategy, TieBreaking};
 
 mod node;
 pub use node::{Internal, Leaf, Node};
@@ -15,5 +15,12 @@ pub use tree_point_addition::AddResult;
 mod tree_point_deletion;
 pub use tree_point_deletion::DeleteResult;
 
+mod tree_group_displacement;
+
+mod tree_bulk_load;
+
+mod tree_validation;
+pub use tree_validation::InvariantViolation;
+
 mod tree;
 pub use tree::{NodeIterator, Tree};
@@ -26,6 +26,7 @@ type PointKey = usize;
 /// assert!(leaf.parent().is_none());
 /// assert_eq!(leaf.mass(), 1);
 /// ```
+#[derive(Clone)]
 pub struct Leaf {
     parent: Option<NodeKey>,
     mass: u32,
@@ -89,6 +90,7 @@ impl Leaf {
 /// // create a new internal node from these data
 /// let node = Internal::new(left_key, right_key, bbox, cut);
 /// ```
+#[derive(Clone)]
 pub struct Internal<T> {
     parent: Option<NodeKey>,
     left: NodeKey,
@@ -168,6 +170,7 @@ impl<T> Internal<T> {
 ///
 /// The methods defined for this enum type are mainly for convenience in working
 /// agnostically with either leaves or internal nodes.
+#[derive(Clone)]
 pub enum Node<T> {
     Leaf(Leaf),
     Internal(Internal<T>),
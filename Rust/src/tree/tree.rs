@@ -2,7 +2,7 @@ extern crate num_traits;
 use num_traits::{Float, One, Zero};
 
 extern crate rand;
-use rand::SeedableRng;
+use rand::{RngCore, SeedableRng};
 
 extern crate rand_chacha;
 use rand_chacha::ChaCha8Rng;
@@ -12,8 +12,8 @@ use std::iter::Sum;
 use std::rc::Rc;
 
 use crate::visitor::Visitor;
-use crate::store::{PointStore, NodeStore};
-use crate::tree::{Cut, Node};
+use crate::store::{self, PointStore, NodeStore};
+use crate::tree::{Cut, CutStrategy, Node, TieBreaking};
 
 /// Random cut tree data structure on nodes and points.
 ///
@@ -49,7 +49,10 @@ pub struct Tree<T> {
     point_store: Rc<RefCell<PointStore<T>>>,
     node_store: NodeStore<T>,
     root_node: Option<usize>,
-    rng: ChaCha8Rng,
+    rng: Box<dyn RngCore>,
+    cut_strategy: CutStrategy,
+    tie_breaking: TieBreaking,
+    masked_dimensions: Vec<bool>,
 }
 
 
@@ -81,7 +84,10 @@ impl<T> Tree<T>
             point_store: point_store.clone(),
             node_store: NodeStore::new(),
             root_node: None,
-            rng: ChaCha8Rng::from_entropy(),
+            rng: Box::new(ChaCha8Rng::from_entropy()),
+            cut_strategy: CutStrategy::RangeProportional,
+            tie_breaking: TieBreaking::Left,
+            masked_dimensions: Vec::new(),
         }
     }
 
@@ -121,9 +127,150 @@ impl<T> Tree<T>
     ///
     /// [cha]: https://rust-random.github.io/rand/rand_chacha/struct.ChaCha8Rng.html
     pub fn seed(&mut self, seed: u64) {
-        self.rng = ChaCha8Rng::seed_from_u64(seed);
+        self.rng = Box::new(ChaCha8Rng::seed_from_u64(seed));
     }
 
+    /// Replaces the tree's random number generator.
+    ///
+    /// Any type implementing [`RngCore`] can be used as a source of random
+    /// cuts; for example, a hardware random number generator or a
+    /// counter-based generator that produces reproducible streams across
+    /// machines.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rand::SeedableRng;
+    /// use rand_chacha::ChaCha20Rng;
+    /// use random_cut_forest::Tree;
+    ///
+    /// let mut tree: Tree<f32> = Tree::new();
+    /// tree.set_rng(Box::new(ChaCha20Rng::seed_from_u64(42)));
+    /// ```
+    pub fn set_rng(&mut self, rng: Box<dyn RngCore>) {
+        self.rng = rng;
+    }
+
+    /// Returns a copy of the tree that gets its points from the given point
+    /// store.
+    ///
+    /// The nodes refer to points by key, so the point store must hold the
+    /// points of this tree under the same keys, for example a clone of the
+    /// tree's point store. The copy has a new random number generator seeded
+    /// from system entropy.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::cell::RefCell;
+    /// use std::rc::Rc;
+    /// use random_cut_forest::Tree;
+    ///
+    /// let mut tree: Tree<f32> = Tree::new();
+    /// tree.add_point(vec![0.0, 0.0]);
+    /// tree.add_point(vec![1.0, 1.0]);
+    ///
+    /// let point_store = Rc::new(RefCell::new(tree.borrow_point_store().clone()));
+    /// let mut copy = tree.fork(point_store);
+    /// copy.add_point(vec![2.0, 2.0]);
+    /// assert_eq!(tree.mass(), 2);
+    /// assert_eq!(copy.mass(), 3);
+    /// ```
+    pub fn fork(&self, point_store: Rc<RefCell<PointStore<T>>>) -> Self {
+        Tree {
+            point_store,
+            node_store: self.node_store.clone(),
+            root_node: self.root_node,
+            rng: Box::new(ChaCha8Rng::from_entropy()),
+            cut_strategy: self.cut_strategy,
+            tie_breaking: self.tie_breaking,
+            masked_dimensions: self.masked_dimensions.clone(),
+        }
+    }
+
+    /// Sets the strategy used to choose the random cuts of new nodes.
+    ///
+    /// Existing nodes keep their cuts. See [`CutStrategy`] for the available
+    /// strategies.
+    ///
+    /// # Panics
+    ///
+    /// If the strategy is `DimensionSubsample(0)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use random_cut_forest::Tree;
+    /// use random_cut_forest::tree::CutStrategy;
+    ///
+    /// let mut tree: Tree<f32> = Tree::new();
+    /// tree.set_cut_strategy(CutStrategy::DimensionSubsample(2));
+    /// assert_eq!(tree.cut_strategy(), CutStrategy::DimensionSubsample(2));
+    /// ```
+    pub fn set_cut_strategy(&mut self, cut_strategy: CutStrategy) {
+        assert!(cut_strategy != CutStrategy::DimensionSubsample(0),
+            "Dimension subsample size must be positive");
+        self.cut_strategy = cut_strategy;
+    }
+
+    /// Returns the strategy used to choose the random cuts of new nodes.
+    pub fn cut_strategy(&self) -> CutStrategy { self.cut_strategy }
+
+    /// Sets the tie breaking policy of the cuts of new nodes.
+    ///
+    /// Existing nodes keep their policy. See [`TieBreaking`] for the
+    /// available policies.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use random_cut_forest::Tree;
+    /// use random_cut_forest::tree::TieBreaking;
+    ///
+    /// let mut tree: Tree<f32> = Tree::new();
+    /// tree.set_tie_breaking(TieBreaking::Right);
+    /// tree.add_point(vec![0.0, 0.0]);
+    /// tree.add_point(vec![1.0, 1.0]);
+    /// assert_eq!(tree.point_mass(&vec![0.0, 0.0]), 1);
+    /// assert_eq!(tree.point_mass(&vec![1.0, 1.0]), 1);
+    /// ```
+    pub fn set_tie_breaking(&mut self, tie_breaking: TieBreaking) {
+        self.tie_breaking = tie_breaking;
+    }
+
+    /// Returns the tie breaking policy of the cuts of new nodes.
+    pub fn tie_breaking(&self) -> TieBreaking { self.tie_breaking }
+
+    /// Sets the dimensions excluded from the cuts of new nodes.
+    ///
+    /// Dimension `i` is masked if `masked_dimensions[i]` is true; an empty
+    /// vector masks no dimension. A new node only cuts a masked dimension if
+    /// its points differ in no other dimension. Existing nodes keep their
+    /// cuts.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use random_cut_forest::Tree;
+    /// use random_cut_forest::tree::Node;
+    ///
+    /// let mut tree: Tree<f32> = Tree::new();
+    /// tree.set_masked_dimensions(vec![true, false]);
+    /// tree.add_point(vec![0.0, 0.0]);
+    /// tree.add_point(vec![100.0, 1.0]);
+    ///
+    /// let root = tree.root_node().unwrap();
+    /// if let Node::Internal(internal) = tree.get_node(root) {
+    ///     assert_eq!(internal.cut().dimension(), 1);
+    /// }
+    /// ```
+    pub fn set_masked_dimensions(&mut self, masked_dimensions: Vec<bool>) {
+        self.masked_dimensions = masked_dimensions;
+    }
+
+    /// Returns the dimensions excluded from the cuts of new nodes.
+    pub fn masked_dimensions(&self) -> &[bool] { &self.masked_dimensions }
+
     /// Return the number of points in the tree's point store.
     ///
     /// It is important to note that if this is a shared point store then this
@@ -178,6 +325,83 @@ impl<T> Tree<T>
         }
     }
 
+    /// Returns the number of bytes allocated on the heap by the tree.
+    ///
+    /// This includes the slots of the node and point stores, the bounding
+    /// boxes of the internal nodes, the stored points, and the random number
+    /// generator. Capacities are used rather than lengths, so unused but
+    /// allocated space is counted. A point store shared with other trees is
+    /// counted by each of them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use random_cut_forest::Tree;
+    ///
+    /// let mut tree: Tree<f32> = Tree::new();
+    /// let empty_size = tree.heap_size();
+    ///
+    /// tree.add_point(vec![0.0, 0.0]);
+    /// tree.add_point(vec![1.0, 1.0]);
+    /// assert!(tree.heap_size() > empty_size + 2 * 2 * std::mem::size_of::<f32>());
+    /// ```
+    pub fn heap_size(&self) -> usize {
+        let node_store = store::slab_heap_size(&self.node_store);
+        let bounding_boxes: usize = self.node_store.iter()
+            .map(|(_, node)| match node {
+                Node::Internal(internal) => internal.bounding_box().heap_size(),
+                Node::Leaf(_) => 0,
+            })
+            .sum();
+
+        // the point store lives in a reference counted allocation holding the
+        // strong and weak counts next to the store
+        let point_store = self.point_store.borrow();
+        let points: usize = point_store.iter()
+            .map(|(_, point)| point.capacity() * std::mem::size_of::<T>())
+            .sum();
+        let point_store_size = 2 * std::mem::size_of::<usize>() +
+            std::mem::size_of::<RefCell<PointStore<T>>>() +
+            store::slab_heap_size(&point_store) + points;
+
+        node_store + bounding_boxes + point_store_size + std::mem::size_of_val(self.rng.as_ref()) +
+            self.masked_dimensions.capacity() * std::mem::size_of::<bool>()
+    }
+
+    /// Returns the mass of the leaf containing the given point.
+    ///
+    /// Duplicate points are not stored more than once. Instead, the mass of
+    /// the leaf containing the point is increased. This function returns
+    /// that mass or zero if the point is not contained in the tree.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use random_cut_forest::Tree;
+    ///
+    /// let mut tree: Tree<f32> = Tree::new();
+    /// tree.add_point(vec![0.0, 0.0]);
+    /// tree.add_point(vec![1.0, 1.0]);
+    /// tree.add_point(vec![0.0, 0.0]);
+    ///
+    /// assert_eq!(tree.point_mass(&vec![0.0, 0.0]), 2);
+    /// assert_eq!(tree.point_mass(&vec![1.0, 1.0]), 1);
+    /// assert_eq!(tree.point_mass(&vec![2.0, 2.0]), 0);
+    /// ```
+    pub fn point_mass(&self, point: &Vec<T>) -> u32 {
+        match self.iter(point).last() {
+            Some(Node::Leaf(leaf)) => {
+                let store = self.borrow_point_store();
+                if *store.get(leaf.point()).unwrap() == *point {
+                    leaf.mass()
+                } else {
+                    0
+                }
+            },
+            _ => 0,
+        }
+    }
+
     /// Returns an iterator on nodes.
     ///
     /// Given a query point, a random cut tree iteration begins at the root node
@@ -261,6 +485,16 @@ impl<T> Tree<T>
         visitor.get_result()
     }
 
+    /// Remove every node from the tree, leaving it empty.
+    ///
+    /// The points referenced by the removed leaves are not removed from the
+    /// point store, which may be shared with other trees. Used to rebuild a
+    /// tree whose nodes are no longer consistent.
+    pub fn clear(&mut self) {
+        self.node_store.clear();
+        self.root_node = None;
+    }
+
     // =========================================================================
     // Helper Functions
     //
@@ -287,7 +521,7 @@ impl<T> Tree<T>
     pub fn node_store_mut(&mut self) -> &mut NodeStore<T> { &mut self.node_store }
 
     #[inline(always)]
-    pub fn rng_mut(&mut self) -> &mut ChaCha8Rng { &mut self.rng }
+    pub fn rng_mut(&mut self) -> &mut dyn RngCore { self.rng.as_mut() }
 
     #[inline(always)]
     pub fn get_node(&self, node_key: usize) -> &Node<T> {
@@ -512,4 +746,67 @@ mod tests {
             }
         }
    }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_duplicate_mass() {
+        let mut tree: Tree<f32> = Tree::new();
+        let points = generate_random_normal(3, 8);
+        let num_copies = 1000;
+
+        // a duplicate-heavy stream: every point is added many times
+        for _ in 0..num_copies {
+            for point in points.iter() {
+                tree.add_point(point.clone());
+            }
+        }
+        assert_eq!(tree.num_points(), points.len());
+        assert_eq!(tree.node_store().len(), 2*points.len() - 1);
+        assert_eq!(tree.mass(), (num_copies * points.len()) as u32);
+        check_node_masses(&tree, tree.root_node().unwrap());
+        for point in points.iter() {
+            assert_eq!(tree.point_mass(point), num_copies as u32);
+        }
+
+        // remove the copies one at a time, interleaved across points
+        for i in 0..num_copies {
+            for point in points.iter() {
+                tree.delete_point(point);
+                assert_eq!(tree.point_mass(point), (num_copies - i - 1) as u32);
+            }
+            if let Some(root_key) = tree.root_node() {
+                check_node_masses(&tree, root_key);
+            }
+        }
+        assert_eq!(tree.mass(), 0);
+        assert_eq!(tree.num_points(), 0);
+        assert_eq!(tree.node_store().len(), 0);
+    }
+
+    #[test]
+    fn test_tie_breaking() {
+        for tie_breaking in [TieBreaking::Left, TieBreaking::Right, TieBreaking::Random].iter() {
+            let mut tree: Tree<f32> = Tree::new();
+            tree.set_tie_breaking(*tie_breaking);
+
+            // integer-valued points land on cut values often
+            let points: Vec<Vec<f32>> = (0..200)
+                .map(|i| vec![(i % 7) as f32, (i % 5) as f32])
+                .collect();
+            for point in points.iter() {
+                tree.add_point(point.clone());
+            }
+            assert_eq!(tree.num_points(), 35);
+            check_node_masses(&tree, tree.root_node().unwrap());
+
+            // every point is found by following the cuts
+            for point in points.iter() {
+                assert!(tree.point_mass(point) > 0);
+            }
+            for point in points.iter() {
+                let result = tree.delete_point(point);
+                assert!(!std::matches!(result, DeleteResult::PointNotFound));
+            }
+            assert_eq!(tree.mass(), 0);
+        }
+    }
+}
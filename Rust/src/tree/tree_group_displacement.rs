@@ -0,0 +1,143 @@
+extern crate num_traits;
+use num_traits::{Float, One, Zero};
+
+use std::iter::Sum;
+
+use crate::tree::{BoundingBox, Cut, Node, Tree};
+
+impl<T> Tree<T>
+    where T: Float + Sum
+{
+
+    /// Returns the expected number of points displaced when a group of points
+    /// is inserted into the tree jointly.
+    ///
+    /// Points of the group that are already retained by the tree are first
+    /// removed from it, so that members of a burst do not mask each other.
+    /// The bounding boxes of the nodes without the group points are the
+    /// *shadow boxes* used in place of the stored boxes. Starting at the
+    /// root, the bounding box of the group is separated from the shadow box
+    /// of a node by a random cut with a probability proportional to the gaps
+    /// between the two boxes, in which case the group displaces all remaining
+    /// points of the node. Otherwise, the group follows the cut of the node.
+    /// When the cut splits the group, the displacements of the two parts are
+    /// added.
+    ///
+    /// For a single point that is not retained by the tree, this is the
+    /// unnormalized
+    /// [`ScoreFunction::Displacement`](crate::visitor::ScoreFunction::Displacement)
+    /// score. A burst of similar points has a large displacement as a whole
+    /// even after it has been added to the tree and each of its points is no
+    /// longer isolated.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use random_cut_forest::Tree;
+    ///
+    /// let mut tree: Tree<f32> = Tree::new();
+    /// for i in 0..10 {
+    ///     tree.add_point(vec![i as f32, 0.0]);
+    /// }
+    ///
+    /// // a burst of duplicates displaces almost all other points once removed
+    /// let burst = vec![vec![100.0, 100.0]; 5];
+    /// for point in burst.iter() {
+    ///     tree.add_point(point.clone());
+    /// }
+    /// assert!(tree.group_displacement(&burst) > 9.0);
+    /// ```
+    pub fn group_displacement(&self, points: &[Vec<T>]) -> T {
+        match self.root_node() {
+            Some(root_key) if !points.is_empty() => {
+                let group: Vec<&Vec<T>> = points.iter().collect();
+                self.group_displacement_at_node(&group, root_key).2
+            },
+            _ => Zero::zero(),
+        }
+    }
+
+    /// Returns the mass and the bounding box of the node without the points
+    /// of the group, and the displacement of the group at the node. The group
+    /// consists of the points routed to this node by the cuts above it.
+    fn group_displacement_at_node(
+        &self,
+        group: &[&Vec<T>],
+        node_key: usize,
+    ) -> (u32, Option<BoundingBox<T>>, T) {
+        let node = self.get_node(node_key);
+        if group.is_empty() {
+            let bounding_box = match node {
+                Node::Leaf(leaf) => {
+                    let point_store = self.borrow_point_store();
+                    BoundingBox::new_from_point(point_store.get(leaf.point()).unwrap())
+                },
+                Node::Internal(internal) => internal.bounding_box().clone(),
+            };
+            return (node.mass(), Some(bounding_box), Zero::zero());
+        }
+
+        match node {
+            Node::Leaf(leaf) => {
+                let point_store = self.borrow_point_store();
+                let leaf_point = point_store.get(leaf.point()).unwrap();
+                if group.contains(&leaf_point) {
+                    (0, None, Zero::zero())
+                } else {
+                    let mass = T::from(leaf.mass()).unwrap();
+                    (leaf.mass(), Some(BoundingBox::new_from_point(leaf_point)), mass)
+                }
+            },
+            Node::Internal(internal) => {
+                let (left, right): (Vec<&Vec<T>>, Vec<&Vec<T>>) = group.iter()
+                    .partition(|point| Cut::is_left_of(point, internal.cut()));
+                let (left_mass, left_box, left_score) =
+                    self.group_displacement_at_node(&left, internal.left());
+                let (right_mass, right_box, right_score) =
+                    self.group_displacement_at_node(&right, internal.right());
+
+                let shadow_box = match (left_box, right_box) {
+                    (Some(left_box), Some(right_box)) =>
+                        BoundingBox::merged_box_with_box(&left_box, &right_box),
+                    (Some(shadow_box), None) | (None, Some(shadow_box)) => shadow_box,
+                    (None, None) => return (0, None, Zero::zero()),
+                };
+                let shadow_mass = left_mass + right_mass;
+
+                let group_box = group.iter().skip(1).fold(
+                    BoundingBox::new_from_point(group[0]),
+                    |bounding_box, point| BoundingBox::merged_box_with_point(&bounding_box, point));
+                let separation_probability = separation_probability(&shadow_box, &group_box);
+
+                let one: T = One::one();
+                let score = separation_probability * T::from(shadow_mass).unwrap() +
+                    (one - separation_probability) * (left_score + right_score);
+                (shadow_mass, Some(shadow_box), score)
+            }
+        }
+    }
+}
+
+/// Returns the probability that a random cut on the merged box of two
+/// bounding boxes separates them.
+fn separation_probability<T>(bounding_box: &BoundingBox<T>, shadow_box: &BoundingBox<T>) -> T
+    where T: Float + Sum
+{
+    let mut gap_sum: T = Zero::zero();
+    let mut merged_range_sum: T = Zero::zero();
+    for i in 0..bounding_box.dimensions() {
+        let (min, max) = (bounding_box.min_values()[i], bounding_box.max_values()[i]);
+        let (shadow_min, shadow_max) = (shadow_box.min_values()[i], shadow_box.max_values()[i]);
+        let gap = Float::max(shadow_min - max, min - shadow_max);
+        if gap > Zero::zero() {
+            gap_sum = gap_sum + gap;
+        }
+        merged_range_sum = merged_range_sum + Float::max(max, shadow_max) - Float::min(min, shadow_min);
+    }
+
+    if merged_range_sum > Zero::zero() {
+        gap_sum / merged_range_sum
+    } else {
+        Zero::zero()
+    }
+}
@@ -110,9 +110,13 @@ impl<T> Tree<T>
         // bounding box. We need to go deeper to find separation.
         let merged_box = self.merge_node_with_point(node_key, &point);
         if !self.point_inside_node(&point, node_key) {
-            let cut = Cut::new_random_cut(&merged_box, self.rng_mut()).unwrap();
+            let tie_breaking = self.tie_breaking();
+            let cut = self.new_random_cut(&merged_box);
+            let dim = cut.dimension();
+            let (merged_min, merged_max) = (merged_box.min_values()[dim], merged_box.max_values()[dim]);
+            let cut = cut.with_tie_breaking(tie_breaking, merged_min, merged_max, self.rng_mut());
             let (min, max) = self.range_on_cut_dimension(node_key, &cut);
-            if (cut.value() < min) || (max <= cut.value()) {
+            if cut.is_left_of_value(min) == cut.is_left_of_value(max) {
                 let new_point_key = self.insert_new_leaf(
                     point, node_key, merged_box, cut, min);
                 return AddResult::AddedPoint(new_point_key);
@@ -141,6 +145,37 @@ impl<T> Tree<T>
         result
     }
 
+    /// Returns a random cut on the merged box of a new node.
+    ///
+    /// The cut is chosen using the tree's cut strategy on the merged box with
+    /// the ranges of masked dimensions collapsed, unless the box has no
+    /// positive range outside of the masked dimensions.
+    pub(crate) fn new_random_cut(&mut self, merged_box: &BoundingBox<T>) -> Cut<T> {
+        let cut_strategy = self.cut_strategy();
+        let masked_box = self.masked_box(merged_box);
+        let cut_box = match &masked_box {
+            Some(masked_box) if masked_box.range_sum() > T::zero() => masked_box,
+            _ => merged_box,
+        };
+        Cut::new_random_cut_with_strategy(cut_box, cut_strategy, self.rng_mut()).unwrap()
+    }
+
+    /// Returns the box with the ranges of masked dimensions collapsed to
+    /// their minimum, or `None` if no dimension is masked.
+    fn masked_box(&self, bounding_box: &BoundingBox<T>) -> Option<BoundingBox<T>> {
+        let masked = self.masked_dimensions();
+        if !masked.iter().any(|&m| m) {
+            return None;
+        }
+        let min_values = bounding_box.min_values();
+        let max_values: Vec<T> = bounding_box.max_values().iter()
+            .zip(min_values.iter())
+            .zip(masked.iter())
+            .map(|((&max, &min), &m)| if m { min } else { max })
+            .collect();
+        Some(BoundingBox::new(min_values, &max_values))
+    }
+
     /// If the current node is a leaf *and* its point is equal to that of the
     /// input point then increase the mass of this leaf and return `true`.
     /// Otherwise, return `false`.
@@ -264,7 +299,7 @@ impl<T> Tree<T>
         let new_leaf_key = self.insert_node(new_leaf);
 
         // B: new merged node. update parent to node's parent
-        let (left, right) = if min > proposed_cut.value() {
+        let (left, right) = if !proposed_cut.is_left_of_value(min) {
             (new_leaf_key, node_key)
         } else {
             (node_key, new_leaf_key)
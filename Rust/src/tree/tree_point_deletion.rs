@@ -111,13 +111,17 @@ impl<T> Tree<T>
         };
 
         // Recurse. Skip updating bounding boxes if a point was not deleted
-        // or if the mass of a point was decreased. Skip if the current node
-        // was deleted
+        // or if the mass of a point was decreased. In the latter case the
+        // mass of this node still needs to be decremented. Skip if the
+        // current node was deleted
         let result = self.delete_point_by_node(point, next_node_key);
         match result {
             DeleteResult::EmptyTree => return result,
             DeleteResult::PointNotFound => return result,
-            DeleteResult::MassDecreased(_) => return result,
+            DeleteResult::MassDecreased(_) => {
+                self.get_node_mut(node_key).decrement_mass();
+                return result;
+            },
             DeleteResult::DeletedPoint(_) => {
                 if !self.contains_node(node_key) {
                     return result;
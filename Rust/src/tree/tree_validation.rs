@@ -0,0 +1,187 @@
+extern crate num_traits;
+use num_traits::Float;
+
+use std::collections::HashSet;
+use std::iter::Sum;
+
+use crate::tree::{BoundingBox, Cut, Node, Tree};
+
+/// A broken structural invariant of a tree, found by [`Tree::validate`].
+///
+/// Nodes and points are identified by their keys in the node store and
+/// point store of the tree.
+#[derive(Clone, Debug, PartialEq)]
+pub enum InvariantViolation {
+    /// A node refers to a node that is not in the node store.
+    MissingNode { node: usize },
+    /// A node is reached more than once from the root.
+    RevisitedNode { node: usize },
+    /// The parent of a node is not the node that refers to it, or the root
+    /// has a parent.
+    BrokenParentLink { node: usize },
+    /// A leaf has a mass of zero.
+    ZeroMassLeaf { node: usize },
+    /// A leaf refers to a point that is not in the point store.
+    MissingPoint { node: usize, point: usize },
+    /// A leaf refers to the point of another leaf.
+    SharedPoint { node: usize, point: usize },
+    /// The mass of an internal node is not the sum of the masses of its
+    /// children.
+    InconsistentMass { node: usize, mass: u32, expected: u32 },
+    /// The bounding box of an internal node is not the merged box of its
+    /// children.
+    InconsistentBoundingBox { node: usize },
+    /// The cut of an internal node does not separate its children.
+    NonSeparatingCut { node: usize },
+    /// The node store holds nodes that are not reachable from the root.
+    UnreachableNodes { count: usize },
+    /// The point store holds points that no leaf refers to.
+    UnreferencedPoints { count: usize },
+    /// The mass of a point in the tree differs from the number of times it
+    /// is in the sample of a [`SampledTree`](crate::SampledTree).
+    SampleMassMismatch { point: usize, num_samples: usize, mass: u32 },
+    /// The mass of the tree differs from the size of the sample of a
+    /// [`SampledTree`](crate::SampledTree).
+    SampleSizeMismatch { num_samples: usize, mass: u32 },
+}
+
+/// Mass and bounding box of a subtree, recomputed from its leaves.
+type Subtree<T> = (u32, BoundingBox<T>);
+
+impl<T> Tree<T>
+    where T: Float + Sum
+{
+
+    /// Check the structure of the tree and return the invariants it breaks,
+    /// in depth first order from the root.
+    ///
+    /// The tree is walked without following any link twice, so that a
+    /// corrupted tree is reported instead of causing a panic or an endless
+    /// loop. The checks are:
+    ///
+    /// * every node refers to nodes and points that exist, and each node and
+    ///   point is reached once
+    /// * the root has no parent and every child refers back to its parent
+    /// * leaves have a positive mass, and the mass of an internal node is the
+    ///   sum of the masses of its children
+    /// * the bounding box of an internal node is the merged box of its
+    ///   children, and its cut separates them
+    /// * the node store holds only nodes reachable from the root, and the
+    ///   point store only points referred to by a leaf
+    ///
+    /// The last check assumes that the tree does not share its point store.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use random_cut_forest::Tree;
+    /// use random_cut_forest::tree::InvariantViolation;
+    ///
+    /// let mut tree: Tree<f32> = Tree::new();
+    /// tree.add_point(vec![0.0, 0.0]);
+    /// tree.add_point(vec![1.0, 1.0]);
+    /// assert!(tree.validate().is_empty());
+    ///
+    /// // corrupt the mass of the root
+    /// let root = tree.root_node().unwrap();
+    /// tree.get_node_mut(root).set_mass(3);
+    /// assert_eq!(tree.validate(), vec![
+    ///     InvariantViolation::InconsistentMass { node: root, mass: 3, expected: 2 },
+    /// ]);
+    /// ```
+    pub fn validate(&self) -> Vec<InvariantViolation> {
+        let mut violations = Vec::new();
+        let mut visited: HashSet<usize> = HashSet::new();
+        let mut points: HashSet<usize> = HashSet::new();
+        if let Some(root_key) = self.root_node() {
+            self.validate_node(root_key, None, &mut visited, &mut points, &mut violations);
+        }
+
+        let num_unreachable = self.node_store().len().saturating_sub(visited.len());
+        if num_unreachable > 0 {
+            violations.push(InvariantViolation::UnreachableNodes { count: num_unreachable });
+        }
+        let num_unreferenced = self.borrow_point_store().len().saturating_sub(points.len());
+        if num_unreferenced > 0 {
+            violations.push(InvariantViolation::UnreferencedPoints { count: num_unreferenced });
+        }
+        violations
+    }
+
+    /// Check the subtree at the given node, whose parent should be `parent`,
+    /// and return its recomputed mass and bounding box, or `None` if it
+    /// cannot be recomputed.
+    fn validate_node(
+        &self,
+        node_key: usize,
+        parent: Option<usize>,
+        visited: &mut HashSet<usize>,
+        points: &mut HashSet<usize>,
+        violations: &mut Vec<InvariantViolation>,
+    ) -> Option<Subtree<T>> {
+        let node = match self.node_store().get(node_key) {
+            Some(node) => node,
+            None => {
+                violations.push(InvariantViolation::MissingNode { node: node_key });
+                return None;
+            }
+        };
+        if !visited.insert(node_key) {
+            violations.push(InvariantViolation::RevisitedNode { node: node_key });
+            return None;
+        }
+        if node.parent() != parent {
+            violations.push(InvariantViolation::BrokenParentLink { node: node_key });
+        }
+
+        match node {
+            Node::Leaf(leaf) => {
+                if leaf.mass() == 0 {
+                    violations.push(InvariantViolation::ZeroMassLeaf { node: node_key });
+                }
+                let point_store = self.borrow_point_store();
+                let point = match point_store.get(leaf.point()) {
+                    Some(point) => point,
+                    None => {
+                        violations.push(InvariantViolation::MissingPoint { node: node_key, point: leaf.point() });
+                        return None;
+                    }
+                };
+                if !points.insert(leaf.point()) {
+                    violations.push(InvariantViolation::SharedPoint { node: node_key, point: leaf.point() });
+                }
+                Some((leaf.mass(), BoundingBox::new_from_point(point)))
+            },
+            Node::Internal(internal) => {
+                let left = self.validate_node(internal.left(), Some(node_key), visited, points, violations);
+                let right = self.validate_node(internal.right(), Some(node_key), visited, points, violations);
+                let ((left_mass, left_box), (right_mass, right_box)) = (left?, right?);
+
+                let mass = left_mass + right_mass;
+                if internal.mass() != mass {
+                    violations.push(InvariantViolation::InconsistentMass {
+                        node: node_key,
+                        mass: internal.mass(),
+                        expected: mass,
+                    });
+                }
+                let merged_box = BoundingBox::merged_box_with_box(&left_box, &right_box);
+                let bounding_box = internal.bounding_box();
+                if bounding_box.min_values() != merged_box.min_values() ||
+                    bounding_box.max_values() != merged_box.max_values()
+                {
+                    violations.push(InvariantViolation::InconsistentBoundingBox { node: node_key });
+                }
+                let cut = internal.cut();
+                let dim = cut.dimension();
+                if dim >= left_box.dimensions() ||
+                    !Cut::is_left_of(left_box.max_values(), cut) ||
+                    Cut::is_left_of(right_box.min_values(), cut)
+                {
+                    violations.push(InvariantViolation::NonSeparatingCut { node: node_key });
+                }
+                Some((mass, merged_box))
+            }
+        }
+    }
+}
@@ -0,0 +1,432 @@
+//! Submodule for choosing forest parameters on sample data.
+//!
+//! Every candidate configuration is evaluated prequentially: each point of a
+//! sample stream is first scored by a forest built from the candidate and
+//! then used to update it, as it would be in a deployed detector. The scores
+//! are passed to an objective function, such as [`roc_auc`] against known
+//! labels, and the candidates are ranked by their objective values.
+//! [`sensitivity`] instead reports how the scores of a single configuration
+//! shift when its parameters are perturbed.
+//!
+//! # Examples
+//!
+//! ```
+//! use random_cut_forest::{RandomCutForest, RandomCutForestBuilder};
+//! use random_cut_forest::tuning::{ParameterGrid, roc_auc, tune};
+//!
+//! // a periodic stream with a few labeled anomalies
+//! let points: Vec<Vec<f32>> = (0..400)
+//!     .map(|i| vec![(i % 20) as f32, if i % 97 == 96 { 30.0 } else { 0.0 }])
+//!     .collect();
+//! let labels: Vec<bool> = (0..400).map(|i| i % 97 == 96).collect();
+//!
+//! let base = RandomCutForestBuilder::<f32>::new(2).seed(0).build().parameters();
+//! let grid = ParameterGrid {
+//!     num_trees: vec![5, 20],
+//!     sample_sizes: vec![16, 64],
+//!     time_decays: vec![0.0],
+//! };
+//!
+//! let ranked = tune(grid.candidates(&base), &points, |scores| roc_auc(scores, &labels), None);
+//! assert_eq!(ranked.len(), 4);
+//! let (best_parameters, best_auc) = &ranked[0];
+//! assert!(*best_auc > 0.9);
+//!
+//! let forest: RandomCutForest<f32> = RandomCutForestBuilder::from_parameters(best_parameters)
+//!     .unwrap()
+//!     .build();
+//! ```
+//!
+extern crate num_traits;
+use num_traits::Float;
+
+use std::cmp::Ordering;
+use std::iter::Sum;
+
+use crate::{ForestParameters, RandomCutForest, RandomCutForestBuilder};
+
+/// A grid of values for the parameters that most affect detection quality.
+///
+/// The candidates of the grid are all combinations of these values. Other
+/// parameters are taken from a base configuration.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ParameterGrid {
+    pub num_trees: Vec<usize>,
+    pub sample_sizes: Vec<usize>,
+    pub time_decays: Vec<f32>,
+}
+
+impl ParameterGrid {
+
+    /// Return every combination of the grid values applied to the base
+    /// configuration, varying the time decay fastest and the number of trees
+    /// slowest. Every tree of a candidate uses the candidate's sample size
+    /// and time decay.
+    pub fn candidates<T: Clone>(&self, base: &ForestParameters<T>) -> Vec<ForestParameters<T>> {
+        let mut candidates = Vec::new();
+        for &num_trees in self.num_trees.iter() {
+            for &sample_size in self.sample_sizes.iter() {
+                for &time_decay in self.time_decays.iter() {
+                    let mut candidate = base.clone();
+                    candidate.num_trees = num_trees;
+                    candidate.sample_size = sample_size;
+                    candidate.tree_sample_sizes = None;
+                    candidate.time_decay = time_decay;
+                    candidate.tree_time_decays = None;
+                    candidates.push(candidate);
+                }
+            }
+        }
+        candidates
+    }
+}
+
+/// Evaluate candidate configurations on a sample stream and return them
+/// ranked by decreasing objective value.
+///
+/// For each candidate a forest is built, and every point is scored before it
+/// is added to the forest. The `objective` receives the scores of all points,
+/// in stream order, and returns a value where larger is better. Candidates
+/// are evaluated in the given order. With a `patience` of `n`, the search
+/// stops early after `n` consecutive candidates that do not improve on the
+/// best objective value so far; only the evaluated candidates are returned.
+///
+/// Candidates that are not valid configurations, see
+/// [`RandomCutForestBuilder::from_parameters`], are skipped. Objective
+/// values that are NaN are ranked last.
+pub fn tune<T, F>(
+    candidates: Vec<ForestParameters<T>>,
+    points: &[Vec<T>],
+    objective: F,
+    patience: Option<usize>,
+) -> Vec<(ForestParameters<T>, f64)>
+    where T: Float + Sum, F: Fn(&[T]) -> f64
+{
+    let mut results: Vec<(ForestParameters<T>, f64)> = Vec::with_capacity(candidates.len());
+    let mut best = f64::NEG_INFINITY;
+    let mut num_without_improvement = 0;
+    for candidate in candidates {
+        let builder = match RandomCutForestBuilder::from_parameters(&candidate) {
+            Ok(builder) => builder,
+            Err(_) => continue,
+        };
+        let value = objective(&prequential_scores(builder.build(), points));
+        results.push((candidate, value));
+
+        if value > best {
+            best = value;
+            num_without_improvement = 0;
+        } else {
+            num_without_improvement += 1;
+            if patience.is_some_and(|patience| num_without_improvement >= patience) {
+                break;
+            }
+        }
+    }
+
+    results.sort_by(|(_, a), (_, b)| match (a.is_nan(), b.is_nan()) {
+        (false, false) => b.partial_cmp(a).unwrap(),
+        (a_nan, b_nan) => a_nan.cmp(&b_nan),
+    });
+    results
+}
+
+/// Area under the ROC curve of scores against binary labels.
+///
+/// This is the probability that a randomly chosen positive has a larger
+/// score than a randomly chosen negative, counting ties as one half. Returns
+/// NaN if there are no positives or no negatives.
+///
+/// # Panics
+///
+/// If `scores` and `labels` have different lengths.
+///
+/// # Examples
+///
+/// ```
+/// use random_cut_forest::tuning::roc_auc;
+///
+/// let scores = vec![0.1, 0.4, 0.35, 0.8];
+/// let labels = vec![false, false, true, true];
+/// assert_eq!(roc_auc(&scores, &labels), 0.75);
+/// ```
+pub fn roc_auc<T: Float>(scores: &[T], labels: &[bool]) -> f64 {
+    assert_eq!(scores.len(), labels.len(), "Scores and labels must have the same length");
+    let num_positives = labels.iter().filter(|&&label| label).count();
+    let num_negatives = labels.len() - num_positives;
+    if num_positives == 0 || num_negatives == 0 {
+        return f64::NAN;
+    }
+
+    let positive_rank_sum: f64 = ranks(scores).iter()
+        .zip(labels.iter())
+        .filter(|(_, &label)| label)
+        .map(|(rank, _)| rank)
+        .sum();
+    let p = num_positives as f64;
+    (positive_rank_sum - p * (p + 1.0) / 2.0) / (p * num_negatives as f64)
+}
+
+/// The change in the score distribution of a forest when one parameter is
+/// perturbed. See [`sensitivity`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Sensitivity<T> {
+    /// The perturbed configuration.
+    pub parameters: ForestParameters<T>,
+    /// Mean score of the perturbed forest minus that of the base forest.
+    pub mean_shift: f64,
+    /// Shift of each requested quantile of the scores.
+    pub quantile_shifts: Vec<f64>,
+    /// Spearman rank correlation between the scores of the perturbed and the
+    /// base forest. Values close to one mean the perturbation hardly changes
+    /// which points are considered anomalous.
+    pub rank_correlation: f64,
+}
+
+/// Report how the scores of a sample stream shift when the parameters of a
+/// base configuration are perturbed.
+///
+/// The number of trees, the sample size and, if it is not zero, the time
+/// decay are each halved and doubled in turn, with all other parameters kept
+/// at their base values. Per-tree sample sizes and time decays, if any, are
+/// scaled along with the sample size and time decay, and repeated
+/// cyclically when the number of trees grows. Every configuration scores
+/// the points prequentially, as in [`tune`], and the result for each
+/// perturbation compares its scores with those of the base configuration.
+///
+/// # Panics
+///
+/// If `points` is empty, if the base configuration is invalid, see
+/// [`RandomCutForestBuilder::from_parameters`], or if a quantile is not in
+/// `[0, 1]`.
+///
+/// # Examples
+///
+/// ```
+/// use random_cut_forest::RandomCutForestBuilder;
+/// use random_cut_forest::tuning::sensitivity;
+///
+/// let points: Vec<Vec<f32>> = (0..300).map(|i| vec![(i % 17) as f32]).collect();
+/// let base = RandomCutForestBuilder::<f32>::new(1).num_trees(10).seed(0).build().parameters();
+///
+/// let results = sensitivity(&base, &points, &[0.5, 0.99]);
+/// assert_eq!(results.len(), 4);
+/// assert_eq!(results[0].parameters.num_trees, 5);
+/// assert_eq!(results[0].quantile_shifts.len(), 2);
+///
+/// // a periodic stream is scored about the same by every configuration
+/// assert!(results.iter().all(|result| result.mean_shift.abs() < 0.1));
+/// assert!(results.iter().all(|result| result.rank_correlation > 0.8));
+/// ```
+pub fn sensitivity<T>(
+    base: &ForestParameters<T>,
+    points: &[Vec<T>],
+    quantiles: &[f64],
+) -> Vec<Sensitivity<T>>
+    where T: Float + Sum
+{
+    assert!(!points.is_empty(), "Cannot measure sensitivity without points");
+    assert!(quantiles.iter().all(|q| (0.0..=1.0).contains(q)), "Quantiles must be in [0, 1]");
+    let scores_of = |parameters: &ForestParameters<T>| -> Vec<f64> {
+        let builder = RandomCutForestBuilder::from_parameters(parameters)
+            .expect("Invalid base configuration");
+        prequential_scores(builder.build(), points).iter()
+            .map(|score| score.to_f64().unwrap())
+            .collect()
+    };
+    let base_scores = scores_of(base);
+    let base_mean = mean(&base_scores);
+    let base_quantiles = sorted_quantiles(&base_scores, quantiles);
+    let base_ranks = ranks(&base_scores);
+
+    let mut perturbed = Vec::new();
+    for &num_trees in [base.num_trees / 2, base.num_trees * 2].iter() {
+        let num_trees = num_trees.max(1);
+        let tree_sample_sizes = base.tree_sample_sizes.as_ref()
+            .map(|sizes| sizes.iter().cycle().take(num_trees).cloned().collect());
+        let tree_time_decays = base.tree_time_decays.as_ref()
+            .map(|decays| decays.iter().cycle().take(num_trees).cloned().collect());
+        perturbed.push(ForestParameters { num_trees, tree_sample_sizes, tree_time_decays, ..base.clone() });
+    }
+    for &halve in [true, false].iter() {
+        let resize = |size: usize| if halve { (size / 2).max(1) } else { size * 2 };
+        let tree_sample_sizes = base.tree_sample_sizes.as_ref()
+            .map(|sizes| sizes.iter().map(|&size| resize(size)).collect());
+        perturbed.push(ForestParameters {
+            sample_size: resize(base.sample_size),
+            tree_sample_sizes,
+            ..base.clone()
+        });
+    }
+    let has_decay = base.time_decay > 0.0 ||
+        base.tree_time_decays.iter().flatten().any(|decay| *decay > 0.0);
+    if has_decay {
+        for &factor in [0.5, 2.0].iter() {
+            let tree_time_decays = base.tree_time_decays.as_ref()
+                .map(|decays| decays.iter().map(|decay| decay * factor).collect());
+            perturbed.push(ForestParameters {
+                time_decay: base.time_decay * factor,
+                tree_time_decays,
+                ..base.clone()
+            });
+        }
+    }
+
+    perturbed.into_iter()
+        .map(|parameters| {
+            let scores = scores_of(&parameters);
+            let quantile_shifts = sorted_quantiles(&scores, quantiles).iter()
+                .zip(base_quantiles.iter())
+                .map(|(q, base_q)| q - base_q)
+                .collect();
+            Sensitivity {
+                parameters,
+                mean_shift: mean(&scores) - base_mean,
+                quantile_shifts,
+                rank_correlation: correlation(&ranks(&scores), &base_ranks),
+            }
+        })
+        .collect()
+}
+
+/// Score every point with the forest and then add it to the forest.
+fn prequential_scores<T: Float + Sum>(mut forest: RandomCutForest<T>, points: &[Vec<T>]) -> Vec<T> {
+    points.iter()
+        .map(|point| {
+            let score = forest.anomaly_score(point);
+            forest.update(point.clone());
+            score
+        })
+        .collect()
+}
+
+/// One-based ranks of the values, giving tied values their mean rank.
+fn ranks<T: Float>(values: &[T]) -> Vec<f64> {
+    let mut order: Vec<usize> = (0..values.len()).collect();
+    order.sort_by(|&i, &j| values[i].partial_cmp(&values[j]).unwrap_or(Ordering::Equal));
+
+    let mut ranks = vec![0.0; values.len()];
+    let mut start = 0;
+    while start < order.len() {
+        let mut end = start + 1;
+        while end < order.len() && values[order[end]] == values[order[start]] {
+            end += 1;
+        }
+        let mean_rank = (start + end + 1) as f64 / 2.0;
+        for &i in order[start..end].iter() {
+            ranks[i] = mean_rank;
+        }
+        start = end;
+    }
+    ranks
+}
+
+fn mean(values: &[f64]) -> f64 {
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+/// Linearly interpolated quantiles of the values, which must not be empty.
+fn sorted_quantiles(values: &[f64], quantiles: &[f64]) -> Vec<f64> {
+    let mut sorted = values.to_vec();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+    quantiles.iter()
+        .map(|q| {
+            let position = q * (sorted.len() - 1) as f64;
+            let (lower, upper) = (position.floor() as usize, position.ceil() as usize);
+            let fraction = position - lower as f64;
+            sorted[lower] + fraction * (sorted[upper] - sorted[lower])
+        })
+        .collect()
+}
+
+/// Pearson correlation of two vectors. NaN if either is constant.
+fn correlation(x: &[f64], y: &[f64]) -> f64 {
+    let (mean_x, mean_y) = (mean(x), mean(y));
+    let mut covariance = 0.0;
+    let (mut variance_x, mut variance_y) = (0.0, 0.0);
+    for (a, b) in x.iter().zip(y.iter()) {
+        covariance += (a - mean_x) * (b - mean_y);
+        variance_x += (a - mean_x) * (a - mean_x);
+        variance_y += (b - mean_y) * (b - mean_y);
+    }
+    covariance / (variance_x * variance_y).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roc_auc_ties() {
+        assert_eq!(roc_auc(&[1.0, 1.0], &[true, false]), 0.5);
+        assert_eq!(roc_auc(&[0.0, 1.0, 2.0], &[true, false, false]), 0.0);
+        assert!(roc_auc(&[0.0, 1.0], &[true, true]).is_nan());
+    }
+
+    #[test]
+    fn patience() {
+        let points: Vec<Vec<f32>> = (0..10).map(|i| vec![i as f32]).collect();
+        let base = RandomCutForestBuilder::<f32>::new(1).seed(0).build().parameters();
+        let grid = ParameterGrid {
+            num_trees: vec![1, 2, 3, 4],
+            sample_sizes: vec![4],
+            time_decays: vec![0.0, 0.1],
+        };
+        assert_eq!(grid.candidates(&base).len(), 8);
+
+        // a constant objective never improves after the first candidate
+        let results = tune(grid.candidates(&base), &points, |_| 1.0, Some(3));
+        assert_eq!(results.len(), 4);
+
+        // the objective receives one score per point
+        let results = tune(grid.candidates(&base), &points, |scores| scores.len() as f64, None);
+        assert_eq!(results.len(), 8);
+        assert!(results.iter().all(|(_, value)| *value == 10.0));
+
+        // invalid candidates are skipped
+        let mut candidates = grid.candidates(&base);
+        candidates[0].sample_size = 0;
+        let results = tune(candidates, &points, |_| 1.0, None);
+        assert_eq!(results.len(), 7);
+    }
+
+    #[test]
+    fn sensitivity_to_parameters() {
+        assert_eq!(sorted_quantiles(&[3.0, 1.0, 2.0], &[0.0, 0.25, 1.0]), vec![1.0, 1.5, 3.0]);
+        assert!((correlation(&[1.0, 2.0, 3.0], &[6.0, 4.0, 2.0]) + 1.0).abs() < 1e-12);
+
+        // points spread over the unit square and a few far outliers
+        let mut points: Vec<Vec<f64>> = (0..400)
+            .map(|i| vec![((i * 37) % 101) as f64 / 101.0, ((i * 59) % 103) as f64 / 103.0])
+            .collect();
+        for i in (100..400).step_by(50) {
+            points[i] = vec![10.0, 10.0];
+        }
+        let base = RandomCutForestBuilder::<f64>::new(2)
+            .num_trees(20)
+            .sample_size(64)
+            .time_decay(0.001)
+            .seed(3)
+            .build()
+            .parameters();
+
+        let results = sensitivity(&base, &points, &[0.5, 0.9]);
+        assert_eq!(results.len(), 6);
+        assert_eq!(results[1].parameters.num_trees, 40);
+        assert_eq!(results[2].parameters.sample_size, 32);
+        assert_eq!(results[5].parameters.time_decay, 0.002);
+
+        // scores are robust to these perturbations on this stream
+        for result in results.iter() {
+            assert_eq!(result.quantile_shifts.len(), 2);
+            assert!(result.rank_correlation > 0.5, "{:?}", result);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "without points")]
+    fn sensitivity_without_points() {
+        let base = RandomCutForestBuilder::<f32>::new(1).seed(0).build().parameters();
+        sensitivity(&base, &[], &[0.5]);
+    }
+}
@@ -1,11 +1,16 @@
 extern crate num_traits;
 use num_traits::{Float, One, Zero};
 
+use std::fmt;
 use std::iter::Sum;
+use std::sync::Arc;
 
 use crate::visitor::Visitor;
 use crate::tree::{BoundingBox, Internal, Leaf, Tree};
 
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
 
 /// A visitor on nodes used to compute an anomaly score.
 ///
@@ -39,6 +44,10 @@ use crate::tree::{BoundingBox, Internal, Leaf, Tree};
 ///
 /// Once the traversal reaches the root node of the tree, the anomaly score can
 /// be obtained by calling[`get_result()`](Self::get_result).
+///
+/// The functions used to initialize, update, and normalize the score are
+/// given by a [`ScoreFunction`]. By default, the standard RCF anomaly score
+/// is computed.
 pub struct AnomalyScoreVisitor<'a, T> {
     // A tree on which an anomaly score will be computed
     tree: &'a Tree<T>,
@@ -55,7 +64,228 @@ pub struct AnomalyScoreVisitor<'a, T> {
 
     // Similar to point_inside_box but for each coordinate, allowing
     // short-cutting of certain computations
-    coordinate_inside_box: Vec<bool>
+    coordinate_inside_box: Vec<bool>,
+
+    // The family of functions defining the score
+    score_function: &'a ScoreFunction,
+
+    // Dimensions treated as missing: they neither separate the point to score
+    // from a bounding box nor distinguish it from a leaf point
+    masked_dimensions: Option<&'a [bool]>,
+}
+
+/// Named presets of the functions used by [`AnomalyScoreVisitor`].
+///
+/// An anomaly score is defined by four functions: the score of a point that is
+/// equal to the leaf point ("seen"), the score of a point separated from a
+/// node ("unseen"), a damping factor for duplicated points, and a normalizer
+/// applied to the final score. Each preset fixes a choice of these functions.
+///
+/// * `Standard` - the anomaly score of the original RCF paper. Separation at
+///   a shallow depth gives a large score. The score is normalized by
+///   `log2(mass + 1)` of the tree.
+/// * `Displacement` - the expected number of points displaced when the query
+///   point is inserted, normalized by the mass of the tree. Separation from
+///   a heavy node gives a large score.
+/// * `ExpectedInverseDepth` - the expected inverse depth, `1 / (depth + 1)`,
+///   at which the query point is separated, without damping or
+///   normalization. This is close to an isolation forest score.
+/// * `Custom` - functions given by a [`CustomScoreFunction`].
+///
+/// # Examples
+///
+/// ```
+/// use random_cut_forest::{RandomCutForest, RandomCutForestBuilder};
+/// use random_cut_forest::visitor::ScoreFunction;
+///
+/// let forest: RandomCutForest<f32> = RandomCutForestBuilder::new(2)
+///     .score_function(ScoreFunction::Displacement)
+///     .build();
+/// assert_eq!(*forest.score_function(), ScoreFunction::Displacement);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ScoreFunction {
+    #[default]
+    Standard,
+    Displacement,
+    ExpectedInverseDepth,
+    // closures cannot be serialized: serializing a custom score function
+    // fails
+    #[cfg_attr(feature = "serde", serde(skip))]
+    Custom(CustomScoreFunction),
+}
+
+/// A function of two counts used by a [`CustomScoreFunction`].
+pub type ScoreClosure = Arc<dyn Fn(usize, usize) -> f64 + Send + Sync>;
+
+/// User-defined functions of an anomaly score, see [`ScoreFunction`].
+///
+/// The functions are closures, so they can capture state such as weights or
+/// lookup tables. They are called with integer depths and masses and return
+/// `f64`, which is converted to the float type of the forest:
+///
+/// * `score_seen(depth, mass)` - the score of a query point equal to a leaf
+///   point with the given mass at the given depth
+/// * `score_unseen(depth, mass)` - the score of a query point separated from
+///   a node with the given mass at the given depth
+/// * `damp(leaf_mass, tree_mass)` - the damping factor applied to the score
+///   of a query point equal to a leaf point, one by default
+/// * `normalizer(tree_mass, _)` - the factor applied to the final score of a
+///   tree, one by default
+///
+/// Two custom score functions are equal if they share the same closures.
+///
+/// # Examples
+///
+/// ```
+/// use random_cut_forest::{RandomCutForest, RandomCutForestBuilder};
+/// use random_cut_forest::visitor::{CustomScoreFunction, ScoreFunction};
+///
+/// // a score that decays geometrically with the separation depth
+/// let decay: f64 = 0.8;
+/// let custom = CustomScoreFunction::new(
+///     move |depth, _| decay.powi(depth as i32 + 1),
+///     move |depth, _| decay.powi(depth as i32),
+/// ).with_normalizer(|tree_mass, _| (tree_mass as f64).ln());
+///
+/// let mut forest: RandomCutForest<f32> = RandomCutForestBuilder::new(1)
+///     .score_function(ScoreFunction::Custom(custom))
+///     .seed(0)
+///     .build();
+/// for i in 0..500 {
+///     forest.update(vec![(i % 10) as f32]);
+/// }
+/// assert!(forest.anomaly_score(&vec![100.0]) > forest.anomaly_score(&vec![5.0]));
+/// ```
+#[derive(Clone)]
+pub struct CustomScoreFunction {
+    score_seen: ScoreClosure,
+    score_unseen: ScoreClosure,
+    damp: Option<ScoreClosure>,
+    normalizer: Option<ScoreClosure>,
+}
+
+impl CustomScoreFunction {
+
+    /// Create a score function from the scores of seen and unseen points,
+    /// without damping or normalization.
+    pub fn new<S, U>(score_seen: S, score_unseen: U) -> Self
+        where S: Fn(usize, usize) -> f64 + Send + Sync + 'static,
+              U: Fn(usize, usize) -> f64 + Send + Sync + 'static
+    {
+        CustomScoreFunction {
+            score_seen: Arc::new(score_seen),
+            score_unseen: Arc::new(score_unseen),
+            damp: None,
+            normalizer: None,
+        }
+    }
+
+    /// Set the damping factor, a function of the leaf mass and tree mass.
+    pub fn with_damp<F>(mut self, damp: F) -> Self
+        where F: Fn(usize, usize) -> f64 + Send + Sync + 'static
+    {
+        self.damp = Some(Arc::new(damp));
+        self
+    }
+
+    /// Set the normalizing factor, a function of the tree mass. The second
+    /// argument is always zero.
+    pub fn with_normalizer<F>(mut self, normalizer: F) -> Self
+        where F: Fn(usize, usize) -> f64 + Send + Sync + 'static
+    {
+        self.normalizer = Some(Arc::new(normalizer));
+        self
+    }
+}
+
+impl fmt::Debug for CustomScoreFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CustomScoreFunction")
+            .field("damp", &self.damp.is_some())
+            .field("normalizer", &self.normalizer.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+impl PartialEq for CustomScoreFunction {
+    fn eq(&self, other: &Self) -> bool {
+        let same = |a: &Option<ScoreClosure>, b: &Option<ScoreClosure>| match (a, b) {
+            (Some(a), Some(b)) => Arc::ptr_eq(a, b),
+            (None, None) => true,
+            _ => false,
+        };
+        Arc::ptr_eq(&self.score_seen, &other.score_seen) &&
+            Arc::ptr_eq(&self.score_unseen, &other.score_unseen) &&
+            same(&self.damp, &other.damp) &&
+            same(&self.normalizer, &other.normalizer)
+    }
+}
+
+/// Call a closure of a custom score function with a depth or mass given as
+/// a float.
+#[inline(always)]
+fn call<T: Float>(closure: &ScoreClosure, a: usize, b: usize) -> T {
+    T::from(closure(a, b)).unwrap_or_else(T::nan)
+}
+
+impl ScoreFunction {
+
+    /// Returns the score of a query point equal to a leaf point with the
+    /// given mass at the given depth.
+    pub fn score_seen<T: Float>(&self, depth: T, mass: u32) -> T {
+        match self {
+            ScoreFunction::Standard => score_seen(depth, mass),
+            ScoreFunction::Displacement => {
+                let one: T = One::one();
+                one / (T::from(mass).unwrap() + one)
+            },
+            ScoreFunction::ExpectedInverseDepth => score_unseen(depth),
+            ScoreFunction::Custom(custom) => call(&custom.score_seen, to_count(depth), mass as usize),
+        }
+    }
+
+    /// Returns the score of a query point separated from a node with the
+    /// given mass at the given depth.
+    pub fn score_unseen<T: Float>(&self, depth: T, mass: u32) -> T {
+        match self {
+            ScoreFunction::Standard => score_unseen(depth),
+            ScoreFunction::Displacement => T::from(mass).unwrap(),
+            ScoreFunction::ExpectedInverseDepth => score_unseen(depth),
+            ScoreFunction::Custom(custom) => call(&custom.score_unseen, to_count(depth), mass as usize),
+        }
+    }
+
+    /// Returns the damping factor applied when the query point is equal to a
+    /// leaf point.
+    pub fn damp<T: Float>(&self, leaf_mass: u32, tree_mass: u32) -> T {
+        match self {
+            ScoreFunction::Standard => damp(leaf_mass, tree_mass),
+            ScoreFunction::Displacement => damp(leaf_mass, tree_mass),
+            ScoreFunction::ExpectedInverseDepth => One::one(),
+            ScoreFunction::Custom(custom) => match &custom.damp {
+                Some(damp) => call(damp, leaf_mass as usize, tree_mass as usize),
+                None => One::one(),
+            },
+        }
+    }
+
+    /// Returns the normalized score of a tree with the given mass.
+    pub fn normalize<T: Float>(&self, score: T, tree_mass: u32) -> T {
+        match self {
+            ScoreFunction::Standard => normalize_score(score, tree_mass),
+            ScoreFunction::Displacement => {
+                let one: T = One::one();
+                score / (T::from(tree_mass).unwrap() + one)
+            },
+            ScoreFunction::ExpectedInverseDepth => score,
+            ScoreFunction::Custom(custom) => match &custom.normalizer {
+                Some(normalizer) => score * call(normalizer, tree_mass as usize, 0),
+                None => score,
+            },
+        }
+    }
 }
 
 impl<'a, T> AnomalyScoreVisitor<'a, T> where
@@ -63,20 +293,49 @@ impl<'a, T> AnomalyScoreVisitor<'a, T> where
 {
     /// Initialize an anomaly score visitor with a tree and a point to score.
     ///
-    /// The anomaly score of this visitor is initialized to zero.
+    /// The anomaly score of this visitor is initialized to zero and the
+    /// standard score function is used.
     pub fn new(
         tree: &'a Tree<T>,
         point_to_score: &'a Vec<T>,
+    ) -> AnomalyScoreVisitor<'a, T> {
+        AnomalyScoreVisitor::new_with_score_function(
+            tree, point_to_score, &ScoreFunction::Standard)
+    }
+
+    /// Initialize an anomaly score visitor using the given score function.
+    pub fn new_with_score_function(
+        tree: &'a Tree<T>,
+        point_to_score: &'a Vec<T>,
+        score_function: &'a ScoreFunction,
     ) -> AnomalyScoreVisitor<'a, T> {
         AnomalyScoreVisitor {
             tree: tree,
             point_to_score: point_to_score,
             anomaly_score: Zero::zero(),
             point_inside_box: false,
-            coordinate_inside_box: vec![false; point_to_score.len()]
+            coordinate_inside_box: vec![false; point_to_score.len()],
+            score_function,
+            masked_dimensions: None,
         }
     }
 
+    /// Treat the masked dimensions of the point to score as missing.
+    ///
+    /// Dimension `i` is masked if `masked_dimensions[i]` is true. Masked
+    /// coordinates are ignored when computing separation probabilities and
+    /// when comparing the point to score with leaf points.
+    pub fn with_masked_dimensions(mut self, masked_dimensions: &'a [bool]) -> Self {
+        self.masked_dimensions = Some(masked_dimensions);
+        self
+    }
+
+    /// Returns true if the given dimension is masked.
+    #[inline(always)]
+    fn is_masked(&self, dimension: usize) -> bool {
+        self.masked_dimensions.is_some_and(|masked| masked[dimension])
+    }
+
     /// Returns the probability that the point to score and the input bounding
     /// box are separated by a random cut.
     fn separation_probability(&mut self, bounding_box: &BoundingBox<T>) -> T {
@@ -86,6 +345,8 @@ impl<'a, T> AnomalyScoreVisitor<'a, T> where
         let max_values = bounding_box.max_values();
 
         for i in 0..bounding_box.dimensions() {
+            if self.is_masked(i) { continue; }
+
             let mut min_value = min_values[i];
             let mut max_value = max_values[i];
             let old_range = max_value - min_value;
@@ -113,6 +374,8 @@ impl<'a, T> AnomalyScoreVisitor<'a, T> where
         }
 
         if new_range_sum <= Zero::zero() {
+            // only masked dimensions have a positive range
+            if self.masked_dimensions.is_some() { return Zero::zero(); }
             panic!("Sum of new range of the shadow box is smaller than zero.");
         }
 
@@ -133,12 +396,16 @@ impl<'a, T> Visitor<T> for AnomalyScoreVisitor<'a, T> where
     fn accept_leaf(&mut self, leaf: &Leaf, depth: T) {
         let point_store = self.tree.borrow_point_store();
         let point = point_store.get(leaf.point()).unwrap();
-        if *self.point_to_score == *point {
+        let is_same_point = match self.masked_dimensions {
+            Some(_) => (0..point.len()).all(|i| self.is_masked(i) || self.point_to_score[i] == point[i]),
+            None => *self.point_to_score == *point,
+        };
+        if is_same_point {
             self.point_inside_box = true;
-            self.anomaly_score = damp::<T>(leaf.mass(), self.tree.mass()) *
-                score_seen(depth, leaf.mass());
+            self.anomaly_score = self.score_function.damp::<T>(leaf.mass(), self.tree.mass()) *
+                self.score_function.score_seen(depth, leaf.mass());
         } else {
-            self.anomaly_score = score_unseen(depth);
+            self.anomaly_score = self.score_function.score_unseen(depth, leaf.mass());
         }
     }
 
@@ -158,7 +425,7 @@ impl<'a, T> Visitor<T> for AnomalyScoreVisitor<'a, T> where
         }
 
         let one: T = One::one();
-        self.anomaly_score = separation_probability * score_unseen(depth) +
+        self.anomaly_score = separation_probability * self.score_function.score_unseen(depth, node.mass()) +
             (one - separation_probability) * self.anomaly_score;
     }
 
@@ -168,10 +435,16 @@ impl<'a, T> Visitor<T> for AnomalyScoreVisitor<'a, T> where
     /// returning. This is so that the resulting anomaly score is independent
     /// of the number of samples in the tree.
     fn get_result(&self) -> T {
-        normalize_score(self.anomaly_score, self.tree.mass())
+        self.score_function.normalize(self.anomaly_score, self.tree.mass())
     }
 }
 
+/// Depth of a node given as a float, as a count.
+#[inline(always)]
+fn to_count<T: Float>(depth: T) -> usize {
+    depth.to_usize().unwrap_or(0)
+}
+
 #[inline(always)]
 fn score_seen<T>(depth: T, mass: u32) -> T
     where T: Float + One
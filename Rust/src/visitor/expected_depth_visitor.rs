@@ -0,0 +1,232 @@
+extern crate num_traits;
+use num_traits::{Float, One, Zero};
+
+use std::iter::Sum;
+
+use crate::visitor::Visitor;
+use crate::tree::{BoundingBox, Internal, Leaf, Tree};
+
+
+/// A visitor on nodes used to compute the expected depth of a point.
+///
+/// The expected depth is the depth at which the point would end up if it
+/// were inserted in the tree: in the style of an isolation forest, outliers
+/// are isolated close to the root and get a small depth. The traversal
+/// starts at the leaf reached by the point, at depth `D`. If the leaf holds
+/// the point itself, with mass `n`, the depth is `D + c(n)`, where `c` is
+/// the [`average_path_length`] of the duplicates; otherwise the point would
+/// be split from the leaf, at depth `D + 1`. At each internal node on the
+/// way up, at depth `d`, a random cut separates the point from the node's
+/// bounding box with some probability `p`, in which case it would be
+/// inserted at depth `d + 1`, so the depth becomes
+/// `p * (d + 1) + (1 - p) * depth`.
+///
+/// This walks the same path and uses the same separation probabilities as
+/// [`AnomalyScoreVisitor`](super::AnomalyScoreVisitor), so depth scores and
+/// RCF scores can be compared on the same trees.
+///
+/// # Examples
+///
+/// ```
+/// use random_cut_forest::Tree;
+/// use random_cut_forest::visitor::ExpectedDepthVisitor;
+///
+/// let mut tree: Tree<f32> = Tree::new();
+/// tree.add_point(vec![0.0]);
+/// tree.add_point(vec![1.0]);
+///
+/// // a cut of [0, 3] separates the point from [0, 1] with probability 2/3,
+/// // leaving it at depth 1, and otherwise it is split from the leaf of 1.0
+/// // at depth 2
+/// let point = vec![3.0];
+/// let mut visitor = ExpectedDepthVisitor::new(&tree, &point);
+/// let depth = tree.traverse(&point, &mut visitor);
+/// assert!((depth - 4.0 / 3.0).abs() < 1e-6);
+/// ```
+pub struct ExpectedDepthVisitor<'a, T> {
+    // A tree in which the depth is computed
+    tree: &'a Tree<T>,
+
+    // Input point whose depth is computed
+    point: &'a Vec<T>,
+
+    // The expected depth computed during the visitor process
+    depth: T,
+
+    // Once the point lies inside a bounding box, it does in every ancestor
+    // and the depth does not change anymore
+    point_inside_box: bool,
+}
+
+impl<'a, T> ExpectedDepthVisitor<'a, T>
+    where T: Float + Sum
+{
+    /// Initialize an expected depth visitor with a tree and a point.
+    pub fn new(tree: &'a Tree<T>, point: &'a Vec<T>) -> Self {
+        ExpectedDepthVisitor {
+            tree,
+            point,
+            depth: Zero::zero(),
+            point_inside_box: false,
+        }
+    }
+
+    /// Returns the probability that a random cut of the bounding box
+    /// extended to the point separates the point from the box.
+    fn separation_probability(&self, bounding_box: &BoundingBox<T>) -> T {
+        let mut gap_sum: T = Zero::zero();
+        for ((&min, &max), &x) in bounding_box.min_values().iter()
+            .zip(bounding_box.max_values().iter())
+            .zip(self.point.iter())
+        {
+            gap_sum = gap_sum + Float::max(min - x, Zero::zero()) + Float::max(x - max, Zero::zero());
+        }
+        match gap_sum > Zero::zero() {
+            true => gap_sum / (bounding_box.range_sum() + gap_sum),
+            false => Zero::zero(),
+        }
+    }
+}
+
+impl<'a, T> Visitor<T> for ExpectedDepthVisitor<'a, T>
+    where T: Float + Sum
+{
+    type Output = T;
+
+    /// Initialize the depth from the leaf reached by the point.
+    fn accept_leaf(&mut self, leaf: &Leaf, depth: T) {
+        let point_store = self.tree.borrow_point_store();
+        if point_store.get(leaf.point()) == Some(self.point) {
+            self.point_inside_box = true;
+            let duplicates = T::from(average_path_length(leaf.mass() as usize)).unwrap();
+            self.depth = depth + duplicates;
+        } else {
+            self.depth = depth + One::one();
+        }
+    }
+
+    /// Update the depth with the probability that the point is separated
+    /// from the bounding box of an internal node.
+    fn accept(&mut self, node: &Internal<T>, depth: T) {
+        if self.point_inside_box { return; }
+
+        let probability = self.separation_probability(node.bounding_box());
+        if probability <= Zero::zero() {
+            self.point_inside_box = true;
+            return;
+        }
+        let one: T = One::one();
+        self.depth = probability * (depth + one) + (one - probability) * self.depth;
+    }
+
+    /// Returns the expected depth of the point.
+    fn get_result(&self) -> T {
+        self.depth
+    }
+}
+
+/// Returns the average depth of a point in a random binary tree of `n`
+/// points, the normalizer `c(n)` of isolation forest scores.
+///
+/// An isolation forest scores a point with expected depth `h` in trees of
+/// `n` points as `2^(-h / c(n))`: close to one for outliers and below one
+/// half for inliers.
+///
+/// # Examples
+///
+/// ```
+/// use random_cut_forest::visitor::average_path_length;
+///
+/// assert_eq!(average_path_length(1), 0.0);
+/// assert_eq!(average_path_length(2), 1.0);
+/// assert!((average_path_length(256) - 10.2448).abs() < 1e-3);
+/// ```
+pub fn average_path_length(n: usize) -> f64 {
+    match n {
+        0 | 1 => 0.0,
+        2 => 1.0,
+        n => {
+            let n = n as f64;
+            let harmonic = (n - 1.0).ln() + 0.577_215_664_901_532_9;
+            2.0 * harmonic - 2.0 * (n - 1.0) / n
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha8Rng;
+    use rand_distr::StandardNormal;
+
+    use crate::{RandomCutForest, RandomCutForestBuilder};
+
+    fn expected_depth(tree: &Tree<f64>, point: &Vec<f64>) -> f64 {
+        let mut visitor = ExpectedDepthVisitor::new(tree, point);
+        tree.traverse(point, &mut visitor)
+    }
+
+    #[test]
+    fn duplicate_leaf() {
+        let mut tree: Tree<f64> = Tree::new();
+        tree.seed(0);
+        tree.add_point(vec![0.0, 0.0]);
+        for _ in 0..3 {
+            tree.add_point(vec![1.0, 1.0]);
+        }
+
+        // the leaf of the duplicates is a child of the root, at depth 1, and
+        // the depth does not change above it
+        let depth = expected_depth(&tree, &vec![1.0, 1.0]);
+        assert!((depth - (1.0 + average_path_length(3))).abs() < 1e-12);
+        let depth = expected_depth(&tree, &vec![0.0, 0.0]);
+        assert!((depth - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn point_inside_root_box() {
+        let mut tree: Tree<f64> = Tree::new();
+        tree.seed(1);
+        tree.add_point(vec![0.0, 0.0]);
+        tree.add_point(vec![2.0, 2.0]);
+
+        // no cut of the root box separates the point, which is split from
+        // its leaf at depth 2 whichever leaf it reaches
+        assert_eq!(expected_depth(&tree, &vec![1.0, 1.0]), 2.0);
+        assert_eq!(expected_depth(&tree, &vec![2.0, 0.0]), 2.0);
+
+        // outside the root box, the depth is between the root and the leaf
+        let depth = expected_depth(&tree, &vec![3.0, 1.0]);
+        assert!(depth > 1.0 && depth < 2.0);
+    }
+
+    #[test]
+    fn forest_expected_depth() {
+        let mut forest: RandomCutForest<f64> = RandomCutForestBuilder::new(3)
+            .num_trees(20)
+            .sample_size(64)
+            .seed(5)
+            .build();
+        let mut rng = ChaCha8Rng::seed_from_u64(5);
+        let mut points: Vec<Vec<f64>> = Vec::new();
+        for _ in 0..300 {
+            let point: Vec<f64> = (0..3).map(|_| rng.sample(StandardNormal)).collect();
+            forest.update(point.clone());
+            points.push(point);
+        }
+
+        // the forest depth is the mean of the depths in its trees
+        let mut queries = points.iter().rev().take(10).cloned().collect::<Vec<Vec<f64>>>();
+        queries.push(vec![10.0, 0.0, -10.0]);
+        for query in queries.iter() {
+            let depths: Vec<f64> = forest.trees().iter()
+                .map(|tree| expected_depth(tree.tree(), query))
+                .collect();
+            let mean = depths.iter().sum::<f64>() / depths.len() as f64;
+            assert!((forest.expected_depth(query) - mean).abs() < 1e-9);
+        }
+        assert!(forest.expected_depth(&queries[10]) < forest.expected_depth(&queries[0]));
+    }
+}
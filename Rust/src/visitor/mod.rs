@@ -5,4 +5,10 @@ mod visitor;
 pub use visitor::Visitor;
 
 mod anomaly_score_visitor;
-pub use anomaly_score_visitor::AnomalyScoreVisitor;
\ No newline at end of file
+pub use anomaly_score_visitor::{AnomalyScoreVisitor, CustomScoreFunction, ScoreClosure, ScoreFunction};
+
+mod expected_depth_visitor;
+pub use expected_depth_visitor::{average_path_length, ExpectedDepthVisitor};
+
+mod near_neighbor_visitor;
+pub use near_neighbor_visitor::NearNeighborVisitor;
\ No newline at end of file
@@ -0,0 +1,122 @@
+extern crate num_traits;
+use num_traits::Float;
+
+use std::collections::HashSet;
+use std::iter::Sum;
+
+use crate::visitor::Visitor;
+use crate::tree::{Internal, Leaf, Node, Tree};
+
+
+/// A visitor on nodes used to collect candidate near neighbors of a point.
+///
+/// The traversal of a tree starts at the leaf reached by the point and
+/// climbs towards the root. Points in the subtrees passed on the way are
+/// the points that the random cuts of the tree found hardest to separate
+/// from the query point. The visitor collects the points of the smallest
+/// subtree on that path that holds at least `num_candidates` distinct
+/// points, or every point of the tree if it holds fewer.
+///
+/// The candidates are not sorted; combining the candidates of several trees
+/// and ranking them by distance is left to the caller, as in
+/// [`RandomCutForest::nearest_neighbors`](crate::RandomCutForest::nearest_neighbors).
+///
+/// # Examples
+///
+/// ```
+/// use random_cut_forest::Tree;
+/// use random_cut_forest::visitor::NearNeighborVisitor;
+///
+/// let mut tree: Tree<f32> = Tree::new();
+/// for i in 0..10 {
+///     tree.add_point(vec![i as f32]);
+/// }
+///
+/// let point = vec![0.2];
+/// let mut visitor = NearNeighborVisitor::new(&tree, 3);
+/// let candidates = tree.traverse(&point, &mut visitor);
+/// assert!(candidates.len() >= 3);
+/// assert!(candidates.contains(&vec![0.0]));
+/// ```
+pub struct NearNeighborVisitor<'a, T> {
+    // A tree in which candidates are collected
+    tree: &'a Tree<T>,
+
+    // Number of distinct points after which no more subtrees are collected
+    num_candidates: usize,
+
+    // Keys of the collected points in the point store of the tree
+    point_keys: Vec<usize>,
+    seen: HashSet<usize>,
+
+    // Node keys of the leaves under the next node to collect
+    pending: Vec<usize>,
+}
+
+impl<'a, T> NearNeighborVisitor<'a, T>
+    where T: Float + Sum
+{
+    /// Initialize a visitor collecting at least `num_candidates` points of
+    /// the tree, when the tree has that many.
+    pub fn new(tree: &'a Tree<T>, num_candidates: usize) -> Self {
+        NearNeighborVisitor {
+            tree,
+            num_candidates,
+            point_keys: Vec::new(),
+            seen: HashSet::new(),
+            pending: Vec::new(),
+        }
+    }
+
+    /// Add the points of the leaves of the subtree at the given node.
+    fn collect_subtree(&mut self, node_key: usize) {
+        self.pending.push(node_key);
+        while let Some(node_key) = self.pending.pop() {
+            match self.tree.get_node(node_key) {
+                Node::Leaf(leaf) => self.add_point(leaf.point()),
+                Node::Internal(internal) => {
+                    self.pending.push(internal.right());
+                    self.pending.push(internal.left());
+                }
+            }
+        }
+    }
+
+    fn add_point(&mut self, point_key: usize) {
+        if self.seen.insert(point_key) {
+            self.point_keys.push(point_key);
+        }
+    }
+}
+
+impl<'a, T> Visitor<T> for NearNeighborVisitor<'a, T>
+    where T: Float + Sum
+{
+    type Output = Vec<Vec<T>>;
+
+    /// Start the candidates with the point of the leaf reached by the query.
+    fn accept_leaf(&mut self, leaf: &Leaf, _depth: T) {
+        self.add_point(leaf.point());
+    }
+
+    /// Add the points of the subtree of an internal node, until enough
+    /// candidates are collected.
+    ///
+    /// The subtree of the previous node on the path is collected again but
+    /// its points are only added once.
+    fn accept(&mut self, node: &Internal<T>, _depth: T) {
+        if self.point_keys.len() >= self.num_candidates {
+            return;
+        }
+        self.collect_subtree(node.left());
+        self.collect_subtree(node.right());
+    }
+
+    /// Returns the collected points, in the units of the tree.
+    fn get_result(&self) -> Self::Output {
+        let point_store = self.tree.borrow_point_store();
+        self.point_keys.iter()
+            .map(|&point_key| point_store.get(point_key).unwrap().clone())
+            .collect()
+    }
+}
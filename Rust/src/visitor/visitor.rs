@@ -35,4 +35,74 @@ pub trait Visitor<T> {
     /// Called at the end of traversal on a given tree. The type of the output
     /// is given by the associated type of this trait, [`Output`](Self::Output).
     fn get_result(&self) -> Self::Output;
-}
\ No newline at end of file
+}
+
+/// A visitor that may be absent, so that visitors can be enabled at run time
+/// in a fused traversal. An absent visitor ignores the nodes and returns
+/// `None`.
+impl<T, V> Visitor<T> for Option<V>
+    where V: Visitor<T>
+{
+    type Output = Option<V::Output>;
+
+    fn accept_leaf(&mut self, node: &Leaf, depth: T) {
+        if let Some(visitor) = self {
+            visitor.accept_leaf(node, depth);
+        }
+    }
+
+    fn accept(&mut self, node: &Internal<T>, depth: T) {
+        if let Some(visitor) = self {
+            visitor.accept(node, depth);
+        }
+    }
+
+    fn get_result(&self) -> Self::Output {
+        self.as_ref().map(|visitor| visitor.get_result())
+    }
+}
+
+/// A pair of visitors run in a single traversal, in order, returning both
+/// results.
+///
+/// Visitors that need the same point walk the same path in a tree, so
+/// running them together finds the path and fetches its nodes once. Pairs
+/// nest to fuse more visitors.
+///
+/// # Examples
+///
+/// ```
+/// use random_cut_forest::Tree;
+/// use random_cut_forest::visitor::{AnomalyScoreVisitor, ExpectedDepthVisitor};
+///
+/// let mut tree: Tree<f32> = Tree::new();
+/// for i in 0..10 {
+///     tree.add_point(vec![i as f32]);
+/// }
+///
+/// let point = vec![20.0];
+/// let mut visitors = (AnomalyScoreVisitor::new(&tree, &point), ExpectedDepthVisitor::new(&tree, &point));
+/// let (score, depth) = tree.traverse(&point, &mut visitors);
+///
+/// assert_eq!(score, tree.traverse(&point, &mut AnomalyScoreVisitor::new(&tree, &point)));
+/// assert_eq!(depth, tree.traverse(&point, &mut ExpectedDepthVisitor::new(&tree, &point)));
+/// ```
+impl<T, A, B> Visitor<T> for (A, B)
+    where T: Copy, A: Visitor<T>, B: Visitor<T>
+{
+    type Output = (A::Output, B::Output);
+
+    fn accept_leaf(&mut self, node: &Leaf, depth: T) {
+        self.0.accept_leaf(node, depth);
+        self.1.accept_leaf(node, depth);
+    }
+
+    fn accept(&mut self, node: &Internal<T>, depth: T) {
+        self.0.accept(node, depth);
+        self.1.accept(node, depth);
+    }
+
+    fn get_result(&self) -> Self::Output {
+        (self.0.get_result(), self.1.get_result())
+    }
+}
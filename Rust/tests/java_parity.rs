@@ -0,0 +1,149 @@
+//! Score parity between this crate and the Java implementation of RCF.
+//!
+//! Enable with `cargo test --features java-parity`. Each fixture is a CSV file
+//! in the directory given by the `RCF_JAVA_PARITY_FIXTURES` environment
+//! variable (default: `tests/fixtures/java_parity`). The first line of a
+//! fixture holds the forest parameters and the tolerances as `key=value`
+//! pairs separated by commas,
+//!
+//! ```text
+//! dimension=3,num_trees=50,sample_size=256,time_decay=0.0,output_after=256,tolerance=0.1,max_divergent_fraction=0.05
+//! ```
+//!
+//! and every following line holds one data point followed by the anomaly
+//! score that the Java `RandomCutForest` reported for it when scoring before
+//! updating, the same streaming order used here.
+//!
+//! Random cuts are not reproducible across the two implementations, so scores
+//! are compared statistically. A point diverges when its scores differ by
+//! more than `tolerance`. A fixture passes when the mean absolute difference
+//! is at most `tolerance` and at most `max_divergent_fraction` of the scored
+//! points diverge. Failures list the divergent points. No fixtures are
+//! checked in: the test is skipped, with a message, when the fixture
+//! directory does not exist.
+#![cfg(feature = "java-parity")]
+
+use random_cut_forest::{RandomCutForest, RandomCutForestBuilder};
+
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+struct Fixture {
+    parameters: HashMap<String, String>,
+    points: Vec<Vec<f64>>,
+    java_scores: Vec<f64>,
+}
+
+impl Fixture {
+    fn read(path: &Path) -> Fixture {
+        let contents = fs::read_to_string(path)
+            .unwrap_or_else(|e| panic!("cannot read {}: {}", path.display(), e));
+        let mut lines = contents.lines().filter(|line| !line.trim().is_empty());
+
+        let header = lines.next()
+            .unwrap_or_else(|| panic!("{}: missing parameter line", path.display()));
+        let parameters: HashMap<String, String> = header.split(',')
+            .map(|pair| {
+                let mut kv = pair.splitn(2, '=');
+                let key = kv.next().unwrap().trim().to_string();
+                let value = kv.next()
+                    .unwrap_or_else(|| panic!("{}: malformed parameter '{}'", path.display(), pair))
+                    .trim().to_string();
+                (key, value)
+            })
+            .collect();
+
+        let mut points = Vec::new();
+        let mut java_scores = Vec::new();
+        for (i, line) in lines.enumerate() {
+            let mut values: Vec<f64> = line.split(',')
+                .map(|v| v.trim().parse::<f64>()
+                    .unwrap_or_else(|e| panic!("{}:{}: {}", path.display(), i + 2, e)))
+                .collect();
+            java_scores.push(values.pop().unwrap());
+            points.push(values);
+        }
+
+        Fixture { parameters, points, java_scores }
+    }
+
+    fn get<V: std::str::FromStr>(&self, key: &str, default: Option<V>) -> V {
+        match self.parameters.get(key) {
+            Some(value) => value.parse::<V>()
+                .unwrap_or_else(|_| panic!("invalid value for parameter '{}'", key)),
+            None => default.unwrap_or_else(|| panic!("missing parameter '{}'", key)),
+        }
+    }
+}
+
+/// Returns the fixtures in the fixture directory, or `None` if the directory
+/// does not exist.
+fn fixture_paths() -> Option<Vec<PathBuf>> {
+    let directory = env::var("RCF_JAVA_PARITY_FIXTURES")
+        .unwrap_or_else(|_| concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/java_parity").to_string());
+    if !Path::new(&directory).is_dir() {
+        eprintln!("skipping Java parity: fixture directory {} does not exist", directory);
+        return None;
+    }
+    let mut paths: Vec<PathBuf> = fs::read_dir(&directory)
+        .unwrap_or_else(|e| panic!("cannot read {}: {}", directory, e))
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|e| e == "csv"))
+        .collect();
+    paths.sort();
+    assert!(!paths.is_empty(), "no Java parity fixtures found in {}", directory);
+    Some(paths)
+}
+
+#[test]
+fn java_score_parity() {
+    let paths = match fixture_paths() {
+        Some(paths) => paths,
+        None => return,
+    };
+    for path in paths {
+        let fixture = Fixture::read(&path);
+        let dimension: usize = fixture.get("dimension", None);
+        let tolerance: f64 = fixture.get("tolerance", None);
+        let max_divergent_fraction: f64 = fixture.get("max_divergent_fraction", Some(0.05));
+        let output_after: usize = fixture.get("output_after", Some(0));
+
+        let mut forest: RandomCutForest<f64> = RandomCutForestBuilder::new(dimension)
+            .num_trees(fixture.get("num_trees", Some(50)))
+            .sample_size(fixture.get("sample_size", Some(256)))
+            .time_decay(fixture.get("time_decay", Some(0.0)))
+            .output_after(output_after)
+            .build();
+
+        let mut total_difference = 0.0;
+        let mut divergent = Vec::new();
+        let mut scored = 0;
+        for (i, (point, java_score)) in fixture.points.iter().zip(fixture.java_scores.iter()).enumerate() {
+            let score = forest.anomaly_score(point);
+            forest.update(point.clone());
+            if i < output_after {
+                continue;
+            }
+
+            let difference = (score - java_score).abs();
+            total_difference += difference;
+            scored += 1;
+            if difference > tolerance {
+                divergent.push(format!("point {}: rust = {:.6}, java = {:.6}", i, score, java_score));
+            }
+        }
+
+        assert!(scored > 0, "{}: no scored points", path.display());
+        let mean_difference = total_difference / scored as f64;
+        let divergent_fraction = divergent.len() as f64 / scored as f64;
+        assert!(mean_difference <= tolerance,
+            "{}: mean score difference {} exceeds tolerance {}, divergent = {}/{}:\n{}",
+            path.display(), mean_difference, tolerance, divergent.len(), scored, divergent.join("\n"));
+        assert!(divergent_fraction <= max_divergent_fraction,
+            "{}: {} of points diverge, more than {}, divergent = {}/{}:\n{}",
+            path.display(), divergent_fraction, max_divergent_fraction, divergent.len(), scored,
+            divergent.join("\n"));
+    }
+}
@@ -0,0 +1,64 @@
+//! Compare the memory accounting of a forest with the memory actually
+//! allocated for it, measured with a counting global allocator.
+extern crate random_cut_forest;
+use random_cut_forest::{RandomCutForest, RandomCutForestBuilder};
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+struct CountingAllocator;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATED.fetch_add(layout.size(), Ordering::SeqCst);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        ALLOCATED.fetch_sub(layout.size(), Ordering::SeqCst);
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATED.fetch_add(new_size, Ordering::SeqCst);
+        ALLOCATED.fetch_sub(layout.size(), Ordering::SeqCst);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+#[test]
+fn size_in_bytes_matches_allocations() {
+    let dimension = 8;
+    let points: Vec<Vec<f32>> = (0..5000)
+        .map(|i| (0..dimension).map(|j| ((i * 31 + j * 17) % 101) as f32).collect())
+        .collect();
+
+    for &(num_trees, sample_size) in [(10, 64), (50, 256)].iter() {
+        let before = ALLOCATED.load(Ordering::SeqCst);
+        let mut forest: RandomCutForest<f32> = RandomCutForestBuilder::new(dimension)
+            .num_trees(num_trees)
+            .sample_size(sample_size)
+            .time_decay(0.001)
+            .track_covariance(0.01)
+            .raw_input_history(32)
+            .track_latency(true)
+            .seed(7)
+            .build();
+        for point in points.iter() {
+            forest.update(point.clone());
+            forest.anomaly_score(point);
+        }
+
+        // the forest itself lives on the stack
+        let allocated = ALLOCATED.load(Ordering::SeqCst) - before +
+            std::mem::size_of::<RandomCutForest<f32>>();
+        let accounted = forest.size_in_bytes();
+        let error = (accounted as f64 - allocated as f64).abs() / allocated as f64;
+        assert!(error < 0.01, "accounted {} bytes, allocated {} bytes", accounted, allocated);
+    }
+}
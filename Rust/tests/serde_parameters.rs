@@ -0,0 +1,66 @@
+//! Round trip of forest parameters through JSON.
+//!
+//! Enable with `cargo test --features serde`.
+#![cfg(feature = "serde")]
+
+extern crate random_cut_forest;
+use random_cut_forest::{
+    ForestParameters, InputTransform, NonFinitePolicy, PreFilter, RandomCutForest, RandomCutForestBuilder,
+    UpdateRateLimit};
+use random_cut_forest::tree::{CutStrategy, TieBreaking};
+use random_cut_forest::visitor::{CustomScoreFunction, ScoreFunction};
+
+use std::time::Duration;
+
+#[test]
+fn parameters_round_trip() {
+    let forest: RandomCutForest<f64> = RandomCutForestBuilder::new(2)
+        .num_trees(10)
+        .sample_size(64)
+        .tree_time_decays(vec![0.001, 0.001, 0.001, 0.001, 0.001, 0.01, 0.01, 0.01, 0.01, 0.01])
+        .output_after(32)
+        .max_score(4.0)
+        .score_function(ScoreFunction::Displacement)
+        .cut_strategy(CutStrategy::DimensionSubsample(1))
+        .tie_breaking(TieBreaking::Random)
+        .non_finite_policy(NonFinitePolicy::Clamp(1e6))
+        .dimension_bounds(vec![-1.0, 0.0], vec![1.0, 100.0])
+        .input_transforms(vec![InputTransform::Log1p, InputTransform::Identity])
+        .pre_filter(PreFilter { z_bound: 3.0, score_threshold: 1.5, false_negative_budget: 0.01 })
+        .update_rate_limit(UpdateRateLimit::new(100, Duration::from_millis(250)))
+        .seed(7)
+        .build();
+    let parameters = forest.parameters();
+
+    let json = serde_json::to_string(&parameters).unwrap();
+    let decoded: ForestParameters<f64> = serde_json::from_str(&json).unwrap();
+    assert_eq!(decoded, parameters);
+    assert_eq!(decoded.fingerprint(), parameters.fingerprint());
+
+    let copy: RandomCutForest<f64> = RandomCutForestBuilder::from_parameters(&decoded).unwrap().build();
+    assert_eq!(copy.parameters(), parameters);
+}
+
+#[test]
+fn functions_are_not_serialized() {
+    let forest: RandomCutForest<f32> = RandomCutForestBuilder::new(1)
+        .score_transform(|score: f32, _| score.ln_1p())
+        .build();
+    let json = serde_json::to_string(&forest.parameters()).unwrap();
+    let decoded: ForestParameters<f32> = serde_json::from_str(&json).unwrap();
+    assert!(decoded.score_transform.is_none());
+
+    let forest: RandomCutForest<f32> = RandomCutForestBuilder::new(1)
+        .score_function(ScoreFunction::Custom(CustomScoreFunction::new(|_, _| 1.0, |_, _| 1.0)))
+        .build();
+    assert!(serde_json::to_string(&forest.parameters()).is_err());
+}
+
+#[test]
+fn invalid_parameters_are_rejected() {
+    let mut parameters = RandomCutForestBuilder::<f32>::new(2).build().parameters();
+    parameters.cut_strategy = CutStrategy::DimensionSubsample(0);
+    let json = serde_json::to_string(&parameters).unwrap();
+    let decoded: ForestParameters<f32> = serde_json::from_str(&json).unwrap();
+    assert!(RandomCutForestBuilder::from_parameters(&decoded).is_err());
+}